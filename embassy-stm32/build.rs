@@ -87,6 +87,11 @@ fn main() {
         }
     };
 
+    cfgs.set(
+        "flash_dual_bank",
+        memory.iter().any(|region| region.name.starts_with("BANK_2")),
+    );
+
     // ========
     // Generate singletons
 
@@ -544,7 +549,9 @@ fn main() {
             }
         }
 
-        fn gen_clock(&mut self, peripheral: &str, name: &str) -> TokenStream {
+        /// Generate the frequency of the clock `name`. It panics if the clock is not running, or is
+        /// `None` with `fallible`.
+        fn gen_clock(&mut self, peripheral: &str, name: &str, fallible: bool) -> TokenStream {
             let name = name.to_ascii_lowercase();
             let (name, frac) = Self::parse_mul_div(&name);
             let clock_name = format_ident!("{}", name);
@@ -559,6 +566,9 @@ fn main() {
                 let val = frac.denom;
                 muldiv.extend(quote!(/ #val));
             }
+            if fallible {
+                return quote!(unsafe { crate::rcc::get_freqs().#clock_name.to_hertz().map(|f| f #muldiv) });
+            }
             quote!(unsafe {
                 unwrap!(
                     crate::rcc::get_freqs().#clock_name.to_hertz(),
@@ -571,7 +581,7 @@ fn main() {
             })
         }
 
-        fn gen_mux(&mut self, peripheral: &str, mux: &PeripheralRccRegister) -> TokenStream {
+        fn gen_mux(&mut self, peripheral: &str, mux: &PeripheralRccRegister, fallible: bool) -> TokenStream {
             let ir = &self.rcc_registers.ir;
             let fieldset_name = mux.register.to_ascii_lowercase();
             let fieldset = ir
@@ -596,15 +606,25 @@ fn main() {
             for v in enumm.variants.iter().filter(|v| v.name != "DISABLE") {
                 let variant_name = format_ident!("{}", v.name);
                 let expr = if let Some(mux) = self.chained_muxes.get(&v.name) {
-                    self.gen_mux(peripheral, mux)
+                    self.gen_mux(peripheral, mux, fallible)
                 } else {
-                    self.gen_clock(peripheral, v.name)
+                    self.gen_clock(peripheral, v.name, fallible)
                 };
                 match_arms.extend(quote! {
                     crate::pac::rcc::vals::#enum_name::#variant_name => #expr,
                 });
             }
 
+            if fallible {
+                return quote! {
+                    match crate::pac::RCC.#fieldset_name().read().#field_name() {
+                        #match_arms
+                        #[allow(unreachable_patterns)]
+                        _ => None,
+                    }
+                };
+            }
+
             quote! {
                 match crate::pac::RCC.#fieldset_name().read().#field_name() {
                     #match_arms
@@ -680,11 +700,15 @@ fn main() {
             };
 
             let clock_frequency = match &rcc.kernel_clock {
-                PeripheralRccKernelClock::Mux(mux) => clock_gen.gen_mux(p.name, mux),
-                PeripheralRccKernelClock::Clock(clock) => clock_gen.gen_clock(p.name, clock),
+                PeripheralRccKernelClock::Mux(mux) => clock_gen.gen_mux(p.name, mux, false),
+                PeripheralRccKernelClock::Clock(clock) => clock_gen.gen_clock(p.name, clock, false),
+            };
+            let try_clock_frequency = match &rcc.kernel_clock {
+                PeripheralRccKernelClock::Mux(mux) => clock_gen.gen_mux(p.name, mux, true),
+                PeripheralRccKernelClock::Clock(clock) => clock_gen.gen_clock(p.name, clock, true),
             };
 
-            let bus_clock_frequency = clock_gen.gen_clock(p.name, &rcc.bus_clock);
+            let bus_clock_frequency = clock_gen.gen_clock(p.name, &rcc.bus_clock, false);
 
             // A refcount leak can result if the same field is shared by peripherals with different stop modes
             // This condition should be checked in stm32-data
@@ -699,6 +723,9 @@ fn main() {
                     fn frequency() -> crate::time::Hertz {
                         #clock_frequency
                     }
+                    fn try_frequency() -> Option<crate::time::Hertz> {
+                        #try_clock_frequency
+                    }
                     fn bus_frequency() -> crate::time::Hertz {
                         #bus_clock_frequency
                     }
@@ -770,7 +797,8 @@ fn main() {
         pub mod mux {
             #(pub use crate::pac::rcc::vals::#enum_names as #enum_names; )*
 
-            #[derive(Clone, Copy)]
+            #[derive(Clone, Copy, Debug)]
+            #[cfg_attr(feature = "defmt", derive(defmt::Format))]
             #[non_exhaustive]
             pub struct ClockMux {
                 #( #struct_fields, )*
@@ -875,6 +903,7 @@ fn main() {
     #[rustfmt::skip]
     let signals: HashMap<_, _> = [
         // (kind, signal) => trait
+        (("crs", "SYNC"), quote!(crate::crs::SyncPin)),
         (("ucpd", "CC1"), quote!(crate::ucpd::Cc1Pin)),
         (("ucpd", "CC2"), quote!(crate::ucpd::Cc2Pin)),
         (("usart", "TX"), quote!(crate::usart::TxPin)),
@@ -1301,6 +1330,7 @@ fn main() {
         (("dac", "OUT2"), quote!(crate::dac::DacPin<Ch2>)),
     ].into();
 
+    let mut lcd_pins = HashSet::new();
     for p in METADATA.peripherals {
         if let Some(regs) = &p.registers {
             for pin in p.pins {
@@ -1433,6 +1463,39 @@ fn main() {
                     }
                 }
 
+                if regs.kind == "comp"
+                    && (chip_name.starts_with("stm32l4")
+                        || (chip_name.starts_with("stm32wb") && !chip_name.starts_with("stm32wba")))
+                {
+                    let peri = format_ident!("{}", p.name);
+                    let pin_name = format_ident!("{}", pin.pin);
+                    // Signals are named INP<n> / INM<n>, the number being the input selection.
+                    if let Some(Ok(ch)) = pin.signal.strip_prefix("INP").map(|s| s.parse::<u8>()) {
+                        g.extend(quote! {
+                            impl_comp_inp_pin!( #peri, #pin_name, #ch);
+                        })
+                    } else if let Some(Ok(ch)) = pin.signal.strip_prefix("INM").map(|s| s.parse::<u8>()) {
+                        g.extend(quote! {
+                            impl_comp_inm_pin!( #peri, #pin_name, #ch);
+                        })
+                    }
+                }
+
+                if regs.kind == "lcd"
+                    && chip_name.starts_with("stm32l1")
+                    && (pin.signal.starts_with("SEG") || pin.signal.starts_with("COM"))
+                {
+                    // A pin can carry several segment and common signals, implement the trait once.
+                    if lcd_pins.insert(pin.pin) {
+                        let peri = format_ident!("{}", p.name);
+                        let pin_name = format_ident!("{}", pin.pin);
+                        let af = pin.af.unwrap_or(0);
+                        g.extend(quote! {
+                            pin_trait_impl!(crate::lcd::SegComPin, #peri, #pin_name, #af);
+                        })
+                    }
+                }
+
                 if regs.kind == "spdifrx" {
                     let peri = format_ident!("{}", p.name);
                     let pin_name = format_ident!("{}", pin.pin);
@@ -1447,6 +1510,22 @@ fn main() {
         }
     }
 
+    // IRTIM has no registers of its own, so its IR_OUT pins are matched by signal name.
+    if chip_name.starts_with("stm32wb") && !chip_name.starts_with("stm32wba") {
+        for p in METADATA.peripherals {
+            for pin in p.pins {
+                if matches!((p.name, pin.signal), ("IR" | "IRTIM", "OUT") | (_, "IR_OUT")) {
+                    let pin_name = format_ident!("{}", pin.pin);
+                    let af = pin.af.unwrap_or(0);
+
+                    g.extend(quote! {
+                        impl_irtim_out_pin!(#pin_name, #af);
+                    })
+                }
+            }
+        }
+    }
+
     // ========
     // Generate dma_trait_impl!
 
@@ -1491,6 +1570,12 @@ fn main() {
     ]
     .into();
 
+    // The `aes` driver only supports the AES peripheral of these families.
+    if chip_name.starts_with("stm32l4") || (chip_name.starts_with("stm32wb") && !chip_name.starts_with("stm32wba")) {
+        signals.insert(("aes", "IN"), quote!(crate::aes::DmaIn));
+        signals.insert(("aes", "OUT"), quote!(crate::aes::DmaOut));
+    }
+
     if chip_name.starts_with("stm32u5") {
         signals.insert(("adc", "ADC4"), quote!(crate::adc::RxDma4));
     } else {
@@ -1504,6 +1589,15 @@ fn main() {
         }
     }
 
+    // Type-erased channels can only implement the request traits when a single DMAMUX serves every
+    // channel. With several (DMAMUX1 and DMAMUX2 on H7), the request number of a peripheral
+    // depends on the DMAMUX of the channel, which is only known at runtime.
+    let single_dmamux = {
+        let muxes: HashSet<_> = METADATA.dma_channels.iter().map(|ch| ch.dmamux).collect();
+        muxes.len() == 1 && !muxes.contains(&None)
+    };
+    cfgs.set("dma_single_dmamux", single_dmamux);
+
     for p in METADATA.peripherals {
         if let Some(regs) = &p.registers {
             // FIXME: stm32u5a crash on Cordic driver
@@ -1539,6 +1633,15 @@ fn main() {
                         unreachable!();
                     };
 
+                    // With a single DMAMUX any channel can serve any request with the same request
+                    // number, so type-erased channels (e.g. from a `dma::pool::DmaPool`) can be used too.
+                    if single_dmamux && dupe.insert((ch.signal, "AnyChannel".to_string())) {
+                        let request = ch.request.unwrap() as u8;
+                        g.extend(quote! {
+                            dma_trait_impl_any!(#tr, #peri, #request);
+                        });
+                    }
+
                     for channel in channels {
                         // Some chips have multiple request numbers for the same (peri, signal, channel) combos.
                         // Ignore the dupes, picking the first one. Otherwise this causes conflicting trait impls
@@ -1922,6 +2025,30 @@ fn main() {
         pub const WRITE_SIZE: usize = #write_size;
     ));
 
+    // ========
+    // Generate SRAM constants for the memory shared with CPU2 on STM32WB
+
+    if is_wb_with_cpu2(&METADATA.name) {
+        let ram_region = |name: &str| {
+            let region = memory
+                .iter()
+                .find(|m| m.kind == MemoryRegionKind::Ram && m.name.replace('_', "") == name)
+                .unwrap();
+            (region.address as usize, region.size as usize)
+        };
+        let (sram1_base, _) = ram_region("SRAM1");
+        let (sram2a_base, sram2a_size) = ram_region("SRAM2A");
+        let (sram2b_base, sram2b_size) = ram_region("SRAM2B");
+
+        g.extend(quote!(
+            pub const SRAM1_BASE: usize = #sram1_base;
+            pub const SRAM2A_BASE: usize = #sram2a_base;
+            pub const SRAM2A_SIZE: usize = #sram2a_size;
+            pub const SRAM2B_BASE: usize = #sram2b_base;
+            pub const SRAM2B_SIZE: usize = #sram2b_size;
+        ));
+    }
+
     // ========
     // Generate EEPROM constants
 
@@ -2155,9 +2282,29 @@ fn rustfmt(path: impl AsRef<Path>) {
 fn gen_memory_x(memory: &[MemoryRegion], out_dir: &Path) {
     let mut memory_x = String::new();
 
-    let flash = get_memory_range(memory, MemoryRegionKind::Flash);
+    let mut flash = get_memory_range(memory, MemoryRegionKind::Flash);
     let ram = get_memory_range(memory, MemoryRegionKind::Ram);
 
+    let wb_cpu2 = is_wb_with_cpu2(&METADATA.name);
+    if wb_cpu2 {
+        // The wireless stack is installed at the end of the flash, from the SFSA page on.
+        if let Some(sfsa) = wb_option_byte("SFSA") {
+            let page_size = memory
+                .iter()
+                .find(|m| m.kind == MemoryRegionKind::Flash && m.name.starts_with("BANK_1"))
+                .and_then(|m| m.settings.as_ref())
+                .unwrap()
+                .erase_size;
+            let length = sfsa * page_size;
+            assert!(
+                length > 0 && length <= flash.1,
+                "EMBASSY_STM32_WB_SFSA is outside of the flash"
+            );
+            flash.1 = length;
+            flash.2 = format!("{} below the wireless stack", flash.2);
+        }
+    }
+
     write!(memory_x, "MEMORY\n{{\n").unwrap();
     writeln!(
         memory_x,
@@ -2175,11 +2322,82 @@ fn gen_memory_x(memory: &[MemoryRegion], out_dir: &Path) {
         ram.2
     )
     .unwrap();
+
+    // SRAM2a/SRAM2b are shared with CPU2. Only their start, below the secure part, is usable.
+    let mut shared = Vec::new();
+    if wb_cpu2 {
+        for (name, option_byte) in [("SRAM2A", "SBRSA"), ("SRAM2B", "SNBRSA")] {
+            let Some(region) = memory
+                .iter()
+                .find(|m| m.kind == MemoryRegionKind::Ram && m.name.replace('_', "") == name)
+            else {
+                continue;
+            };
+            let length = match wb_option_byte(option_byte) {
+                Some(secure_start) => {
+                    assert!(
+                        secure_start * 1024 <= region.size,
+                        "EMBASSY_STM32_WB_{} is outside of {}",
+                        option_byte,
+                        name
+                    );
+                    secure_start * 1024
+                }
+                None => region.size,
+            };
+            writeln!(
+                memory_x,
+                "    {:<5} : ORIGIN = 0x{:08x}, LENGTH = {:>4}K /* shared with CPU2 */",
+                name,
+                region.address,
+                length / 1024
+            )
+            .unwrap();
+            shared.push(name);
+        }
+    }
+
     write!(memory_x, "}}").unwrap();
 
+    if !shared.is_empty() {
+        write!(memory_x, "\n\nSECTIONS\n{{\n").unwrap();
+        for name in &shared {
+            let section = name.to_ascii_lowercase();
+            writeln!(
+                memory_x,
+                "    .{section} (NOLOAD) : ALIGN(4) {{ *(.{section} .{section}.*); . = ALIGN(4); }} > {name}"
+            )
+            .unwrap();
+        }
+        write!(memory_x, "}}\nINSERT AFTER .bss;\n").unwrap();
+    }
+
     std::fs::write(out_dir.join("memory.x"), memory_x.as_bytes()).unwrap();
 }
 
+/// STM32WB parts with the radio coprocessor (CPU2), as opposed to the WBA and WB0 series.
+fn is_wb_with_cpu2(chip: &str) -> bool {
+    chip.starts_with("STM32WB") && !chip.starts_with("STM32WBA") && !chip.starts_with("STM32WB0")
+}
+
+/// Value of a CPU2 secure option byte, given at build time as `EMBASSY_STM32_WB_<name>`.
+///
+/// The values are the ones shown by STM32CubeProgrammer after installing the wireless stack, in
+/// hexadecimal with a `0x` prefix or in decimal.
+fn wb_option_byte(name: &str) -> Option<u32> {
+    let var = format!("EMBASSY_STM32_WB_{name}");
+    println!("cargo:rerun-if-env-changed={var}");
+    let value = env::var(&var).ok()?;
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    match parsed {
+        Ok(value) => Some(value),
+        Err(_) => panic!("Invalid value for env var {var}: {value}"),
+    }
+}
+
 fn get_memory_range(memory: &[MemoryRegion], kind: MemoryRegionKind) -> (u32, u32, String) {
     let mut mems: Vec<_> = memory.iter().filter(|m| m.kind == kind && m.size != 0).collect();
     mems.sort_by_key(|m| m.address);
@@ -2211,12 +2429,8 @@ fn get_memory_range(memory: &[MemoryRegion], kind: MemoryRegionKind) -> (u32, u3
 }
 
 fn mem_filter(chip: &str, region: &str) -> bool {
-    // in STM32WB, SRAM2a/SRAM2b are reserved for the radio core.
-    if chip.starts_with("STM32WB")
-        && !chip.starts_with("STM32WBA")
-        && !chip.starts_with("STM32WB0")
-        && region.starts_with("SRAM2")
-    {
+    // in STM32WB, SRAM2a/SRAM2b are shared with the radio core.
+    if is_wb_with_cpu2(chip) && region.starts_with("SRAM2") {
         return false;
     }
 
@@ -30,6 +30,8 @@ use embassy_sync::waitqueue::AtomicWaker;
 #[path = "u5_adc4.rs"]
 pub mod adc4;
 
+#[allow(unused)]
+pub(crate) use crate::delay::blocking_delay_us;
 pub use crate::pac::adc::vals;
 #[cfg(not(any(adc_f1, adc_f3_v2)))]
 pub use crate::pac::adc::vals::Res as Resolution;
@@ -46,6 +48,8 @@ pub struct Adc<'d, T: Instance> {
     adc: crate::Peri<'d, T>,
     #[cfg(not(any(adc_f3_v2, adc_f3_v1_1)))]
     sample_time: SampleTime,
+    #[cfg(any(adc_v3, adc_g0, adc_h5, adc_h7rs, adc_u0))]
+    suspended: bool,
 }
 
 #[cfg(any(adc_f1, adc_f3, adc_v1, adc_l0, adc_f3_v1_1))]
@@ -80,20 +84,6 @@ pub(crate) trait SealedAdcChannel<T> {
     fn channel(&self) -> u8;
 }
 
-/// Performs a busy-wait delay for a specified number of microseconds.
-#[allow(unused)]
-pub(crate) fn blocking_delay_us(us: u32) {
-    #[cfg(feature = "time")]
-    embassy_time::block_for(embassy_time::Duration::from_micros(us as u64));
-    #[cfg(not(feature = "time"))]
-    {
-        let freq = unsafe { crate::rcc::get_freqs() }.sys.to_hertz().unwrap().0 as u64;
-        let us = us as u64;
-        let cycles = freq * us / 1_000_000;
-        cortex_m::asm::delay(cycles as u32);
-    }
-}
-
 /// ADC instance.
 #[cfg(not(any(
     adc_f1,
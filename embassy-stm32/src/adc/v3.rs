@@ -1,5 +1,9 @@
 use cfg_if::cfg_if;
 use pac::adc::vals::Dmacfg;
+#[cfg(adc_v3)]
+pub use pac::adc::vals::Exten;
+#[cfg(adc_v3)]
+use pac::adc::vals::{Adcaldif, Difsel};
 
 use super::{
     blocking_delay_us, Adc, AdcChannel, AnyAdcChannel, Instance, Resolution, RxDma, SampleTime, SealedAdcChannel,
@@ -12,6 +16,30 @@ pub const VREF_DEFAULT_MV: u32 = 3300;
 /// VREF voltage used for factory calibration of VREFINTCAL register.
 pub const VREF_CALIB_MV: u32 = 3000;
 
+/// Factory calibration values stored in system memory.
+///
+/// Only L4 and WB families are described here; other families using this driver keep
+/// these values at different addresses, or do not provide all of them.
+#[cfg(any(stm32l4, stm32wb))]
+mod cal {
+    /// Raw VREFINT reading at 30 °C with VDDA = [`super::VREF_CALIB_MV`].
+    pub(super) const VREFINT: *const u16 = 0x1FFF_75AA as *const u16;
+    /// Raw temperature sensor reading at [`TS_CAL1_TEMP`] with VDDA = [`super::VREF_CALIB_MV`].
+    pub(super) const TS_CAL1: *const u16 = 0x1FFF_75A8 as *const u16;
+    /// Raw temperature sensor reading at [`TS_CAL2_TEMP`] with VDDA = [`super::VREF_CALIB_MV`].
+    pub(super) const TS_CAL2: *const u16 = 0x1FFF_75CA as *const u16;
+
+    pub(super) const TS_CAL1_TEMP: i32 = 30;
+    #[cfg(any(stm32l4r5, stm32l4r7, stm32l4r9, stm32l4s5, stm32l4s7, stm32l4s9))]
+    pub(super) const TS_CAL2_TEMP: i32 = 110;
+    #[cfg(not(any(stm32l4r5, stm32l4r7, stm32l4r9, stm32l4s5, stm32l4s7, stm32l4s9)))]
+    pub(super) const TS_CAL2_TEMP: i32 = 130;
+
+    pub(super) fn read(addr: *const u16) -> u16 {
+        unsafe { core::ptr::read_volatile(addr) }
+    }
+}
+
 pub struct VrefInt;
 impl<T: Instance> AdcChannel<T> for VrefInt {}
 impl<T: Instance> SealedAdcChannel<T> for VrefInt {
@@ -31,7 +59,39 @@ impl<T: Instance> SealedAdcChannel<T> for VrefInt {
     }
 }
 
+#[cfg(any(stm32l4, stm32wb))]
+impl VrefInt {
+    /// Factory calibration value (`VREFINT_CAL`), sampled with VDDA = [`VREF_CALIB_MV`].
+    pub fn calibrated_value(&self) -> u16 {
+        cal::read(cal::VREFINT)
+    }
+
+    /// Compute the actual VDDA supply voltage in millivolts from a 12-bit VREFINT sample.
+    pub fn to_vdda_mv(&self, sample: u16) -> u32 {
+        if sample == 0 {
+            return 0;
+        }
+        VREF_CALIB_MV * self.calibrated_value() as u32 / sample as u32
+    }
+}
+
 pub struct Temperature;
+
+#[cfg(any(stm32l4, stm32wb))]
+impl Temperature {
+    /// Convert a 12-bit sample of the temperature sensor to degrees Celsius.
+    ///
+    /// `vdda_mv` is the supply voltage the sample was taken at, see [`VrefInt::to_vdda_mv`].
+    /// The factory calibration points (`TS_CAL1`/`TS_CAL2`) are scaled accordingly.
+    pub fn to_celsius(&self, sample: u16, vdda_mv: u32) -> f32 {
+        let cal1 = cal::read(cal::TS_CAL1) as f32;
+        let cal2 = cal::read(cal::TS_CAL2) as f32;
+        let sample = sample as f32 * vdda_mv as f32 / VREF_CALIB_MV as f32;
+
+        (cal::TS_CAL2_TEMP - cal::TS_CAL1_TEMP) as f32 / (cal2 - cal1) * (sample - cal1) + cal::TS_CAL1_TEMP as f32
+    }
+}
+
 impl<T: Instance> AdcChannel<T> for Temperature {}
 impl<T: Instance> SealedAdcChannel<T> for Temperature {
     fn channel(&self) -> u8 {
@@ -51,6 +111,17 @@ impl<T: Instance> SealedAdcChannel<T> for Temperature {
 }
 
 pub struct Vbat;
+
+#[cfg(any(stm32l4, stm32wb))]
+impl Vbat {
+    /// Convert a 12-bit sample of the VBAT channel to millivolts.
+    ///
+    /// VBAT is internally divided by 3 before reaching the ADC, this is compensated here.
+    pub fn to_mv(&self, sample: u16, vdda_mv: u32) -> u32 {
+        sample as u32 * vdda_mv * 3 / 4095
+    }
+}
+
 impl<T: Instance> AdcChannel<T> for Vbat {}
 impl<T: Instance> SealedAdcChannel<T> for Vbat {
     fn channel(&self) -> u8 {
@@ -93,14 +164,42 @@ cfg_if! {
     }
 }
 
+/// ADC calibration factors, see [`Adc::calibration_factors`].
+#[cfg(adc_v3)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CalibrationFactors {
+    /// Calibration factor for single-ended inputs.
+    pub single_ended: u8,
+    /// Calibration factor for differential inputs.
+    pub differential: u8,
+}
+
+/// Convert a raw differential conversion result to a signed value.
+///
+/// The ADC returns differential results in offset binary, with mid-scale meaning
+/// `VINP == VINN`. The result is in the range `-mid..=mid - 1`, where `mid` is half the number of
+/// codes of `resolution` (e.g. `-2048..=2047` at 12 bits).
+#[cfg(adc_v3)]
+pub fn differential_to_signed(sample: u16, resolution: Resolution) -> i32 {
+    let mid = (super::resolution_to_max_count(resolution) + 1) / 2;
+    sample as i32 - mid as i32
+}
+
+/// Hardware trigger of the injected channel group.
+#[cfg(adc_v3)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InjectedTrigger {
+    /// Trigger source (`JEXTSEL`).
+    pub source: crate::trigger::AdcInjectedTrigger,
+    /// Trigger polarity. [`Exten::DISABLED`] makes the group software-triggered.
+    pub edge: Exten,
+}
+
 impl<'d, T: Instance> Adc<'d, T> {
     pub fn new(adc: Peri<'d, T>) -> Self {
         rcc::enable_and_reset::<T>();
-        T::regs().cr().modify(|reg| {
-            #[cfg(not(any(adc_g0, adc_u0)))]
-            reg.set_deeppwd(false);
-            reg.set_advregen(true);
-        });
 
         // If this is false then each ADC_CHSELR bit enables an input channel.
         #[cfg(any(adc_g0, adc_u0))]
@@ -108,9 +207,62 @@ impl<'d, T: Instance> Adc<'d, T> {
             reg.set_chselrmod(false);
         });
 
+        let mut s = Self {
+            adc,
+            sample_time: SampleTime::from_bits(0),
+            suspended: false,
+        };
+        s.calibrate();
+        s
+    }
+
+    /// Power up the internal voltage regulator, if needed.
+    fn power_up() {
+        let cr = T::regs().cr().read();
+        #[cfg(not(any(adc_g0, adc_u0)))]
+        let powered = cr.advregen() && !cr.deeppwd();
+        #[cfg(any(adc_g0, adc_u0))]
+        let powered = cr.advregen();
+        if powered {
+            return;
+        }
+
+        T::regs().cr().modify(|reg| {
+            #[cfg(not(any(adc_g0, adc_u0)))]
+            reg.set_deeppwd(false);
+            reg.set_advregen(true);
+        });
+
+        // tADCVREG_STUP: the regulator needs up to 20 us to settle before calibration.
         blocking_delay_us(20);
+    }
+
+    /// Disable the ADC, stopping any ongoing conversion.
+    fn disable() {
+        Self::cancel_conversions();
+        if T::regs().cr().read().aden() {
+            T::regs().cr().modify(|reg| reg.set_addis(true));
+            while T::regs().cr().read().aden() {
+                // spin
+            }
+        }
+    }
+
+    /// Run the ADC self-calibration.
+    ///
+    /// This is done by [`Adc::new`], but should be repeated after large changes of VDDA or
+    /// temperature. On parts with differential inputs, both the single-ended and the
+    /// differential calibration factors are updated.
+    pub fn calibrate(&mut self) {
+        // ADDIS is only allowed once both regular and injected conversions are stopped.
+        #[cfg(adc_v3)]
+        self.stop_injected();
+        Self::disable();
+        Self::power_up();
 
         T::regs().cr().modify(|reg| {
+            #[cfg(adc_v3)]
+            reg.set_adcaldif(Adcaldif::SINGLE_ENDED);
             reg.set_adcal(true);
         });
 
@@ -118,14 +270,49 @@ impl<'d, T: Instance> Adc<'d, T> {
             // spin
         }
 
+        // Differential inputs use a separate calibration factor.
+        #[cfg(adc_v3)]
+        {
+            T::regs().cr().modify(|reg| {
+                reg.set_adcaldif(Adcaldif::DIFFERENTIAL);
+                reg.set_adcal(true);
+            });
+
+            while T::regs().cr().read().adcal() {
+                // spin
+            }
+        }
+
         blocking_delay_us(1);
+    }
 
-        Self {
-            adc,
-            sample_time: SampleTime::from_bits(0),
+    /// Read the calibration factors computed by the last [`Adc::calibrate`].
+    ///
+    /// These can be stored and restored with [`Adc::set_calibration_factors`] to skip
+    /// calibration, e.g. when waking up from a low-power mode that powers the ADC down.
+    #[cfg(adc_v3)]
+    pub fn calibration_factors(&self) -> CalibrationFactors {
+        let r = T::regs().calfact().read();
+        CalibrationFactors {
+            single_ended: r.calfact_s(),
+            differential: r.calfact_d(),
         }
     }
 
+    /// Restore calibration factors previously read with [`Adc::calibration_factors`].
+    #[cfg(adc_v3)]
+    pub fn set_calibration_factors(&mut self, factors: CalibrationFactors) {
+        Self::power_up();
+
+        // CALFACT can only be written while the ADC is enabled and idle.
+        Self::cancel_conversions();
+        self.enable();
+        T::regs().calfact().write(|w| {
+            w.set_calfact_s(factors.single_ended);
+            w.set_calfact_d(factors.differential);
+        });
+    }
+
     // Enable ADC only when it is not already running.
     fn enable(&mut self) {
         // Make sure bits are off
@@ -148,6 +335,28 @@ impl<'d, T: Instance> Adc<'d, T> {
         }
     }
 
+    /// Disable the ADC and gate its clock while no conversions are needed.
+    ///
+    /// The voltage regulator stays on, so the calibration and the configuration are kept. The ADC
+    /// must be [resumed](Self::resume) before the next conversion. Does nothing if the ADC is
+    /// already suspended.
+    pub fn suspend(&mut self) {
+        if !self.suspended {
+            Self::disable();
+            self.suspended = true;
+            rcc::disable::<T>();
+        }
+    }
+
+    /// Ungate the clock and enable the ADC after [`suspend`](Self::suspend).
+    pub fn resume(&mut self) {
+        if self.suspended {
+            self.suspended = false;
+            rcc::enable::<T>();
+            self.enable();
+        }
+    }
+
     pub fn enable_vrefint(&self) -> VrefInt {
         #[cfg(not(any(adc_g0, adc_u0)))]
         T::common_regs().ccr().modify(|reg| {
@@ -205,6 +414,43 @@ impl<'d, T: Instance> Adc<'d, T> {
         Vbat {}
     }
 
+    /// Measure VDDA through VREFINT, in mV, and the die temperature, in °C.
+    ///
+    /// The ADC must use the 12-bit resolution. Sets the sampling time to
+    /// [`SampleTime::CYCLES640_5`]: both channels need at least 5 µs, which is 8 µs at the maximum
+    /// 80 MHz ADC clock.
+    #[cfg(any(stm32l4, stm32wb))]
+    pub(crate) fn blocking_read_vdda_and_temperature(&mut self) -> (u32, f32) {
+        self.set_sample_time(SampleTime::CYCLES640_5);
+
+        let mut vrefint = self.enable_vrefint();
+        let vdda_mv = vrefint.to_vdda_mv(self.blocking_read(&mut vrefint));
+        let mut temperature = self.enable_temperature();
+        let celsius = temperature.to_celsius(self.blocking_read(&mut temperature), vdda_mv);
+        (vdda_mv, celsius)
+    }
+
+    /// Disable the VBAT channel.
+    ///
+    /// The VBAT divider draws current from the battery while the channel is enabled.
+    pub fn disable_vbat(&self) {
+        cfg_if! {
+            if #[cfg(any(adc_g0, adc_u0))] {
+                T::regs().ccr().modify(|reg| {
+                    reg.set_vbaten(false);
+                });
+            } else if #[cfg(any(adc_h5, adc_h7rs))] {
+                T::common_regs().ccr().modify(|reg| {
+                    reg.set_vbaten(false);
+                });
+            } else {
+                T::common_regs().ccr().modify(|reg| {
+                    reg.set_ch18sel(false);
+                });
+            }
+        }
+    }
+
     /// Set the ADC sample time.
     pub fn set_sample_time(&mut self, sample_time: SampleTime) {
         self.sample_time = sample_time;
@@ -223,14 +469,13 @@ impl<'d, T: Instance> Adc<'d, T> {
         T::regs().cfgr1().modify(|reg| reg.set_res(resolution.into()));
     }
 
-    /*
-    /// Convert a raw sample from the `Temperature` to deg C
-    pub fn to_degrees_centigrade(sample: u16) -> f32 {
-        (130.0 - 30.0) / (VtempCal130::get().read() as f32 - VtempCal30::get().read() as f32)
-            * (sample as f32 - VtempCal30::get().read() as f32)
-            + 30.0
+    /// Get the ADC resolution.
+    pub fn resolution(&self) -> Resolution {
+        #[cfg(not(any(adc_g0, adc_u0)))]
+        return T::regs().cfgr().read().res().into();
+        #[cfg(any(adc_g0, adc_u0))]
+        return T::regs().cfgr1().read().res().into();
     }
-     */
 
     /// Perform a single conversion.
     fn convert(&mut self) -> u16 {
@@ -304,6 +549,69 @@ impl<'d, T: Instance> Adc<'d, T> {
         Self::cancel_conversions();
         self.enable();
 
+        Self::configure_sequence(sequence);
+
+        // Set continuous mode with oneshot dma.
+        // Clear overrun flag before starting transfer.
+        T::regs().isr().modify(|reg| {
+            reg.set_ovr(true);
+        });
+
+        #[cfg(not(any(adc_g0, adc_u0)))]
+        T::regs().cfgr().modify(|reg| {
+            reg.set_discen(false);
+            reg.set_cont(true);
+            reg.set_dmacfg(Dmacfg::ONE_SHOT);
+            reg.set_dmaen(true);
+        });
+        #[cfg(any(adc_g0, adc_u0))]
+        T::regs().cfgr1().modify(|reg| {
+            reg.set_discen(false);
+            reg.set_cont(true);
+            reg.set_dmacfg(Dmacfg::ONE_SHOT);
+            reg.set_dmaen(true);
+        });
+
+        let request = rx_dma.request();
+        let transfer = unsafe {
+            Transfer::new_read(
+                rx_dma,
+                request,
+                T::regs().dr().as_ptr() as *mut u16,
+                readings,
+                Default::default(),
+            )
+        };
+
+        // Start conversion
+        T::regs().cr().modify(|reg| {
+            reg.set_adstart(true);
+        });
+
+        // Wait for conversion sequence to finish.
+        transfer.await;
+
+        // Ensure conversions are finished.
+        Self::cancel_conversions();
+
+        // Reset configuration.
+        #[cfg(not(any(adc_g0, adc_u0)))]
+        T::regs().cfgr().modify(|reg| {
+            reg.set_cont(false);
+            reg.set_dmaen(false);
+        });
+        #[cfg(any(adc_g0, adc_u0))]
+        T::regs().cfgr1().modify(|reg| {
+            reg.set_cont(false);
+            reg.set_dmaen(false);
+        });
+    }
+
+    /// Program the regular sequence, including each channel's sampling time.
+    fn configure_sequence<'a>(sequence: impl ExactSizeIterator<Item = (&'a mut AnyAdcChannel<T>, SampleTime)>)
+    where
+        T: 'a,
+    {
         // Set sequence length
         #[cfg(not(any(adc_g0, adc_u0)))]
         T::regs().sqr1().modify(|w| {
@@ -356,59 +664,67 @@ impl<'d, T: Instance> Adc<'d, T> {
         T::regs().chselr().modify(|reg| {
             reg.set_chsel(channel_mask);
         });
+    }
 
-        // Set continuous mode with oneshot dma.
-        // Clear overrun flag before starting transfer.
-        T::regs().isr().modify(|reg| {
-            reg.set_ovr(true);
-        });
+    /// Read one or multiple ADC channels without DMA, each with its own sampling time.
+    ///
+    /// This allows e.g. a long sampling time for the internal temperature sensor and a short one
+    /// for low-impedance signals within the same sequence. `sequence` iterator and `readings`
+    /// must have the same length.
+    ///
+    /// Note: On G0 and U0 all channels share one sampling time and are converted in channel
+    /// number order; the sampling time of the last channel in `sequence` is used.
+    pub fn blocking_read_sequence<'a>(
+        &mut self,
+        sequence: impl ExactSizeIterator<Item = (&'a mut AnyAdcChannel<T>, SampleTime)>,
+        readings: &mut [u16],
+    ) where
+        T: 'a,
+    {
+        assert!(sequence.len() != 0, "Read sequence cannot be empty");
+        assert!(
+            sequence.len() == readings.len(),
+            "Sequence length must be equal to readings length"
+        );
+        assert!(sequence.len() <= 16, "Read sequence cannot be more than 16 in length");
 
+        Self::cancel_conversions();
+        self.enable();
+        Self::configure_sequence(sequence);
+
+        // Single conversion of the sequence, with the results read from DR by the CPU.
         #[cfg(not(any(adc_g0, adc_u0)))]
         T::regs().cfgr().modify(|reg| {
             reg.set_discen(false);
-            reg.set_cont(true);
-            reg.set_dmacfg(Dmacfg::ONE_SHOT);
-            reg.set_dmaen(true);
+            reg.set_cont(false);
+            reg.set_dmaen(false);
         });
         #[cfg(any(adc_g0, adc_u0))]
         T::regs().cfgr1().modify(|reg| {
             reg.set_discen(false);
-            reg.set_cont(true);
-            reg.set_dmacfg(Dmacfg::ONE_SHOT);
-            reg.set_dmaen(true);
+            reg.set_cont(false);
+            reg.set_dmaen(false);
         });
 
-        let request = rx_dma.request();
-        let transfer = unsafe {
-            Transfer::new_read(
-                rx_dma,
-                request,
-                T::regs().dr().as_ptr() as *mut u16,
-                readings,
-                Default::default(),
-            )
-        };
+        T::regs().isr().modify(|reg| {
+            reg.set_eoc(true);
+            reg.set_eos(true);
+            reg.set_ovr(true);
+        });
 
-        // Start conversion
         T::regs().cr().modify(|reg| {
             reg.set_adstart(true);
         });
 
-        // Wait for conversion sequence to finish.
-        transfer.await;
-
-        // Ensure conversions are finished.
-        Self::cancel_conversions();
+        for reading in readings.iter_mut() {
+            while !T::regs().isr().read().eoc() {
+                // spin
+            }
+            // Reading DR clears EOC.
+            *reading = T::regs().dr().read().0 as u16;
+        }
 
-        // Reset configuration.
-        #[cfg(not(any(adc_g0, adc_u0)))]
-        T::regs().cfgr().modify(|reg| {
-            reg.set_cont(false);
-        });
-        #[cfg(any(adc_g0, adc_u0))]
-        T::regs().cfgr1().modify(|reg| {
-            reg.set_cont(false);
-        });
+        T::regs().isr().modify(|reg| reg.set_eos(true));
     }
 
     fn configure_channel(channel: &mut impl AdcChannel<T>, sample_time: SampleTime) {
@@ -443,7 +759,14 @@ impl<'d, T: Instance> Adc<'d, T> {
         let _ = self.convert();
         let val = self.convert();
 
-        T::regs().cr().modify(|reg| reg.set_addis(true));
+        // Keep the ADC enabled while an injected sequence is armed.
+        #[cfg(adc_v3)]
+        let injected_active = T::regs().cr().read().jadstart();
+        #[cfg(not(adc_v3))]
+        let injected_active = false;
+        if !injected_active {
+            T::regs().cr().modify(|reg| reg.set_addis(true));
+        }
 
         // RM0492, RM0481, etc.
         // "This option bit must be set to 1 when ADCx_INP0 or ADCx_INN1 channel is selected."
@@ -455,19 +778,55 @@ impl<'d, T: Instance> Adc<'d, T> {
         val
     }
 
-    #[cfg(any(adc_g0, adc_u0))]
+    /// Enable or disable differential mode for a channel.
+    ///
+    /// In differential mode the channel measures `VINP[i] - VINN[i]`, where `VINN[i]` is the
+    /// input of channel `i + 1`. That channel can no longer be converted on its own.
+    /// Use [`differential_to_signed`] to interpret the results.
+    #[cfg(adc_v3)]
+    pub fn set_differential(&mut self, channel: &mut impl AdcChannel<T>, enable: bool) {
+        // DIFSEL can only be written while the ADC is disabled, and ADDIS is only allowed once
+        // both regular and injected conversions are stopped.
+        self.stop_injected();
+        Self::disable();
+
+        T::regs().difsel().modify(|w| {
+            w.set_difsel(
+                channel.channel() as usize,
+                if enable {
+                    Difsel::DIFFERENTIAL
+                } else {
+                    Difsel::SINGLE_ENDED
+                },
+            );
+        });
+    }
+
+    /// Set oversampling shift.
+    ///
+    /// The accumulated result is right-shifted by `shift` bits (0..=8).
+    #[cfg(any(adc_g0, adc_u0, adc_v3))]
     pub fn set_oversampling_shift(&mut self, shift: u8) {
         T::regs().cfgr2().modify(|reg| reg.set_ovss(shift));
     }
 
-    #[cfg(any(adc_g0, adc_u0))]
+    /// Set oversampling ratio.
+    ///
+    /// The number of accumulated samples is `2^(ratio + 1)`, i.e. `0` is 2x and `7` is 256x.
+    #[cfg(any(adc_g0, adc_u0, adc_v3))]
     pub fn set_oversampling_ratio(&mut self, ratio: u8) {
         T::regs().cfgr2().modify(|reg| reg.set_ovsr(ratio));
     }
 
-    #[cfg(any(adc_g0, adc_u0))]
+    /// Enable oversampling of regular conversions.
+    ///
+    /// For example, a ratio of 256x with a shift of 4 gives a 16-bit result from 12-bit samples.
+    #[cfg(any(adc_g0, adc_u0, adc_v3))]
     pub fn oversampling_enable(&mut self, enable: bool) {
+        #[cfg(any(adc_g0, adc_u0))]
         T::regs().cfgr2().modify(|reg| reg.set_ovse(enable));
+        #[cfg(adc_v3)]
+        T::regs().cfgr2().modify(|reg| reg.set_rovse(enable));
     }
 
     fn set_channel_sample_time(_ch: u8, sample_time: SampleTime) {
@@ -489,6 +848,92 @@ impl<'d, T: Instance> Adc<'d, T> {
         }
     }
 
+    /// Start regular conversions on a hardware trigger, or by software with `None`.
+    ///
+    /// With a trigger, [`Adc::blocking_read`] and [`Adc::read`] start converting on the next
+    /// trigger edge instead of immediately.
+    #[cfg(any(stm32l4, stm32wb))]
+    pub fn set_regular_trigger(&mut self, trigger: Option<(crate::trigger::AdcTrigger, crate::trigger::Edge)>) {
+        Self::cancel_conversions();
+        T::regs().cfgr().modify(|w| match trigger {
+            Some((source, edge)) => {
+                w.set_extsel(source as u8);
+                w.set_exten(Exten::from_bits(edge as u8));
+            }
+            None => w.set_exten(Exten::DISABLED),
+        });
+    }
+
+    /// Configure the injected channel group.
+    ///
+    /// Up to 4 channels are converted, in order, each time `trigger` fires. Each result is kept
+    /// in its own data register, so the injected group can interrupt ongoing regular conversions,
+    /// e.g. to sample motor phase currents at a fixed point of the PWM period.
+    ///
+    /// Conversions start once [`Adc::start_injected`] is called.
+    #[cfg(adc_v3)]
+    pub fn configure_injected(
+        &mut self,
+        sequence: &mut [(&mut AnyAdcChannel<T>, SampleTime)],
+        trigger: InjectedTrigger,
+    ) {
+        assert!(
+            !sequence.is_empty() && sequence.len() <= 4,
+            "Injected sequence must contain 1 to 4 channels"
+        );
+
+        self.stop_injected();
+        self.enable();
+
+        for (channel, sample_time) in sequence.iter_mut() {
+            Self::configure_channel(*channel, *sample_time);
+        }
+
+        T::regs().jsqr().write(|w| {
+            w.set_jl(sequence.len() as u8 - 1);
+            for (i, (channel, _)) in sequence.iter().enumerate() {
+                w.set_jsq(i, channel.channel());
+            }
+            w.set_jextsel(trigger.source as u8);
+            w.set_jexten(trigger.edge);
+        });
+    }
+
+    /// Arm the injected group; conversions then run on every trigger event.
+    #[cfg(adc_v3)]
+    pub fn start_injected(&mut self) {
+        T::regs().isr().modify(|w| {
+            w.set_jeoc(true);
+            w.set_jeos(true);
+        });
+        T::regs().cr().modify(|w| w.set_jadstart(true));
+    }
+
+    /// Returns `true` when a full injected sequence has been converted since the last
+    /// [`Adc::read_injected`].
+    #[cfg(adc_v3)]
+    pub fn injected_ready(&self) -> bool {
+        T::regs().isr().read().jeos()
+    }
+
+    /// Read the results of the last injected sequence and clear the end-of-sequence flag.
+    ///
+    /// Entries past the configured sequence length are unspecified.
+    #[cfg(adc_v3)]
+    pub fn read_injected(&mut self) -> [u16; 4] {
+        T::regs().isr().modify(|w| w.set_jeos(true));
+        core::array::from_fn(|i| T::regs().jdr(i).read().jdata())
+    }
+
+    /// Stop injected conversions.
+    #[cfg(adc_v3)]
+    pub fn stop_injected(&mut self) {
+        if T::regs().cr().read().jadstart() {
+            T::regs().cr().modify(|w| w.set_jadstp(true));
+            while T::regs().cr().read().jadstart() {}
+        }
+    }
+
     fn cancel_conversions() {
         if T::regs().cr().read().adstart() && !T::regs().cr().read().addis() {
             T::regs().cr().modify(|reg| {
@@ -498,3 +943,28 @@ impl<'d, T: Instance> Adc<'d, T> {
         }
     }
 }
+
+impl<'d, T: Instance> Drop for Adc<'d, T> {
+    fn drop(&mut self) {
+        if !self.suspended {
+            Self::disable();
+            rcc::disable::<T>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(adc_v3)]
+    #[test]
+    fn differential_to_signed() {
+        use super::{differential_to_signed, Resolution};
+
+        assert_eq!(differential_to_signed(0, Resolution::BITS12), -2048);
+        assert_eq!(differential_to_signed(2048, Resolution::BITS12), 0);
+        assert_eq!(differential_to_signed(4095, Resolution::BITS12), 2047);
+        assert_eq!(differential_to_signed(0, Resolution::BITS8), -128);
+        assert_eq!(differential_to_signed(255, Resolution::BITS8), 127);
+        assert_eq!(differential_to_signed(63, Resolution::BITS6), 31);
+    }
+}
@@ -0,0 +1,838 @@
+//! AES hardware accelerator (AES)
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::ptr;
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::{Peri, PeripheralType};
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::dma::{ChannelAndRequest, TransferOptions};
+use crate::interrupt::typelevel::Interrupt;
+use crate::mode::{Async, Blocking, Mode};
+use crate::{interrupt, pac, peripherals, rcc};
+
+/// AES block size, in bytes.
+pub const BLOCK_SIZE: usize = 16;
+/// GCM nonce size, in bytes.
+pub const GCM_NONCE_SIZE: usize = 12;
+
+/// Largest DMA transfer, in bytes: the most 32-bit words a DMA transfer can move, rounded down
+/// to whole blocks.
+const MAX_DMA_LEN: usize = 0xFFF0 * 4;
+
+/// AES interrupt handler.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let sr = T::regs().sr().read();
+        if sr.ccf() || sr.rderr() || sr.wrerr() {
+            T::regs().cr().modify(|w| {
+                w.set_ccfie(false);
+                w.set_errie(false);
+            });
+            T::waker().wake();
+        }
+    }
+}
+
+/// AES error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The key is neither 128 nor 256 bits long.
+    InvalidKeyLength,
+    /// The data length is not a multiple of the block size, which the chaining mode requires,
+    /// the input and output buffers have different lengths, or a CCM nonce, tag or message
+    /// length is out of range.
+    InvalidLength,
+    /// A partial block was already processed, no more data can be processed in this operation.
+    Finished,
+    /// Data registers were accessed during a computation.
+    ReadWrite,
+    /// The authentication tag doesn't match the data.
+    AuthenticationFailed,
+    /// A DMA buffer is not 4-byte aligned.
+    Unaligned,
+}
+
+/// Chaining mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChainingMode {
+    /// Electronic codebook.
+    Ecb,
+    /// Cipher block chaining, with the initialization vector.
+    Cbc([u8; BLOCK_SIZE]),
+    /// Counter mode, with the initial counter block.
+    Ctr([u8; BLOCK_SIZE]),
+}
+
+impl ChainingMode {
+    fn chmod(&self) -> u8 {
+        match self {
+            Self::Ecb => 0b00,
+            Self::Cbc(_) => 0b01,
+            Self::Ctr(_) => 0b10,
+        }
+    }
+
+    fn iv(&self) -> Option<&[u8; BLOCK_SIZE]> {
+        match self {
+            Self::Ecb => None,
+            Self::Cbc(iv) | Self::Ctr(iv) => Some(iv),
+        }
+    }
+}
+
+/// Operation direction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    /// Encryption.
+    Encrypt,
+    /// Decryption.
+    Decrypt,
+}
+
+/// AES driver.
+pub struct Aes<'d, T: Instance, M: Mode> {
+    _peripheral: Peri<'d, T>,
+    _phantom: PhantomData<M>,
+    indma: Option<ChannelAndRequest<'d>>,
+    outdma: Option<ChannelAndRequest<'d>>,
+    mode: ChainingMode,
+    finished: bool,
+}
+
+impl<'d, T: Instance> Aes<'d, T, Blocking> {
+    /// Create a new AES driver in blocking mode.
+    pub fn new_blocking(peri: Peri<'d, T>) -> Self {
+        rcc::enable_and_reset::<T>();
+
+        Self {
+            _peripheral: peri,
+            _phantom: PhantomData,
+            indma: None,
+            outdma: None,
+            mode: ChainingMode::Ecb,
+            finished: true,
+        }
+    }
+}
+
+impl<'d, T: Instance> Aes<'d, T, Async> {
+    /// Create a new AES driver, using DMA to move data in and out of the peripheral.
+    pub fn new(
+        peri: Peri<'d, T>,
+        indma: Peri<'d, impl DmaIn<T>>,
+        outdma: Peri<'d, impl DmaOut<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+    ) -> Self {
+        rcc::enable_and_reset::<T>();
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        Self {
+            _peripheral: peri,
+            _phantom: PhantomData,
+            indma: new_dma!(indma),
+            outdma: new_dma!(outdma),
+            mode: ChainingMode::Ecb,
+            finished: true,
+        }
+    }
+
+    /// Encrypt `input` into `output` using DMA, in a single operation.
+    ///
+    /// Same as [`Self::start`] followed by [`Self::process_dma`] and [`Self::finish`].
+    pub async fn encrypt_dma(
+        &mut self,
+        key: &[u8],
+        mode: ChainingMode,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        self.start(key, mode, Direction::Encrypt)?;
+        let result = self.process_dma(input, output).await;
+        self.finish();
+        result
+    }
+
+    /// Decrypt `input` into `output` using DMA, in a single operation.
+    ///
+    /// Same as [`Self::start`] followed by [`Self::process_dma`] and [`Self::finish`].
+    pub async fn decrypt_dma(
+        &mut self,
+        key: &[u8],
+        mode: ChainingMode,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        self.start(key, mode, Direction::Decrypt)?;
+        let result = self.process_dma(input, output).await;
+        self.finish();
+        result
+    }
+
+    /// Process data using DMA.
+    ///
+    /// Same as [`Self::process_blocking`], but whole blocks are moved by DMA without CPU
+    /// involvement, 32 bits at a time. Both buffers must be 4-byte aligned, or
+    /// [`Error::Unaligned`] is returned. A last partial block in counter mode is processed by the
+    /// CPU.
+    pub async fn process_dma(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        if input.as_ptr() as usize % 4 != 0 || output.as_ptr() as usize % 4 != 0 {
+            return Err(Error::Unaligned);
+        }
+        self.check_lengths(input, output)?;
+
+        let full = input.len() / BLOCK_SIZE * BLOCK_SIZE;
+        let regs = T::regs();
+
+        // Stop the DMA requests if the future is dropped mid-transfer.
+        let on_drop = OnDrop::new(|| {
+            regs.cr().modify(|w| {
+                w.set_dmainen(false);
+                w.set_dmaouten(false);
+            })
+        });
+
+        for (input, output) in input[..full]
+            .chunks(MAX_DMA_LEN)
+            .zip(output[..full].chunks_mut(MAX_DMA_LEN))
+        {
+            regs.cr().modify(|w| {
+                w.set_dmainen(true);
+                w.set_dmaouten(true);
+            });
+
+            let options = TransferOptions {
+                #[cfg(not(gpdma))]
+                priority: crate::dma::Priority::High,
+                ..Default::default()
+            };
+            let words = input.len() / 4;
+            let src: *const [u32] = ptr::slice_from_raw_parts(input.as_ptr().cast(), words);
+            let dst: *mut [u32] = ptr::slice_from_raw_parts_mut(output.as_mut_ptr().cast(), words);
+            let write = unsafe { unwrap!(self.indma.as_mut()).write_raw(src, regs.dinr().as_ptr(), options) };
+            let read = unsafe { unwrap!(self.outdma.as_mut()).read_raw(regs.doutr().as_ptr(), dst, options) };
+            embassy_futures::join::join(write, read).await;
+
+            regs.cr().modify(|w| {
+                w.set_dmainen(false);
+                w.set_dmaouten(false);
+                w.set_ccfc(true);
+            });
+
+            let sr = regs.sr().read();
+            if sr.rderr() || sr.wrerr() {
+                regs.cr().modify(|w| w.set_errc(true));
+                return Err(Error::ReadWrite);
+            }
+        }
+        on_drop.defuse();
+
+        // Counter mode allows a last partial block, which is not worth a DMA transfer.
+        if full < input.len() {
+            let mut block = [0; BLOCK_SIZE];
+            block[..input.len() - full].copy_from_slice(&input[full..]);
+            self.write_block(&block);
+            self.wait_ccf().await?;
+            let block = self.read_block();
+            output[full..].copy_from_slice(&block[..input.len() - full]);
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the end of the computation, sleeping until the AES interrupt fires.
+    async fn wait_ccf(&self) -> Result<(), Error> {
+        let regs = T::regs();
+        let _on_drop = OnDrop::new(|| {
+            regs.cr().modify(|w| {
+                w.set_ccfie(false);
+                w.set_errie(false);
+            })
+        });
+
+        poll_fn(|cx| {
+            T::waker().register(cx.waker());
+
+            let sr = regs.sr().read();
+            if sr.rderr() || sr.wrerr() {
+                regs.cr().modify(|w| w.set_errc(true));
+                return Poll::Ready(Err(Error::ReadWrite));
+            }
+            if sr.ccf() {
+                regs.cr().modify(|w| w.set_ccfc(true));
+                return Poll::Ready(Ok(()));
+            }
+
+            regs.cr().modify(|w| {
+                w.set_ccfie(true);
+                w.set_errie(true);
+            });
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<'d, T: Instance, M: Mode> Aes<'d, T, M> {
+    /// Start a new operation.
+    ///
+    /// `key` must be 16 or 32 bytes long. For decryption in ECB and CBC modes, the decryption key
+    /// is derived from `key` by the peripheral before processing data.
+    ///
+    /// Data is then processed with [`Self::process_blocking`], possibly in several chunks.
+    pub fn start(&mut self, key: &[u8], mode: ChainingMode, dir: Direction) -> Result<(), Error> {
+        let keysize = key_size(key)?;
+
+        let regs = T::regs();
+        regs.cr().write(|w| {
+            w.set_en(false);
+            w.set_keysize(keysize);
+            // Set data type to 8-bit. This will match software implementations.
+            w.set_datatype(2);
+            w.set_chmod(mode.chmod());
+        });
+        self.load_key(key);
+
+        if dir == Direction::Decrypt && !matches!(mode, ChainingMode::Ctr(_)) {
+            // Key derivation.
+            regs.cr().modify(|w| w.set_mode(0b01));
+            regs.cr().modify(|w| w.set_en(true));
+            self.wait_ccf_blocking()?;
+            regs.cr().modify(|w| w.set_en(false));
+            regs.cr().modify(|w| w.set_mode(0b10));
+        } else {
+            // Counter mode decryption is the same as encryption.
+            regs.cr().modify(|w| w.set_mode(0b00));
+        }
+
+        if let Some(iv) = mode.iv() {
+            self.load_iv(iv);
+        }
+
+        regs.cr().modify(|w| w.set_en(true));
+
+        self.mode = mode;
+        self.finished = false;
+        Ok(())
+    }
+
+    /// Process data, blocking until done.
+    ///
+    /// `input` and `output` must have the same length, a multiple of the block size. In counter
+    /// mode, the last chunk of an operation may have any length.
+    pub fn process_blocking(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        self.check_lengths(input, output)?;
+
+        for (input, output) in input.chunks(BLOCK_SIZE).zip(output.chunks_mut(BLOCK_SIZE)) {
+            let mut block = [0; BLOCK_SIZE];
+            block[..input.len()].copy_from_slice(input);
+            let block = self.process_block_blocking(&block)?;
+            output.copy_from_slice(&block[..output.len()]);
+        }
+
+        Ok(())
+    }
+
+    /// Finish the current operation and disable the peripheral.
+    pub fn finish(&mut self) {
+        T::regs().cr().modify(|w| w.set_en(false));
+        self.finished = true;
+    }
+
+    /// Encrypt `buffer` in place with AES-GCM, and authenticate it along with `aad`.
+    ///
+    /// Returns the authentication tag.
+    pub fn gcm_encrypt_blocking(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8; GCM_NONCE_SIZE],
+        aad: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<[u8; BLOCK_SIZE], Error> {
+        self.gcm_blocking(key, nonce, aad, buffer, Direction::Encrypt)
+    }
+
+    /// Decrypt `buffer` in place with AES-GCM, and check it along with `aad` against `tag`.
+    ///
+    /// If the tag doesn't match, `buffer` is zeroed and [`Error::AuthenticationFailed`] is returned.
+    pub fn gcm_decrypt_blocking(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8; GCM_NONCE_SIZE],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8; BLOCK_SIZE],
+    ) -> Result<(), Error> {
+        let computed = self.gcm_blocking(key, nonce, aad, buffer, Direction::Decrypt)?;
+        check_tag(buffer, &computed, tag)
+    }
+
+    /// Encrypt `buffer` in place with AES-CCM, and authenticate it along with `aad`.
+    ///
+    /// `nonce` must be 7 to 13 bytes long. The authentication tag is written to `tag`, whose
+    /// length must be even and between 4 and 16 bytes.
+    pub fn ccm_encrypt_blocking(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<(), Error> {
+        let computed = self.ccm_blocking(key, nonce, aad, buffer, tag.len(), Direction::Encrypt)?;
+        tag.copy_from_slice(&computed[..tag.len()]);
+        Ok(())
+    }
+
+    /// Decrypt `buffer` in place with AES-CCM, and check it along with `aad` against `tag`.
+    ///
+    /// If the tag doesn't match, `buffer` is zeroed and [`Error::AuthenticationFailed`] is returned.
+    pub fn ccm_decrypt_blocking(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), Error> {
+        let computed = self.ccm_blocking(key, nonce, aad, buffer, tag.len(), Direction::Decrypt)?;
+        check_tag(buffer, &computed[..tag.len()], tag)
+    }
+
+    fn gcm_blocking(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8; GCM_NONCE_SIZE],
+        aad: &[u8],
+        buffer: &mut [u8],
+        dir: Direction,
+    ) -> Result<[u8; BLOCK_SIZE], Error> {
+        // The payload is encrypted starting from counter value 2.
+        let mut iv = [0; BLOCK_SIZE];
+        iv[..GCM_NONCE_SIZE].copy_from_slice(nonce);
+        iv[BLOCK_SIZE - 1] = 2;
+
+        let mut lengths = [0; BLOCK_SIZE];
+        lengths[..8].copy_from_slice(&(aad.len() as u64 * 8).to_be_bytes());
+        lengths[8..].copy_from_slice(&(buffer.len() as u64 * 8).to_be_bytes());
+
+        self.start_authenticated(key, 0b011, dir, &iv)?;
+        // The hash covers the ciphertext, so padding must be excluded when encrypting.
+        self.authenticated_phases_blocking(&[aad], buffer, dir == Direction::Encrypt, Some(&lengths))
+    }
+
+    fn ccm_blocking(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag_len: usize,
+        dir: Direction,
+    ) -> Result<[u8; BLOCK_SIZE], Error> {
+        if !(7..=13).contains(&nonce.len()) || !(4..=16).contains(&tag_len) || tag_len % 2 != 0 {
+            return Err(Error::InvalidLength);
+        }
+        // Size of the message length field.
+        let l = 15 - nonce.len();
+        if l < 8 && buffer.len() as u64 >> (8 * l) != 0 {
+            return Err(Error::InvalidLength);
+        }
+
+        let mut b0 = [0; BLOCK_SIZE];
+        b0[0] = ((!aad.is_empty() as u8) << 6) | (((tag_len - 2) / 2) as u8) << 3 | (l - 1) as u8;
+        b0[1..1 + nonce.len()].copy_from_slice(nonce);
+        b0[BLOCK_SIZE - l..].copy_from_slice(&(buffer.len() as u64).to_be_bytes()[8 - l..]);
+
+        // The associated data is prefixed with its encoded length.
+        let mut prefix = [0; 6];
+        let prefix_len = if aad.is_empty() {
+            0
+        } else if aad.len() < 0xFF00 {
+            prefix[..2].copy_from_slice(&(aad.len() as u16).to_be_bytes());
+            2
+        } else {
+            prefix[..2].copy_from_slice(&[0xFF, 0xFE]);
+            prefix[2..].copy_from_slice(&(aad.len() as u32).to_be_bytes());
+            6
+        };
+
+        self.start_authenticated(key, 0b100, dir, &b0)?;
+        // The MAC covers the plaintext, so padding must be excluded when decrypting.
+        self.authenticated_phases_blocking(&[&prefix[..prefix_len], aad], buffer, dir == Direction::Decrypt, None)
+    }
+
+    /// Configure an authenticated mode and run its init phase.
+    fn start_authenticated(
+        &mut self,
+        key: &[u8],
+        chmod: u8,
+        dir: Direction,
+        iv: &[u8; BLOCK_SIZE],
+    ) -> Result<(), Error> {
+        let keysize = key_size(key)?;
+
+        let regs = T::regs();
+        regs.cr().write(|w| {
+            w.set_en(false);
+            w.set_keysize(keysize);
+            w.set_datatype(2);
+            w.set_chmod(chmod);
+            w.set_gcmph(0b00);
+            w.set_mode(if dir == Direction::Decrypt { 0b10 } else { 0b00 });
+        });
+        self.load_key(key);
+        self.load_iv(iv);
+
+        // The peripheral computes the hash subkey (GCM) or processes B0 (CCM), then clears EN.
+        self.finished = true;
+        regs.cr().modify(|w| w.set_en(true));
+        self.wait_ccf_blocking()
+    }
+
+    /// Run the header, payload and final phases of an authenticated mode, and return the tag.
+    ///
+    /// `header` is the concatenation of the slices, zero-padded to whole blocks. If
+    /// `exclude_padding` is set, the padding of a last partial payload block is excluded from the
+    /// tag computation. `final_block` is written in the final phase, if any.
+    fn authenticated_phases_blocking(
+        &mut self,
+        header: &[&[u8]],
+        buffer: &mut [u8],
+        exclude_padding: bool,
+        final_block: Option<&[u8; BLOCK_SIZE]>,
+    ) -> Result<[u8; BLOCK_SIZE], Error> {
+        let regs = T::regs();
+
+        if header.iter().any(|h| !h.is_empty()) {
+            regs.cr().modify(|w| {
+                w.set_gcmph(0b01);
+                w.set_en(true);
+            });
+
+            let mut block = [0; BLOCK_SIZE];
+            let mut len = 0;
+            for &byte in header.iter().flat_map(|h| h.iter()) {
+                block[len] = byte;
+                len += 1;
+                if len == BLOCK_SIZE {
+                    self.write_block_blocking(&block)?;
+                    len = 0;
+                }
+            }
+            if len > 0 {
+                block[len..].fill(0);
+                self.write_block_blocking(&block)?;
+            }
+        }
+
+        if !buffer.is_empty() {
+            regs.cr().modify(|w| {
+                w.set_gcmph(0b10);
+                w.set_en(true);
+            });
+
+            for chunk in buffer.chunks_mut(BLOCK_SIZE) {
+                let mut block = [0; BLOCK_SIZE];
+                block[..chunk.len()].copy_from_slice(chunk);
+                if exclude_padding && chunk.len() < BLOCK_SIZE {
+                    regs.cr().modify(|w| w.set_npblb((BLOCK_SIZE - chunk.len()) as u8));
+                }
+                let block = self.process_block_blocking(&block)?;
+                chunk.copy_from_slice(&block[..chunk.len()]);
+            }
+        }
+
+        // The tag is always computed in encryption mode.
+        regs.cr().modify(|w| {
+            w.set_gcmph(0b11);
+            w.set_mode(0b00);
+            w.set_en(true);
+        });
+        match final_block {
+            Some(block) => self.write_block_blocking(block)?,
+            None => self.wait_ccf_blocking()?,
+        }
+        let tag = self.read_block();
+
+        regs.cr().modify(|w| {
+            w.set_en(false);
+            w.set_npblb(0);
+        });
+        Ok(tag)
+    }
+
+    fn check_lengths(&mut self, input: &[u8], output: &[u8]) -> Result<(), Error> {
+        if self.finished {
+            return Err(Error::Finished);
+        }
+        if input.len() != output.len() {
+            return Err(Error::InvalidLength);
+        }
+        if input.len() % BLOCK_SIZE != 0 {
+            if !matches!(self.mode, ChainingMode::Ctr(_)) {
+                return Err(Error::InvalidLength);
+            }
+            self.finished = true;
+        }
+        Ok(())
+    }
+
+    fn process_block_blocking(&mut self, block: &[u8; BLOCK_SIZE]) -> Result<[u8; BLOCK_SIZE], Error> {
+        self.write_block_blocking(block)?;
+        Ok(self.read_block())
+    }
+
+    fn write_block_blocking(&mut self, block: &[u8; BLOCK_SIZE]) -> Result<(), Error> {
+        self.write_block(block);
+        self.wait_ccf_blocking()
+    }
+
+    fn write_block(&mut self, block: &[u8; BLOCK_SIZE]) {
+        for word in block.chunks_exact(4) {
+            T::regs()
+                .dinr()
+                .write_value(u32::from_le_bytes(unwrap!(word.try_into())));
+        }
+    }
+
+    fn read_block(&mut self) -> [u8; BLOCK_SIZE] {
+        let mut out = [0; BLOCK_SIZE];
+        for word in out.chunks_exact_mut(4) {
+            word.copy_from_slice(&T::regs().doutr().read().to_le_bytes());
+        }
+        out
+    }
+
+    fn load_key(&self, key: &[u8]) {
+        // KEYR0 holds the last word of the key.
+        let words = key.len() / 4;
+        for (i, word) in key.chunks_exact(4).enumerate() {
+            T::regs()
+                .keyr(words - 1 - i)
+                .write_value(u32::from_be_bytes(unwrap!(word.try_into())));
+        }
+    }
+
+    fn load_iv(&self, iv: &[u8; BLOCK_SIZE]) {
+        // IVR3 holds the first word.
+        for (i, word) in iv.chunks_exact(4).enumerate() {
+            T::regs()
+                .ivr(3 - i)
+                .write_value(u32::from_be_bytes(unwrap!(word.try_into())));
+        }
+    }
+
+    fn wait_ccf_blocking(&self) -> Result<(), Error> {
+        let regs = T::regs();
+        loop {
+            let sr = regs.sr().read();
+            if sr.rderr() || sr.wrerr() {
+                regs.cr().modify(|w| w.set_errc(true));
+                return Err(Error::ReadWrite);
+            }
+            if sr.ccf() {
+                regs.cr().modify(|w| w.set_ccfc(true));
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<'d, T: Instance, M: Mode> Drop for Aes<'d, T, M> {
+    fn drop(&mut self) {
+        T::regs().cr().modify(|w| w.set_en(false));
+        rcc::disable::<T>();
+    }
+}
+
+/// AES-GCM with a 96-bit nonce and a 128-bit tag, implementing the [`aead`] traits.
+#[cfg(feature = "aead")]
+pub struct AesGcm<'a, 'd, T: Instance, M: Mode> {
+    aes: core::cell::RefCell<&'a mut Aes<'d, T, M>>,
+    key: &'a [u8],
+}
+
+#[cfg(feature = "aead")]
+impl<'a, 'd, T: Instance, M: Mode> AesGcm<'a, 'd, T, M> {
+    /// Create a new AES-GCM cipher using `aes` with `key`, which must be 16 or 32 bytes long.
+    pub fn new(aes: &'a mut Aes<'d, T, M>, key: &'a [u8]) -> Result<Self, Error> {
+        key_size(key)?;
+        Ok(Self {
+            aes: core::cell::RefCell::new(aes),
+            key,
+        })
+    }
+}
+
+#[cfg(feature = "aead")]
+impl<'a, 'd, T: Instance, M: Mode> aead::AeadCore for AesGcm<'a, 'd, T, M> {
+    type NonceSize = aead::consts::U12;
+    type TagSize = aead::consts::U16;
+    type CiphertextOverhead = aead::consts::U0;
+}
+
+#[cfg(feature = "aead")]
+impl<'a, 'd, T: Instance, M: Mode> aead::AeadInPlace for AesGcm<'a, 'd, T, M> {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &aead::Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> aead::Result<aead::Tag<Self>> {
+        let mut aes = self.aes.borrow_mut();
+        let tag = aes
+            .gcm_encrypt_blocking(self.key, &(*nonce).into(), associated_data, buffer)
+            .map_err(|_| aead::Error)?;
+        Ok(tag.into())
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &aead::Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &aead::Tag<Self>,
+    ) -> aead::Result<()> {
+        let mut aes = self.aes.borrow_mut();
+        aes.gcm_decrypt_blocking(self.key, &(*nonce).into(), associated_data, buffer, &(*tag).into())
+            .map_err(|_| aead::Error)
+    }
+}
+
+/// AES-CCM with `N`-byte nonces and `M`-byte tags, implementing the [`aead`] traits.
+///
+/// `N` must be between 7 and 13, `M` must be even and between 4 and 16.
+#[cfg(feature = "aead")]
+pub struct AesCcm<'a, 'd, T: Instance, Md: Mode, M, N> {
+    aes: core::cell::RefCell<&'a mut Aes<'d, T, Md>>,
+    key: &'a [u8],
+    _phantom: PhantomData<(M, N)>,
+}
+
+#[cfg(feature = "aead")]
+impl<'a, 'd, T: Instance, Md: Mode, M, N> AesCcm<'a, 'd, T, Md, M, N>
+where
+    M: aead::generic_array::ArrayLength<u8>,
+    N: aead::generic_array::ArrayLength<u8>,
+{
+    /// Create a new AES-CCM cipher using `aes` with `key`, which must be 16 or 32 bytes long.
+    pub fn new(aes: &'a mut Aes<'d, T, Md>, key: &'a [u8]) -> Result<Self, Error> {
+        key_size(key)?;
+        if !(7..=13).contains(&N::USIZE) || !(4..=16).contains(&M::USIZE) || M::USIZE % 2 != 0 {
+            return Err(Error::InvalidLength);
+        }
+        Ok(Self {
+            aes: core::cell::RefCell::new(aes),
+            key,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+#[cfg(feature = "aead")]
+impl<'a, 'd, T: Instance, Md: Mode, M, N> aead::AeadCore for AesCcm<'a, 'd, T, Md, M, N>
+where
+    M: aead::generic_array::ArrayLength<u8>,
+    N: aead::generic_array::ArrayLength<u8>,
+{
+    type NonceSize = N;
+    type TagSize = M;
+    type CiphertextOverhead = aead::consts::U0;
+}
+
+#[cfg(feature = "aead")]
+impl<'a, 'd, T: Instance, Md: Mode, M, N> aead::AeadInPlace for AesCcm<'a, 'd, T, Md, M, N>
+where
+    M: aead::generic_array::ArrayLength<u8>,
+    N: aead::generic_array::ArrayLength<u8>,
+{
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &aead::Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> aead::Result<aead::Tag<Self>> {
+        let mut tag = aead::Tag::<Self>::default();
+        let mut aes = self.aes.borrow_mut();
+        aes.ccm_encrypt_blocking(self.key, nonce, associated_data, buffer, &mut tag)
+            .map_err(|_| aead::Error)?;
+        Ok(tag)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &aead::Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &aead::Tag<Self>,
+    ) -> aead::Result<()> {
+        let mut aes = self.aes.borrow_mut();
+        aes.ccm_decrypt_blocking(self.key, nonce, associated_data, buffer, tag)
+            .map_err(|_| aead::Error)
+    }
+}
+
+/// Compare tags in constant time, and zero `buffer` if they differ.
+fn check_tag(buffer: &mut [u8], computed: &[u8], tag: &[u8]) -> Result<(), Error> {
+    let diff = computed.iter().zip(tag).fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff == 0 {
+        Ok(())
+    } else {
+        buffer.fill(0);
+        Err(Error::AuthenticationFailed)
+    }
+}
+
+fn key_size(key: &[u8]) -> Result<bool, Error> {
+    match key.len() {
+        16 => Ok(false),
+        32 => Ok(true),
+        _ => Err(Error::InvalidKeyLength),
+    }
+}
+
+trait SealedInstance {
+    fn regs() -> pac::aes::Aes;
+    fn waker() -> &'static AtomicWaker;
+}
+
+/// AES instance trait.
+#[allow(private_bounds)]
+pub trait Instance: SealedInstance + PeripheralType + crate::rcc::RccPeripheral + 'static + Send {
+    /// Interrupt for this AES instance.
+    type Interrupt: interrupt::typelevel::Interrupt;
+}
+
+foreach_interrupt!(
+    ($inst:ident, aes, AES, GLOBAL, $irq:ident) => {
+        impl Instance for peripherals::$inst {
+            type Interrupt = crate::interrupt::typelevel::$irq;
+        }
+
+        impl SealedInstance for peripherals::$inst {
+            fn regs() -> crate::pac::aes::Aes {
+                crate::pac::$inst
+            }
+
+            fn waker() -> &'static AtomicWaker {
+                static WAKER: AtomicWaker = AtomicWaker::new();
+                &WAKER
+            }
+        }
+    };
+);
+
+dma_trait!(DmaIn, Instance);
+dma_trait!(DmaOut, Instance);
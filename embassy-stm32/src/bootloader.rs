@@ -0,0 +1,57 @@
+//! System bootloader
+//!
+//! Enter the ROM bootloader from firmware, without driving the BOOT0 pin. This allows firmware
+//! recovery in the field, for example over USB DFU, UART or I2C depending on the part.
+//!
+//! [`reboot_to_bootloader`] leaves a flag in RAM and resets the device. [`crate::init`] then finds
+//! the flag before touching any peripheral, and jumps to the bootloader from a clean reset state.
+//!
+//! To enter the bootloader on a USB DFU detach request, call [`reboot_to_bootloader`] from the
+//! reset hook of the DFU runtime class, for example with `embassy-usb-dfu`:
+//!
+//! ```rust,ignore
+//! struct EnterBootloader;
+//!
+//! impl embassy_usb_dfu::Reset for EnterBootloader {
+//!     fn sys_reset(&self) {
+//!         embassy_stm32::bootloader::reboot_to_bootloader()
+//!     }
+//! }
+//! ```
+
+use core::mem::MaybeUninit;
+
+/// Start of system memory, where the ROM bootloader vector table lives. It is the same on L4, WB
+/// and WL.
+const SYSTEM_MEMORY: u32 = 0x1FFF_0000;
+const MAGIC: u32 = 0xB007_10AD;
+
+#[link_section = ".uninit.BOOTLOADER_REQUEST"]
+static mut BOOTLOADER_REQUEST: MaybeUninit<u32> = MaybeUninit::uninit();
+
+/// Reset the device into the ROM bootloader.
+pub fn reboot_to_bootloader() -> ! {
+    unsafe {
+        core::ptr::addr_of_mut!(BOOTLOADER_REQUEST)
+            .cast::<u32>()
+            .write_volatile(MAGIC)
+    };
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// Jump to the ROM bootloader if [`reboot_to_bootloader`] requested it before the last reset.
+///
+/// This must run before any clock or peripheral is configured.
+pub(crate) fn jump_if_requested() {
+    let request = unsafe { core::ptr::addr_of_mut!(BOOTLOADER_REQUEST).cast::<u32>() };
+    if unsafe { request.read_volatile() } != MAGIC {
+        return;
+    }
+    unsafe { request.write_volatile(0) };
+
+    unsafe {
+        (*cortex_m::peripheral::SCB::PTR).vtor.write(SYSTEM_MEMORY);
+        // Sets MSP and PC from the bootloader vector table.
+        cortex_m::asm::bootload(SYSTEM_MEMORY as *const u32)
+    }
+}
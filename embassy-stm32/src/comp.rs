@@ -0,0 +1,515 @@
+//! Comparator (COMP)
+#![macro_use]
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::PeripheralType;
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::adc::blocking_delay_us;
+use crate::interrupt::typelevel::Interrupt;
+use crate::pac::EXTI;
+use crate::timer::complementary_pwm::ComplementaryPwm;
+use crate::timer::AdvancedInstance4Channel;
+use crate::{interrupt, peripherals, Peri};
+
+/// Inverting input source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InvertingInput {
+    /// 1/4 of VREFINT.
+    VrefintQuarter,
+    /// 1/2 of VREFINT.
+    VrefintHalf,
+    /// 3/4 of VREFINT.
+    VrefintThreeQuarter,
+    /// VREFINT.
+    Vrefint,
+    /// DAC channel 1 output.
+    #[cfg(dac)]
+    DacCh1,
+    /// DAC channel 2 output.
+    #[cfg(dac)]
+    DacCh2,
+}
+
+impl InvertingInput {
+    fn inmsel(&self) -> u8 {
+        match self {
+            InvertingInput::VrefintQuarter => 0b000,
+            InvertingInput::VrefintHalf => 0b001,
+            InvertingInput::VrefintThreeQuarter => 0b010,
+            InvertingInput::Vrefint => 0b011,
+            #[cfg(dac)]
+            InvertingInput::DacCh1 => 0b100,
+            #[cfg(dac)]
+            InvertingInput::DacCh2 => 0b101,
+        }
+    }
+
+    fn needs_scaler(&self) -> bool {
+        matches!(
+            self,
+            InvertingInput::VrefintQuarter | InvertingInput::VrefintHalf | InvertingInput::VrefintThreeQuarter
+        )
+    }
+}
+
+/// Hysteresis level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Hysteresis {
+    /// No hysteresis.
+    None,
+    /// Low hysteresis.
+    Low,
+    /// Medium hysteresis.
+    Medium,
+    /// High hysteresis.
+    High,
+}
+
+/// Power mode, trading propagation delay against consumption.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerMode {
+    /// High speed, full power.
+    HighSpeed,
+    /// Medium speed, medium power.
+    MediumSpeed,
+    /// Ultra low power.
+    UltraLowPower,
+}
+
+/// Timer break input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BreakInput {
+    /// Break input (`BRK`).
+    Break,
+    /// Break input 2 (`BRK2`).
+    Break2,
+}
+
+/// Blanking source, masking the comparator output while a timer output compare signal is high,
+/// for example during PWM switching edges.
+///
+/// Not every source is available on every comparator, see [`Config::blanking`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Blanking {
+    /// No blanking.
+    None,
+    /// TIM1 OC4.
+    #[cfg(stm32wb)]
+    Tim1Oc4,
+    /// TIM1 OC5. COMP1 only on L4.
+    Tim1Oc5,
+    /// TIM2 OC3. COMP1 only on L4.
+    Tim2Oc3,
+    /// TIM3 OC3, COMP1 only.
+    #[cfg(stm32l4)]
+    Tim3Oc3,
+    /// TIM3 OC4, COMP2 only.
+    #[cfg(stm32l4)]
+    Tim3Oc4,
+    /// TIM8 OC5, COMP2 only.
+    #[cfg(stm32l4)]
+    Tim8Oc5,
+    /// TIM15 OC1, COMP2 only.
+    #[cfg(stm32l4)]
+    Tim15Oc1,
+}
+
+impl Blanking {
+    /// `BLANKING` field value for the comparator with the given index, `None` if the source is
+    /// not available on it.
+    fn bits(&self, index: usize) -> Option<u8> {
+        match (self, index) {
+            (Blanking::None, _) => Some(0b000),
+            #[cfg(stm32wb)]
+            (Blanking::Tim1Oc4, _) => Some(0b001),
+            #[cfg(stm32wb)]
+            (Blanking::Tim1Oc5, _) => Some(0b010),
+            #[cfg(stm32wb)]
+            (Blanking::Tim2Oc3, _) => Some(0b100),
+            #[cfg(stm32l4)]
+            (Blanking::Tim1Oc5, 0) => Some(0b001),
+            #[cfg(stm32l4)]
+            (Blanking::Tim2Oc3, 0) => Some(0b010),
+            #[cfg(stm32l4)]
+            (Blanking::Tim3Oc3, 0) => Some(0b100),
+            #[cfg(stm32l4)]
+            (Blanking::Tim3Oc4, 1) => Some(0b001),
+            #[cfg(stm32l4)]
+            (Blanking::Tim8Oc5, 1) => Some(0b010),
+            #[cfg(stm32l4)]
+            (Blanking::Tim15Oc1, 1) => Some(0b100),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+/// Comparator configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Inverting input, used by [`Comparator::new`].
+    pub inverting: InvertingInput,
+    /// Hysteresis.
+    pub hysteresis: Hysteresis,
+    /// Invert the comparator output.
+    pub invert_output: bool,
+    /// Blanking source. The constructors fail with [`ConfigError::BlankingNotAvailable`] if it isn't
+    /// available on the comparator.
+    pub blanking: Blanking,
+    /// Power mode.
+    pub power_mode: PowerMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            inverting: InvertingInput::VrefintHalf,
+            hysteresis: Hysteresis::None,
+            invert_output: false,
+            blanking: Blanking::None,
+            power_mode: PowerMode::HighSpeed,
+        }
+    }
+}
+
+/// Comparator configuration error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The blanking source isn't available on this comparator.
+    BlankingNotAvailable,
+}
+
+/// COMP interrupt handler.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let line = T::EXTI_LINE;
+        if EXTI.pr(0).read().line(line) {
+            // Mask the line, the waiting future unmasks it again if needed.
+            exti_imr().modify(|w| w.set_line(line, false));
+            EXTI.pr(0).write(|w| w.set_line(line, true));
+            T::waker().wake();
+        }
+    }
+}
+
+#[cfg(stm32wb)]
+fn exti_imr() -> crate::pac::common::Reg<crate::pac::exti::regs::Lines, crate::pac::common::RW> {
+    #[cfg(feature = "_core-cm0p")]
+    return EXTI.cpu(1).imr(0);
+    #[cfg(not(feature = "_core-cm0p"))]
+    return EXTI.cpu(0).imr(0);
+}
+
+#[cfg(not(stm32wb))]
+fn exti_imr() -> crate::pac::common::Reg<crate::pac::exti::regs::Lines, crate::pac::common::RW> {
+    EXTI.imr(0)
+}
+
+/// Comparator driver.
+pub struct Comparator<'d, T: Instance> {
+    _peri: Peri<'d, T>,
+}
+
+impl<'d, T: Instance> Comparator<'d, T> {
+    /// Create a new comparator comparing `inp` against the internal source selected by
+    /// [`Config::inverting`].
+    ///
+    /// The comparator is enabled on creation.
+    pub fn new(
+        peri: Peri<'d, T>,
+        inp: Peri<'d, impl NonInvertingPin<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        let blanking = Self::blanking(&config)?;
+        inp.set_as_analog();
+        let inpsel = inp.channel();
+        Ok(Self::new_inner(
+            peri,
+            inpsel,
+            config.inverting.inmsel(),
+            config.inverting.needs_scaler(),
+            blanking,
+            config,
+        ))
+    }
+
+    /// Create a new comparator comparing `inp` against the `inm` pin.
+    ///
+    /// [`Config::inverting`] is ignored. The comparator is enabled on creation.
+    pub fn new_with_pins(
+        peri: Peri<'d, T>,
+        inp: Peri<'d, impl NonInvertingPin<T>>,
+        inm: Peri<'d, impl InvertingPin<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        let blanking = Self::blanking(&config)?;
+        inp.set_as_analog();
+        inm.set_as_analog();
+        let inpsel = inp.channel();
+        // INMSEL 0b110 selects INM0, 0b111 selects one of the extended inputs through INMESEL.
+        let inmsel = match inm.channel() {
+            0 => 0b110,
+            ch => {
+                T::regs().csr().modify(|w| w.set_inmesel(ch - 1));
+                0b111
+            }
+        };
+        Ok(Self::new_inner(peri, inpsel, inmsel, false, blanking, config))
+    }
+
+    fn blanking(config: &Config) -> Result<u8, ConfigError> {
+        config.blanking.bits(T::INDEX).ok_or(ConfigError::BlankingNotAvailable)
+    }
+
+    fn new_inner(peri: Peri<'d, T>, inpsel: u8, inmsel: u8, scaler: bool, blanking: u8, config: Config) -> Self {
+        // COMP is clocked through SYSCFG, which is enabled by `init()`.
+        T::regs().csr().modify(|w| {
+            w.set_en(false);
+            w.set_inpsel(inpsel);
+            w.set_inmsel(inmsel);
+            w.set_scalen(scaler);
+            w.set_brgen(scaler);
+            w.set_hyst(match config.hysteresis {
+                Hysteresis::None => 0b00,
+                Hysteresis::Low => 0b01,
+                Hysteresis::Medium => 0b10,
+                Hysteresis::High => 0b11,
+            });
+            w.set_polarity(config.invert_output);
+            w.set_blanking(blanking);
+            w.set_pwrmode(match config.power_mode {
+                PowerMode::HighSpeed => 0b00,
+                PowerMode::MediumSpeed => 0b01,
+                PowerMode::UltraLowPower => 0b11,
+            });
+        });
+        T::regs().csr().modify(|w| w.set_en(true));
+
+        // tSTART: up to 80 us in ultra-low-power mode.
+        blocking_delay_us(80);
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        Self { _peri: peri }
+    }
+
+    /// Current output level of the comparator, after polarity selection.
+    pub fn output_level(&self) -> bool {
+        T::regs().csr().read().value()
+    }
+
+    /// Wait for a rising edge of the comparator output.
+    pub async fn wait_for_rising_edge(&mut self) {
+        self.wait_for_edge(true, false).await
+    }
+
+    /// Wait for a falling edge of the comparator output.
+    pub async fn wait_for_falling_edge(&mut self) {
+        self.wait_for_edge(false, true).await
+    }
+
+    /// Wait for a rising or falling edge of the comparator output.
+    pub async fn wait_for_any_edge(&mut self) {
+        self.wait_for_edge(true, true).await
+    }
+
+    /// Wait for the comparator output to be high.
+    pub async fn wait_for_high(&mut self) {
+        if !self.output_level() {
+            self.wait_for_rising_edge().await
+        }
+    }
+
+    /// Wait for the comparator output to be low.
+    pub async fn wait_for_low(&mut self) {
+        if self.output_level() {
+            self.wait_for_falling_edge().await
+        }
+    }
+
+    /// Route the comparator output to a break input of the timer driven by `pwm`, inverted if
+    /// `invert` is set.
+    ///
+    /// The break function itself (`BKE`/`BK2E`, polarity, filter) is configured on the timer.
+    pub fn route_to_break<TIM: AdvancedInstance4Channel>(
+        &mut self,
+        pwm: &mut ComplementaryPwm<'_, TIM>,
+        input: BreakInput,
+        invert: bool,
+    ) {
+        let r = pwm.regs_advanced();
+        let af = match input {
+            BreakInput::Break => r.af1(),
+            BreakInput::Break2 => r.af2(),
+        };
+        af.modify(|w| {
+            w.set_bkcmpp(T::INDEX, invert);
+            w.set_bkcmpe(T::INDEX, true);
+        });
+    }
+
+    /// Disconnect the comparator output from a break input of the timer driven by `pwm`.
+    pub fn unroute_from_break<TIM: AdvancedInstance4Channel>(
+        &mut self,
+        pwm: &mut ComplementaryPwm<'_, TIM>,
+        input: BreakInput,
+    ) {
+        let r = pwm.regs_advanced();
+        let af = match input {
+            BreakInput::Break => r.af1(),
+            BreakInput::Break2 => r.af2(),
+        };
+        af.modify(|w| w.set_bkcmpe(T::INDEX, false));
+    }
+
+    async fn wait_for_edge(&mut self, rising: bool, falling: bool) {
+        let line = T::EXTI_LINE;
+        critical_section::with(|_| {
+            EXTI.rtsr(0).modify(|w| w.set_line(line, rising));
+            EXTI.ftsr(0).modify(|w| w.set_line(line, falling));
+            EXTI.pr(0).write(|w| w.set_line(line, true));
+            exti_imr().modify(|w| w.set_line(line, true));
+        });
+
+        let _guard = OnDrop::new(|| {
+            critical_section::with(|_| exti_imr().modify(|w| w.set_line(line, false)));
+        });
+
+        poll_fn(|cx| {
+            T::waker().register(cx.waker());
+            if !exti_imr().read().line(line) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl<'d, T: Instance> Drop for Comparator<'d, T> {
+    fn drop(&mut self) {
+        T::regs().csr().modify(|w| w.set_en(false));
+    }
+}
+
+pub(crate) trait SealedInstance {
+    fn regs() -> crate::pac::comp::Comp;
+    fn waker() -> &'static AtomicWaker;
+    const EXTI_LINE: usize;
+    /// Index of this comparator in the timer break input selection.
+    const INDEX: usize;
+}
+
+pub(crate) trait SealedNonInvertingPin<T: Instance> {
+    fn channel(&self) -> u8;
+}
+
+pub(crate) trait SealedInvertingPin<T: Instance> {
+    fn channel(&self) -> u8;
+}
+
+/// Comparator instance trait.
+#[allow(private_bounds)]
+pub trait Instance: SealedInstance + PeripheralType + 'static {
+    /// Interrupt for this comparator instance.
+    type Interrupt: interrupt::typelevel::Interrupt;
+}
+
+/// Non-inverting input pin trait.
+#[allow(private_bounds)]
+pub trait NonInvertingPin<T: Instance>: SealedNonInvertingPin<T> + crate::gpio::Pin {}
+
+/// Inverting input pin trait.
+#[allow(private_bounds)]
+pub trait InvertingPin<T: Instance>: SealedInvertingPin<T> + crate::gpio::Pin {}
+
+macro_rules! impl_comp {
+    ($inst:ident, $line:expr, $index:expr) => {
+        impl SealedInstance for peripherals::$inst {
+            fn regs() -> crate::pac::comp::Comp {
+                crate::pac::$inst
+            }
+
+            fn waker() -> &'static AtomicWaker {
+                static WAKER: AtomicWaker = AtomicWaker::new();
+                &WAKER
+            }
+
+            const EXTI_LINE: usize = $line;
+            const INDEX: usize = $index;
+        }
+    };
+}
+
+// EXTI lines of the comparator outputs.
+#[cfg(stm32l4)]
+foreach_peripheral!(
+    (comp, COMP1) => {
+        impl_comp!(COMP1, 21, 0);
+    };
+    (comp, COMP2) => {
+        impl_comp!(COMP2, 22, 1);
+    };
+);
+#[cfg(stm32wb)]
+foreach_peripheral!(
+    (comp, COMP1) => {
+        impl_comp!(COMP1, 20, 0);
+    };
+    (comp, COMP2) => {
+        impl_comp!(COMP2, 21, 1);
+    };
+);
+
+foreach_interrupt!(
+    ($inst:ident, comp, COMP, $signal:ident, $irq:ident) => {
+        impl Instance for peripherals::$inst {
+            type Interrupt = crate::interrupt::typelevel::$irq;
+        }
+    };
+);
+
+#[allow(unused_macros)]
+macro_rules! impl_comp_inp_pin {
+    ($inst:ident, $pin:ident, $ch:expr) => {
+        impl crate::comp::NonInvertingPin<peripherals::$inst> for crate::peripherals::$pin {}
+        impl crate::comp::SealedNonInvertingPin<peripherals::$inst> for crate::peripherals::$pin {
+            fn channel(&self) -> u8 {
+                $ch
+            }
+        }
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! impl_comp_inm_pin {
+    ($inst:ident, $pin:ident, $ch:expr) => {
+        impl crate::comp::InvertingPin<peripherals::$inst> for crate::peripherals::$pin {}
+        impl crate::comp::SealedInvertingPin<peripherals::$inst> for crate::peripherals::$pin {
+            fn channel(&self) -> u8 {
+                $ch
+            }
+        }
+    };
+}
@@ -0,0 +1,348 @@
+//! Clock recovery system (CRS)
+//!
+//! Trims the HSI48 oscillator against a precise synchronization signal: the LSE, an external
+//! pin, or the USB start-of-frame packets. This keeps HSI48 accurate for its consumers (USB, RNG)
+//! without a crystal.
+//!
+//! This driver is an alternative to [`crate::rcc::Hsi48Config::sync_from_usb`], which only
+//! covers the USB case. Creating it fails with [`ConfigError::AlreadyEnabled`] if `sync_from_usb`
+//! already started the CRS.
+//!
+//! The LSE doesn't divide 48 MHz: synchronized from it, HSI48 is trimmed to the nearest multiple,
+//! 1465 × 32.768 kHz = 48.005 MHz, which is 107 ppm fast. This is well within the 500 ppm USB
+//! tolerance.
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU8, Ordering};
+use core::task::Poll;
+
+use embassy_hal_internal::PeripheralType;
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::gpio::{AfType, Pull, SealedPin as _};
+use crate::interrupt::typelevel::Interrupt;
+use crate::pac::crs::vals::{Syncdiv, Syncpol, Syncsrc};
+use crate::rcc::HSI48_FREQ;
+use crate::time::Hertz;
+use crate::{interrupt, peripherals, rcc, Peri};
+
+static CRS_WAKER: AtomicWaker = AtomicWaker::new();
+/// Events received since the last call to [`Crs::wait_event`], as a bit set of [`Event`]s.
+static CRS_EVENTS: AtomicU8 = AtomicU8::new(0);
+
+/// CRS interrupt handler.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let regs = T::regs();
+        let isr = regs.isr().read();
+
+        let mut events = 0;
+        if isr.syncokf() {
+            events |= 1 << Event::SyncOk as u8;
+        }
+        if isr.syncwarnf() {
+            events |= 1 << Event::SyncWarning as u8;
+        }
+        if isr.syncmiss() {
+            events |= 1 << Event::SyncMiss as u8;
+        }
+        if isr.syncerr() {
+            events |= 1 << Event::SyncError as u8;
+        }
+        if isr.trimovf() {
+            events |= 1 << Event::TrimOverflow as u8;
+        }
+
+        regs.icr().write(|w| {
+            w.set_syncokc(true);
+            w.set_syncwarnc(true);
+            w.set_errc(true);
+            w.set_esyncc(true);
+        });
+
+        if events != 0 {
+            CRS_EVENTS.fetch_or(events, Ordering::Release);
+            CRS_WAKER.wake();
+        }
+    }
+}
+
+/// Synchronization source.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SyncSource {
+    /// External signal on the CRS_SYNC pin, with its frequency.
+    Gpio(Hertz),
+    /// LSE clock, at 32.768 kHz. HSI48 then runs 107 ppm fast, see the module documentation.
+    Lse,
+    /// USB start-of-frame packets, at 1 kHz.
+    Usb,
+}
+
+impl SyncSource {
+    fn frequency(&self) -> Hertz {
+        match self {
+            Self::Gpio(freq) => *freq,
+            Self::Lse => Hertz(32_768),
+            Self::Usb => Hertz(1_000),
+        }
+    }
+}
+
+/// Synchronization signal polarity.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SyncPolarity {
+    /// Synchronize on rising edges.
+    RisingEdge,
+    /// Synchronize on falling edges.
+    FallingEdge,
+}
+
+/// CRS configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct Config {
+    /// Synchronization source.
+    pub source: SyncSource,
+    /// Synchronization signal polarity.
+    pub polarity: SyncPolarity,
+    /// Synchronization signal divider, as a power of two from 0 (not divided) to 7 (divided by 128).
+    pub divider: u8,
+    /// Frequency error limit, in counter steps. Errors above it trigger
+    /// [`Event::SyncWarning`], errors above three times it trigger [`Event::SyncError`].
+    ///
+    /// `None` computes it from the trimming step of about 0.14%.
+    pub error_limit: Option<u8>,
+    /// Adjust the trimming automatically.
+    pub auto_trim: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            source: SyncSource::Usb,
+            polarity: SyncPolarity::RisingEdge,
+            divider: 0,
+            error_limit: None,
+            auto_trim: true,
+        }
+    }
+}
+
+/// CRS configuration error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The synchronization divider is above 7.
+    InvalidDivider,
+    /// [`SyncSource::Gpio`] was selected without a sync pin, or another source with one.
+    WrongSyncSource,
+    /// The CRS is already enabled by [`crate::rcc::Hsi48Config::sync_from_usb`].
+    AlreadyEnabled,
+}
+
+/// CRS event.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// The frequency error is below the error limit, HSI48 is in sync.
+    SyncOk,
+    /// The frequency error is above the error limit, trimming is needed.
+    SyncWarning,
+    /// No synchronization pulse was received in time.
+    SyncMiss,
+    /// The frequency error is above three times the error limit.
+    SyncError,
+    /// Automatic trimming reached its limit.
+    TrimOverflow,
+}
+
+const EVENTS: [Event; 5] = [
+    Event::SyncError,
+    Event::SyncMiss,
+    Event::TrimOverflow,
+    Event::SyncWarning,
+    Event::SyncOk,
+];
+
+/// CRS driver.
+pub struct Crs<'d, T: Instance> {
+    _peri: Peri<'d, T>,
+}
+
+impl<'d, T: Instance> Crs<'d, T> {
+    /// Create a new CRS driver, synchronized from the LSE or USB.
+    pub fn new(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        // Use `new_with_sync_pin` to synchronize from a pin.
+        if matches!(config.source, SyncSource::Gpio(_)) {
+            return Err(ConfigError::WrongSyncSource);
+        }
+        Self::new_inner(peri, config)
+    }
+
+    /// Create a new CRS driver, synchronized from the CRS_SYNC pin.
+    pub fn new_with_sync_pin(
+        peri: Peri<'d, T>,
+        sync: Peri<'d, impl SyncPin<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        if !matches!(config.source, SyncSource::Gpio(_)) {
+            return Err(ConfigError::WrongSyncSource);
+        }
+        sync.set_as_af(sync.af_num(), AfType::input(Pull::None));
+        Self::new_inner(peri, config)
+    }
+
+    fn new_inner(peri: Peri<'d, T>, config: Config) -> Result<Self, ConfigError> {
+        if config.divider > 7 {
+            return Err(ConfigError::InvalidDivider);
+        }
+        // The CRS registers read as zero while its clock is off.
+        if T::regs().cr().read().cen() {
+            return Err(ConfigError::AlreadyEnabled);
+        }
+
+        rcc::enable_and_reset::<T>();
+
+        // Round to the nearest number of HSI48 periods per synchronization period.
+        let sync_freq = config.source.frequency().0 >> config.divider;
+        let reload = ((HSI48_FREQ.0 + sync_freq / 2) / sync_freq - 1) as u16;
+        let error_limit = config
+            .error_limit
+            .unwrap_or(((reload as u32 + 1) * 14).div_ceil(20_000) as u8);
+
+        let regs = T::regs();
+        regs.cfgr().write(|w| {
+            w.set_reload(reload);
+            w.set_felim(error_limit);
+            w.set_syncdiv(Syncdiv::from_bits(config.divider));
+            w.set_syncsrc(match config.source {
+                SyncSource::Gpio(_) => Syncsrc::GPIO,
+                SyncSource::Lse => Syncsrc::LSE,
+                SyncSource::Usb => Syncsrc::USB,
+            });
+            w.set_syncpol(match config.polarity {
+                SyncPolarity::RisingEdge => Syncpol::RISING_EDGE,
+                SyncPolarity::FallingEdge => Syncpol::FALLING_EDGE,
+            });
+        });
+
+        CRS_EVENTS.store(0, Ordering::Relaxed);
+        regs.icr().write(|w| {
+            w.set_syncokc(true);
+            w.set_syncwarnc(true);
+            w.set_errc(true);
+            w.set_esyncc(true);
+        });
+        regs.cr().modify(|w| {
+            w.set_syncokie(true);
+            w.set_syncwarnie(true);
+            w.set_errie(true);
+            w.set_autotrimen(config.auto_trim);
+            w.set_cen(true);
+        });
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        Ok(Self { _peri: peri })
+    }
+
+    /// Wait for the next CRS event.
+    ///
+    /// Events received since the last call are returned first, most severe first. Events of the
+    /// same kind received in a row are reported once.
+    pub async fn wait_event(&mut self) -> Event {
+        poll_fn(|cx| {
+            CRS_WAKER.register(cx.waker());
+
+            let events = CRS_EVENTS.load(Ordering::Acquire);
+            match EVENTS.iter().find(|&&e| events & (1 << e as u8) != 0) {
+                Some(&event) => {
+                    CRS_EVENTS.fetch_and(!(1 << event as u8), Ordering::AcqRel);
+                    Poll::Ready(event)
+                }
+                None => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// Current HSI48 trimming value.
+    pub fn trim(&self) -> u8 {
+        T::regs().cr().read().trim()
+    }
+
+    /// Set the HSI48 trimming value. With automatic trimming, it is only the starting point.
+    pub fn set_trim(&mut self, trim: u8) {
+        T::regs().cr().modify(|w| w.set_trim(trim));
+    }
+
+    /// Frequency error captured at the last synchronization, in counter steps.
+    ///
+    /// The error is positive when HSI48 runs too fast, negative when it runs too slow.
+    pub fn frequency_error(&self) -> i32 {
+        let isr = T::regs().isr().read();
+        // The counter is still counting down when HSI48 runs too slow.
+        if isr.fedir() {
+            -(isr.fecap() as i32)
+        } else {
+            isr.fecap() as i32
+        }
+    }
+
+    /// Generate a synchronization event by software, for example to test the configuration.
+    pub fn software_sync(&mut self) {
+        T::regs().cr().modify(|w| w.set_swsync(true));
+    }
+}
+
+impl<'d, T: Instance> Drop for Crs<'d, T> {
+    fn drop(&mut self) {
+        T::regs().cr().modify(|w| {
+            w.set_cen(false);
+            w.set_syncokie(false);
+            w.set_syncwarnie(false);
+            w.set_errie(false);
+        });
+        rcc::disable::<T>();
+    }
+}
+
+trait SealedInstance {
+    fn regs() -> crate::pac::crs::Crs;
+}
+
+/// CRS instance trait.
+#[allow(private_bounds)]
+pub trait Instance: SealedInstance + PeripheralType + rcc::RccPeripheral + 'static {
+    /// Interrupt for this CRS instance.
+    type Interrupt: interrupt::typelevel::Interrupt;
+}
+
+pin_trait!(SyncPin, Instance);
+
+foreach_interrupt!(
+    ($inst:ident, crs, CRS, GLOBAL, $irq:ident) => {
+        impl SealedInstance for peripherals::$inst {
+            fn regs() -> crate::pac::crs::Crs {
+                crate::pac::$inst
+            }
+        }
+
+        impl Instance for peripherals::$inst {
+            type Interrupt = crate::interrupt::typelevel::$irq;
+        }
+    };
+);
@@ -96,6 +96,27 @@ pub enum ValueArray<'a> {
     Bit12Right(&'a [u16]),
 }
 
+/// Built-in waveform generator of a DAC channel.
+///
+/// The generated value is added to the channel's data register on every trigger event,
+/// so triggering must be enabled for the waveform to advance.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Waveform {
+    /// No waveform generation, the data register is output as-is.
+    Disabled,
+    /// Pseudo-random noise from the LFSR, using the `unmasked_bits` lowest bits (1..=12).
+    Noise {
+        /// Number of unmasked LFSR bits.
+        unmasked_bits: u8,
+    },
+    /// Triangle wave with an amplitude of `2^amplitude_bits - 1` (1..=12).
+    Triangle {
+        /// Amplitude of the triangle, as a power of two.
+        amplitude_bits: u8,
+    },
+}
+
 /// Driver for a single DAC channel.
 ///
 /// If you want to use both channels, either together or independently,
@@ -151,6 +172,16 @@ impl<'d, T: Instance, C: Channel> DacChannel<'d, T, C, Async> {
     /// flag can be set. This configures a circular DMA transfer that continually outputs
     /// `data`. Note that for performance reasons in circular mode the transfer-complete
     /// interrupt is disabled.
+    ///
+    /// The sample rate is set by the trigger source: select a timer with
+    /// [`DacChannel::set_trigger()`] and enable triggering before starting the transfer.
+    ///
+    /// ```ignore
+    /// dac.set_trigger(TriggerSel::Tim6);
+    /// dac.set_triggering(true);
+    /// // Configure TIM6 to emit an update TRGO at the sample rate, then:
+    /// dac.write(ValueArray::Bit12Right(&SINE), true).await;
+    /// ```
     #[cfg(not(gpdma))]
     pub async fn write(&mut self, data: ValueArray<'_>, circular: bool) {
         // Enable DAC and DMA
@@ -304,6 +335,51 @@ impl<'d, T: Instance, C: Channel, M: PeriMode> DacChannel<'d, T, C, M> {
         });
     }
 
+    /// Enable or disable the output buffer of this channel.
+    ///
+    /// The buffer reduces output impedance so external loads can be driven directly; disabling
+    /// it allows the output to reach closer to the supply rails.
+    ///
+    /// This method disables the channel, so you may need to re-enable afterwards.
+    #[cfg(any(dac_v1, dac_v2))]
+    pub fn set_output_buffer(&mut self, on: bool) {
+        critical_section::with(|_| {
+            T::regs().cr().modify(|reg| {
+                reg.set_en(C::IDX, false);
+                reg.set_boff(C::IDX, !on);
+            });
+        });
+    }
+
+    /// Configure the built-in noise or triangle waveform generator.
+    ///
+    /// The waveform is offset by the value last written with [`DacChannel::set()`], and
+    /// advances on each trigger, see [`DacChannel::set_trigger()`] and
+    /// [`DacChannel::set_triggering()`].
+    ///
+    /// This method disables the channel, so you may need to re-enable afterwards.
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        let (wave, mamp) = match waveform {
+            Waveform::Disabled => (crate::pac::dac::vals::Wave::DISABLED, 0),
+            Waveform::Noise { unmasked_bits } => {
+                assert!((1..=12).contains(&unmasked_bits));
+                (crate::pac::dac::vals::Wave::NOISE, unmasked_bits - 1)
+            }
+            Waveform::Triangle { amplitude_bits } => {
+                assert!((1..=12).contains(&amplitude_bits));
+                (crate::pac::dac::vals::Wave::TRIANGLE, amplitude_bits - 1)
+            }
+        };
+
+        critical_section::with(|_| {
+            T::regs().cr().modify(|reg| {
+                reg.set_en(C::IDX, false);
+                reg.set_wave(C::IDX, wave);
+                reg.set_mamp(C::IDX, mamp);
+            });
+        });
+    }
+
     /// Write a new value to this channel.
     ///
     /// If triggering is not enabled, the new value is immediately output; otherwise,
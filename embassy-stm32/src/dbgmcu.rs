@@ -0,0 +1,86 @@
+//! Debug support (DBGMCU)
+//!
+//! Controls whether the debugger stays connected in low-power modes, and which peripherals stop
+//! counting while the core is halted by the debugger. Both can be changed at runtime, for example
+//! to take power measurements with the shipping configuration without reflashing.
+
+/// Keep the debug connection and the core clocks running in Sleep, Stop and Standby modes.
+///
+/// This raises the power consumption in these modes. [`crate::Config::enable_debug_during_sleep`]
+/// sets the initial value.
+pub fn set_debug_during_sleep(enable: bool) {
+    crate::pac::DBGMCU.cr().modify(|cr| {
+        #[cfg(dbgmcu_h5)]
+        {
+            cr.set_stop(enable);
+            cr.set_standby(enable);
+        }
+        #[cfg(any(dbgmcu_f0, dbgmcu_c0, dbgmcu_g0, dbgmcu_u0, dbgmcu_u5, dbgmcu_wba, dbgmcu_l5))]
+        {
+            cr.set_dbg_stop(enable);
+            cr.set_dbg_standby(enable);
+        }
+        #[cfg(any(
+            dbgmcu_f1, dbgmcu_f2, dbgmcu_f3, dbgmcu_f4, dbgmcu_f7, dbgmcu_g4, dbgmcu_l0, dbgmcu_l1, dbgmcu_l4,
+            dbgmcu_wb, dbgmcu_wl
+        ))]
+        {
+            cr.set_dbg_sleep(enable);
+            cr.set_dbg_stop(enable);
+            cr.set_dbg_standby(enable);
+        }
+        #[cfg(dbgmcu_h7)]
+        {
+            cr.set_d1dbgcken(enable);
+            cr.set_d3dbgcken(enable);
+            cr.set_dbgsleep_d1(enable);
+            cr.set_dbgstby_d1(enable);
+            cr.set_dbgstop_d1(enable);
+        }
+    });
+}
+
+/// Peripheral that can be frozen while the core is halted.
+#[cfg(any(dbgmcu_l4, dbgmcu_wb, dbgmcu_wl))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+pub enum Freeze {
+    Tim1,
+    Tim2,
+    Tim16,
+    Tim17,
+    Rtc,
+    Wwdg,
+    Iwdg,
+    I2c1,
+    #[cfg(any(dbgmcu_l4, dbgmcu_wl))]
+    I2c2,
+    I2c3,
+    Lptim1,
+    Lptim2,
+}
+
+/// Stop `peripheral` while the core is halted by the debugger, or let it run.
+///
+/// Freezing the watchdogs avoids resets while stepping through code. Timers, I2C timeouts and the
+/// RTC otherwise keep running when the core is halted.
+#[cfg(any(dbgmcu_l4, dbgmcu_wb, dbgmcu_wl))]
+pub fn set_freeze(peripheral: Freeze, freeze: bool) {
+    let regs = crate::pac::DBGMCU;
+    critical_section::with(|_| match peripheral {
+        Freeze::Tim2 => regs.apb1fzr1().modify(|w| w.set_dbg_tim2_stop(freeze)),
+        Freeze::Rtc => regs.apb1fzr1().modify(|w| w.set_dbg_rtc_stop(freeze)),
+        Freeze::Wwdg => regs.apb1fzr1().modify(|w| w.set_dbg_wwdg_stop(freeze)),
+        Freeze::Iwdg => regs.apb1fzr1().modify(|w| w.set_dbg_iwdg_stop(freeze)),
+        Freeze::I2c1 => regs.apb1fzr1().modify(|w| w.set_dbg_i2c1_stop(freeze)),
+        #[cfg(any(dbgmcu_l4, dbgmcu_wl))]
+        Freeze::I2c2 => regs.apb1fzr1().modify(|w| w.set_dbg_i2c2_stop(freeze)),
+        Freeze::I2c3 => regs.apb1fzr1().modify(|w| w.set_dbg_i2c3_stop(freeze)),
+        Freeze::Lptim1 => regs.apb1fzr1().modify(|w| w.set_dbg_lptim1_stop(freeze)),
+        Freeze::Lptim2 => regs.apb1fzr2().modify(|w| w.set_dbg_lptim2_stop(freeze)),
+        Freeze::Tim1 => regs.apb2fzr().modify(|w| w.set_dbg_tim1_stop(freeze)),
+        Freeze::Tim16 => regs.apb2fzr().modify(|w| w.set_dbg_tim16_stop(freeze)),
+        Freeze::Tim17 => regs.apb2fzr().modify(|w| w.set_dbg_tim17_stop(freeze)),
+    });
+}
@@ -0,0 +1,338 @@
+//! Buffered `defmt` logger
+//!
+//! A `defmt` global logger that only copies the encoded frames to a RAM buffer, and a task that
+//! sends them over a UART ([`run`]). The UART is [suspended](crate::usart::UartTx::suspend) while
+//! the buffer is empty, so logging neither keeps the chip out of the low-power stop modes nor
+//! busy waits on the transmitter.
+//!
+//! With the `defmt-buffer-rtt` feature, `run_rtt` sends them over RTT instead. It defines its own
+//! `_SEGGER_RTT` control block, so it can't be combined with `defmt-rtt` or `rtt-target`.
+//!
+//! Frames logged before [`init`], or while the buffer is full, are dropped. `defmt::flush()`
+//! sends the buffered frames synchronously, e.g. before a reset.
+//!
+//! ```rust,ignore
+//! static BUFFER: StaticCell<[u8; 1024]> = StaticCell::new();
+//!
+//! #[embassy_executor::task]
+//! async fn logger(mut tx: UartTx<'static, Async>) {
+//!     defmt_buffer::run(&mut tx).await
+//! }
+//!
+//! defmt_buffer::init(BUFFER.init([0; 1024]));
+//! let tx = UartTx::new(p.USART1, p.PA9, p.DMA1_CH4, Default::default()).unwrap();
+//! spawner.spawn(logger(tx)).unwrap();
+//! ```
+use core::cell::Cell;
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::ptr::addr_of_mut;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::Poll;
+
+use embassy_hal_internal::atomic_ring_buffer::RingBuffer;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::mode::Async;
+use crate::usart::{TxHandle, UartTx};
+
+static BUFFER: RingBuffer = RingBuffer::new();
+static WAKER: AtomicWaker = AtomicWaker::new();
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+/// A frame was logged since the sink task last checked.
+#[cfg(feature = "defmt-buffer-rtt")]
+static NEW_FRAME: AtomicBool = AtomicBool::new(false);
+
+/// The logger is in use, to catch logging from within the logger.
+static TAKEN: AtomicBool = AtomicBool::new(false);
+/// Part of the current frame did not fit in the buffer.
+static OVERFLOW: AtomicBool = AtomicBool::new(false);
+static mut CS_RESTORE: critical_section::RestoreState = critical_section::RestoreState::invalid();
+static mut ENCODER: defmt::Encoder = defmt::Encoder::new();
+
+/// Where the frames are sent, registered by the sink task for `defmt::flush()`.
+#[derive(Clone, Copy)]
+enum Sink {
+    None,
+    Uart(TxHandle),
+    #[cfg(feature = "defmt-buffer-rtt")]
+    Rtt,
+}
+
+static SINK: Mutex<CriticalSectionRawMutex, Cell<Sink>> = Mutex::new(Cell::new(Sink::None));
+/// Length of the chunk at the head of the buffer that [`run`] is sending, or 0.
+static SENDING: AtomicUsize = AtomicUsize::new(0);
+/// The DMA transfer of the `SENDING` chunk has started.
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Start buffering log frames in `buffer`.
+pub fn init(buffer: &'static mut [u8]) {
+    critical_section::with(|_| unsafe { BUFFER.init(buffer.as_mut_ptr(), buffer.len()) });
+}
+
+/// Number of frames dropped because the buffer was full.
+pub fn dropped_frames() -> usize {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Send the buffered frames over `tx` as they are logged.
+///
+/// Run this from a low-priority task: it only writes to the UART when the executor is awake
+/// anyway, and suspends the UART in between.
+pub async fn run(tx: &mut UartTx<'_, Async>) -> ! {
+    let handle = tx.handle();
+    SINK.lock(|s| s.set(Sink::Uart(handle)));
+
+    loop {
+        tx.suspend();
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if BUFFER.is_empty() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await;
+
+        tx.resume();
+        loop {
+            // Claim the chunk at the head of the buffer. If `defmt::flush()` runs before its
+            // transfer starts, it sends the chunk itself, otherwise it waits for the transfer.
+            let (ptr, n) = critical_section::with(|_| {
+                let (ptr, n) = unsafe { BUFFER.reader() }.pop_buf();
+                SENDING.store(n, Ordering::Relaxed);
+                STARTED.store(false, Ordering::Relaxed);
+                (ptr, n)
+            });
+            if n == 0 {
+                break;
+            }
+
+            let data = unsafe { core::slice::from_raw_parts(ptr, n) };
+            let mut write = pin!(tx.write(data));
+            // Poll in a critical section, so that `defmt::flush()` never sees a transfer half
+            // started.
+            poll_fn(|cx| {
+                critical_section::with(|_| {
+                    if SENDING.load(Ordering::Relaxed) == 0 {
+                        // Sent by `defmt::flush()`.
+                        return Poll::Ready(());
+                    }
+                    if !STARTED.swap(true, Ordering::Relaxed) {
+                        handle.clear_tc();
+                    }
+                    // There is nowhere to report an error to, the host decoder skips the broken
+                    // frame.
+                    write.as_mut().poll(cx).map(|_| ())
+                })
+            })
+            .await;
+
+            critical_section::with(|_| {
+                let n = SENDING.swap(0, Ordering::Relaxed);
+                unsafe { BUFFER.reader() }.pop_done(n);
+            });
+        }
+    }
+}
+
+/// Send the buffered frames over RTT, through up channel 0 of a `_SEGGER_RTT` control block
+/// using `rtt_buffer`.
+///
+/// The debug probe reads the channel at its own pace: frames that don't fit in `rtt_buffer` stay
+/// in the log buffer until the next frame is logged, this never polls.
+#[cfg(feature = "defmt-buffer-rtt")]
+pub async fn run_rtt(rtt_buffer: &'static mut [u8]) -> ! {
+    critical_section::with(|_| {
+        rtt::init(rtt_buffer);
+        SINK.lock(|s| s.set(Sink::Rtt));
+    });
+
+    loop {
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if NEW_FRAME.swap(false, Ordering::Relaxed) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        critical_section::with(|_| rtt::push(&mut unsafe { BUFFER.reader() }));
+    }
+}
+
+#[defmt::global_logger]
+struct Logger;
+
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {
+        let restore = unsafe { critical_section::acquire() };
+        if TAKEN.load(Ordering::Relaxed) {
+            panic!("defmt logger taken reentrantly")
+        }
+        TAKEN.store(true, Ordering::Relaxed);
+        OVERFLOW.store(false, Ordering::Relaxed);
+        unsafe {
+            CS_RESTORE = restore;
+            (*addr_of_mut!(ENCODER)).start_frame(write);
+        }
+    }
+
+    unsafe fn flush() {
+        // Interrupts are disabled, the sink task can't run meanwhile.
+        let mut reader = unsafe { BUFFER.reader() };
+        match SINK.lock(|s| s.get()) {
+            Sink::None => {}
+            Sink::Uart(tx) => {
+                let sending = SENDING.swap(0, Ordering::Relaxed);
+                if sending > 0 && STARTED.load(Ordering::Relaxed) {
+                    // Let the DMA finish the chunk `run` started.
+                    tx.blocking_flush();
+                    reader.pop_done(sending);
+                }
+                tx.blocking_write(core::iter::from_fn(|| reader.pop_one()));
+                tx.blocking_flush();
+            }
+            #[cfg(feature = "defmt-buffer-rtt")]
+            Sink::Rtt => loop {
+                rtt::push(&mut reader);
+                if BUFFER.is_empty() || !rtt::blocking() {
+                    break;
+                }
+            },
+        }
+    }
+
+    unsafe fn release() {
+        unsafe { (*addr_of_mut!(ENCODER)).end_frame(write) };
+        if OVERFLOW.load(Ordering::Relaxed) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+            // Terminate the truncated frame, so the decoder can find the start of the next one.
+            // `write` keeps a byte free for it.
+            unsafe { BUFFER.writer() }.push_one(0);
+        }
+        TAKEN.store(false, Ordering::Relaxed);
+        let restore = unsafe { CS_RESTORE };
+        unsafe { critical_section::release(restore) };
+        #[cfg(feature = "defmt-buffer-rtt")]
+        NEW_FRAME.store(true, Ordering::Relaxed);
+        WAKER.wake();
+    }
+
+    unsafe fn write(bytes: &[u8]) {
+        unsafe { (*addr_of_mut!(ENCODER)).write(bytes, write) };
+    }
+}
+
+fn write(bytes: &[u8]) {
+    if OVERFLOW.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut writer = unsafe { BUFFER.writer() };
+    let [first, second] = writer.push_slices();
+    // Keep a byte free for the terminator of a truncated frame.
+    if first.len() + second.len() <= bytes.len() {
+        OVERFLOW.store(true, Ordering::Relaxed);
+        return;
+    }
+    let n = first.len().min(bytes.len());
+    first[..n].copy_from_slice(&bytes[..n]);
+    second[..bytes.len() - n].copy_from_slice(&bytes[n..]);
+    writer.push_done(bytes.len());
+}
+
+/// Minimal SEGGER RTT control block, with a single up channel.
+#[cfg(feature = "defmt-buffer-rtt")]
+mod rtt {
+    use core::ptr::{addr_of, addr_of_mut};
+    use core::sync::atomic::{compiler_fence, AtomicUsize, Ordering};
+
+    use embassy_hal_internal::atomic_ring_buffer::Reader;
+
+    /// Up channel mode, set by the host in the channel flags.
+    const MODE_MASK: usize = 0b11;
+    /// The host reads the channel, wait for it instead of dropping data.
+    const MODE_BLOCK_IF_FULL: usize = 2;
+
+    #[repr(C)]
+    struct Channel {
+        name: *const u8,
+        buffer: *mut u8,
+        size: usize,
+        write: AtomicUsize,
+        read: AtomicUsize,
+        flags: AtomicUsize,
+    }
+
+    #[repr(C)]
+    struct ControlBlock {
+        id: [u8; 16],
+        max_up_channels: usize,
+        max_down_channels: usize,
+        up: Channel,
+    }
+
+    #[no_mangle]
+    static mut _SEGGER_RTT: ControlBlock = ControlBlock {
+        id: [0; 16],
+        max_up_channels: 0,
+        max_down_channels: 0,
+        up: Channel {
+            name: core::ptr::null(),
+            buffer: core::ptr::null_mut(),
+            size: 0,
+            write: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            flags: AtomicUsize::new(0),
+        },
+    };
+
+    /// Set up up channel 0, named `defmt`, with `buffer`.
+    pub(super) fn init(buffer: &'static mut [u8]) {
+        unsafe {
+            let cb = &mut *addr_of_mut!(_SEGGER_RTT);
+            cb.max_up_channels = 1;
+            cb.max_down_channels = 0;
+            cb.up.name = b"defmt\0".as_ptr();
+            cb.up.buffer = buffer.as_mut_ptr();
+            cb.up.size = buffer.len();
+            // The host scans RAM for the ID, it must only find a complete control block.
+            compiler_fence(Ordering::SeqCst);
+            addr_of_mut!(cb.id).write_volatile(*b"SEGGER RTT\0\0\0\0\0\0");
+        }
+    }
+
+    /// Move as many bytes as fit from `reader` to the up channel.
+    pub(super) fn push(reader: &mut Reader<'_>) {
+        let up = unsafe { &*addr_of!(_SEGGER_RTT.up) };
+        if up.size == 0 {
+            return;
+        }
+
+        let mut write = up.write.load(Ordering::Relaxed);
+        loop {
+            // One slot stays empty, to tell a full channel from an empty one.
+            let next = (write + 1) % up.size;
+            if next == up.read.load(Ordering::Acquire) {
+                break;
+            }
+            let Some(byte) = reader.pop_one() else {
+                break;
+            };
+            unsafe { up.buffer.add(write).write_volatile(byte) };
+            write = next;
+        }
+        up.write.store(write, Ordering::Release);
+    }
+
+    /// Whether the host asked to wait for it when the channel is full.
+    pub(super) fn blocking() -> bool {
+        let up = unsafe { &*addr_of!(_SEGGER_RTT.up) };
+        up.flags.load(Ordering::Relaxed) & MODE_MASK == MODE_BLOCK_IF_FULL
+    }
+}
@@ -0,0 +1,114 @@
+//! Cycle-counter based blocking delay
+//!
+//! Busy waits with the core clock instead of the `embassy-time` tick, for bit-banged protocols
+//! that need microsecond or sub-microsecond timing. The delay is computed from the current
+//! SYSCLK on every call, so it stays correct after [`crate::rcc::reinit`].
+//!
+//! Interrupts served during a delay make it longer; run the delay in a critical section if that
+//! matters.
+//!
+//! ```rust,ignore
+//! let mut cp = cortex_m::Peripherals::take().unwrap();
+//! let mut delay = CycleDelay::new(&mut cp.DCB, &mut cp.DWT);
+//! pin.set_high();
+//! delay.delay_us(10);
+//! pin.set_low();
+//! ```
+#[cfg(not(armv6m))]
+use cortex_m::peripheral::{DCB, DWT};
+
+/// Core clock cycles per iteration of the `cortex_m::asm::delay` loop, which assumes 2.
+#[cfg(armv6m)]
+const DELAY_LOOP_CYCLES: u64 = 3;
+
+/// Blocking delay counted in core clock cycles.
+///
+/// Uses the DWT cycle counter, or a calibrated busy loop on Cortex-M0+ cores, which have no
+/// cycle counter.
+pub struct CycleDelay {
+    _private: (),
+}
+
+impl CycleDelay {
+    /// Create a delay, enabling the DWT cycle counter.
+    #[cfg(not(armv6m))]
+    pub fn new(dcb: &mut DCB, dwt: &mut DWT) -> Self {
+        dcb.enable_trace();
+        dwt.enable_cycle_counter();
+        Self { _private: () }
+    }
+
+    /// Create a delay.
+    #[cfg(armv6m)]
+    pub fn new() -> Self {
+        Self { _private: () }
+    }
+
+    /// Wait for at least `us` microseconds.
+    pub fn delay_us(&mut self, us: u32) {
+        self.delay_cycles(Self::sysclk() * us as u64 / 1_000_000);
+    }
+
+    /// Wait for at least `ns` nanoseconds, rounded up to a whole core clock cycle.
+    pub fn delay_ns(&mut self, ns: u32) {
+        self.delay_cycles((Self::sysclk() * ns as u64).div_ceil(1_000_000_000));
+    }
+
+    fn sysclk() -> u64 {
+        unsafe { crate::rcc::get_freqs() }.sys.to_hertz().unwrap().0 as u64
+    }
+
+    #[cfg(not(armv6m))]
+    fn delay_cycles(&mut self, cycles: u64) {
+        let mut remaining = cycles;
+        let mut last = DWT::cycle_count();
+        while remaining > 0 {
+            let now = DWT::cycle_count();
+            remaining = remaining.saturating_sub(now.wrapping_sub(last) as u64);
+            last = now;
+        }
+    }
+
+    #[cfg(armv6m)]
+    fn delay_cycles(&mut self, cycles: u64) {
+        let mut loops = cycles.div_ceil(DELAY_LOOP_CYCLES) * 2;
+        while loops > 0 {
+            let chunk = loops.min(u32::MAX as u64);
+            cortex_m::asm::delay(chunk as u32);
+            loops -= chunk;
+        }
+    }
+}
+
+#[cfg(armv6m)]
+impl Default for CycleDelay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl embedded_hal_1::delay::DelayNs for CycleDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        CycleDelay::delay_ns(self, ns)
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        CycleDelay::delay_us(self, us)
+    }
+}
+
+/// Performs a busy-wait delay for a specified number of microseconds.
+///
+/// For short waits inside drivers, which don't own the DWT.
+#[allow(unused)]
+pub(crate) fn blocking_delay_us(us: u32) {
+    #[cfg(feature = "time")]
+    embassy_time::block_for(embassy_time::Duration::from_micros(us as u64));
+    #[cfg(not(feature = "time"))]
+    {
+        let freq = unsafe { crate::rcc::get_freqs() }.sys.to_hertz().unwrap().0 as u64;
+        let us = us as u64;
+        let cycles = freq * us / 1_000_000;
+        cortex_m::asm::delay(cycles as u32);
+    }
+}
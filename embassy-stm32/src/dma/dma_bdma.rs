@@ -1,6 +1,6 @@
 use core::future::{poll_fn, Future};
 use core::pin::Pin;
-use core::sync::atomic::{fence, AtomicUsize, Ordering};
+use core::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
 use core::task::{Context, Poll, Waker};
 
 use embassy_hal_internal::Peri;
@@ -140,6 +140,7 @@ mod dma_only {
             match raw {
                 Dir::MemoryToPeripheral => Self::MEMORY_TO_PERIPHERAL,
                 Dir::PeripheralToMemory => Self::PERIPHERAL_TO_MEMORY,
+                Dir::MemoryToMemory => Self::MEMORY_TO_MEMORY,
             }
         }
     }
@@ -235,6 +236,8 @@ mod bdma_only {
             match raw {
                 Dir::MemoryToPeripheral => Self::FROM_MEMORY,
                 Dir::PeripheralToMemory => Self::FROM_PERIPHERAL,
+                // With MEM2MEM set, the "peripheral" address is the source.
+                Dir::MemoryToMemory => Self::FROM_PERIPHERAL,
             }
         }
     }
@@ -243,15 +246,33 @@ mod bdma_only {
 pub(crate) struct ChannelState {
     waker: AtomicWaker,
     complete_count: AtomicUsize,
+    error: AtomicBool,
 }
 
 impl ChannelState {
     pub(crate) const NEW: Self = Self {
         waker: AtomicWaker::new(),
         complete_count: AtomicUsize::new(0),
+        error: AtomicBool::new(false),
     };
 }
 
+/// DMA transfer error.
+///
+/// The hardware aborted the transfer because of a bus error, usually caused by an invalid
+/// memory address (e.g. a buffer in a memory region the DMA cannot access). The channel is
+/// disabled and can be reused for a new transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TransferError {
+    /// Peripheral address register of the channel at the time of the error.
+    pub peripheral_address: u32,
+    /// Memory address register of the channel at the time of the error.
+    pub memory_address: u32,
+    /// Number of transfers that were still remaining when the error occurred.
+    pub remaining_transfers: u16,
+}
+
 /// safety: must be called only once
 pub(crate) unsafe fn init(
     cs: critical_section::CriticalSection,
@@ -286,7 +307,12 @@ impl AnyChannel {
                 let isr = r.isr(info.num / 4).read();
 
                 if isr.teif(info.num % 4) {
-                    panic!("DMA: error on DMA@{:08x} channel {}", r.as_ptr() as u32, info.num);
+                    // The hardware already disabled the channel.
+                    error!("DMA: error on DMA@{:08x} channel {}", r.as_ptr() as u32, info.num);
+                    r.ifcr(info.num / 4).write(|w| w.set_teif(info.num % 4, true));
+                    state.error.store(true, Ordering::Release);
+                    state.waker.wake();
+                    return;
                 }
 
                 if isr.htif(info.num % 4) && cr.read().htie() {
@@ -307,7 +333,12 @@ impl AnyChannel {
                 let cr = r.ch(info.num).cr();
 
                 if isr.teif(info.num) {
-                    panic!("DMA: error on BDMA@{:08x} channel {}", r.as_ptr() as u32, info.num);
+                    // The hardware already disabled the channel.
+                    error!("DMA: error on BDMA@{:08x} channel {}", r.as_ptr() as u32, info.num);
+                    r.ifcr().write(|w| w.set_teif(info.num, true));
+                    state.error.store(true, Ordering::Release);
+                    state.waker.wake();
+                    return;
                 }
 
                 if isr.htif(info.num) && cr.read().htie() {
@@ -366,13 +397,19 @@ impl AnyChannel {
                 fence(Ordering::SeqCst);
 
                 state.complete_count.store(0, Ordering::Release);
+                state.error.store(false, Ordering::Release);
                 self.clear_irqs();
 
                 ch.par().write_value(peri_addr as u32);
                 ch.m0ar().write_value(mem_addr as u32);
                 ch.ndtr().write_value(pac::dma::regs::Ndtr(mem_len as _));
                 ch.fcr().write(|w| {
-                    if let Some(fth) = options.fifo_threshold {
+                    // Direct mode is not allowed for memory-to-memory transfers.
+                    let fifo_threshold = match dir {
+                        Dir::MemoryToMemory => Some(options.fifo_threshold.unwrap_or(FifoThreshold::Full)),
+                        _ => options.fifo_threshold,
+                    };
+                    if let Some(fth) = fifo_threshold {
                         // FIFO mode
                         w.set_dmdis(pac::dma::vals::Dmdis::DISABLED);
                         w.set_fth(fth.into());
@@ -387,7 +424,7 @@ impl AnyChannel {
                     w.set_psize(peripheral_size.into());
                     w.set_pl(options.priority.into());
                     w.set_minc(incr_mem);
-                    w.set_pinc(false);
+                    w.set_pinc(dir == Dir::MemoryToMemory);
                     w.set_teie(true);
                     w.set_htie(options.half_transfer_ir);
                     w.set_tcie(options.complete_transfer_ir);
@@ -411,6 +448,7 @@ impl AnyChannel {
                 let ch = r.ch(info.num);
 
                 state.complete_count.store(0, Ordering::Release);
+                state.error.store(false, Ordering::Release);
                 self.clear_irqs();
 
                 ch.par().write_value(peri_addr as u32);
@@ -420,6 +458,8 @@ impl AnyChannel {
                     w.set_psize(peripheral_size.into());
                     w.set_msize(mem_size.into());
                     w.set_minc(incr_mem);
+                    w.set_pinc(dir == Dir::MemoryToMemory);
+                    w.set_mem2mem(dir == Dir::MemoryToMemory);
                     w.set_dir(dir.into());
                     w.set_teie(true);
                     w.set_tcie(options.complete_transfer_ir);
@@ -433,6 +473,8 @@ impl AnyChannel {
     }
 
     fn start(&self) {
+        STATE[self.id as usize].error.store(false, Ordering::Release);
+
         let info = self.info();
         match self.info().dma {
             #[cfg(dma)]
@@ -515,6 +557,45 @@ impl AnyChannel {
         }
     }
 
+    /// Restart a channel paused with `request_pause`, continuing where it stopped.
+    ///
+    /// `started_len` is the transfer count the channel was (re)started with; it is updated
+    /// for the next resume.
+    fn resume(&self, started_len: &mut u16) {
+        let info = self.info();
+
+        // The controller restarts from the programmed base addresses, so advance them past
+        // the data already transferred.
+        macro_rules! advance {
+            ($ch:expr, $mar:ident) => {{
+                let ch = $ch;
+                let cr = ch.cr().read();
+                let remaining = ch.ndtr().read().ndt();
+                let done = (*started_len - remaining) as u32;
+                if cr.minc() {
+                    ch.$mar()
+                        .write_value(ch.$mar().read() + done * (1 << cr.msize().to_bits()));
+                }
+                if cr.pinc() {
+                    ch.par()
+                        .write_value(ch.par().read() + done * (1 << cr.psize().to_bits()));
+                }
+                ch.ndtr().write(|w| w.set_ndt(remaining));
+                *started_len = remaining;
+
+                fence(Ordering::SeqCst);
+                ch.cr().modify(|w| w.set_en(true));
+            }};
+        }
+
+        match info.dma {
+            #[cfg(dma)]
+            DmaInfo::Dma(r) => advance!(r.st(info.num), m0ar),
+            #[cfg(bdma)]
+            DmaInfo::Bdma(r) => advance!(r.ch(info.num), mar),
+        }
+    }
+
     fn is_running(&self) -> bool {
         let info = self.info();
         match self.info().dma {
@@ -542,6 +623,35 @@ impl AnyChannel {
         }
     }
 
+    fn error(&self) -> Option<TransferError> {
+        let state: &ChannelState = &STATE[self.id as usize];
+        if !state.error.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let info = self.info();
+        Some(match info.dma {
+            #[cfg(dma)]
+            DmaInfo::Dma(r) => {
+                let ch = r.st(info.num);
+                TransferError {
+                    peripheral_address: ch.par().read(),
+                    memory_address: ch.m0ar().read(),
+                    remaining_transfers: ch.ndtr().read().ndt(),
+                }
+            }
+            #[cfg(bdma)]
+            DmaInfo::Bdma(r) => {
+                let ch = r.ch(info.num);
+                TransferError {
+                    peripheral_address: ch.par().read(),
+                    memory_address: ch.mar().read(),
+                    remaining_transfers: ch.ndtr().read().ndt(),
+                }
+            }
+        })
+    }
+
     fn disable_circular_mode(&self) {
         let info = self.info();
         match self.info().dma {
@@ -572,6 +682,7 @@ impl AnyChannel {
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Transfer<'a> {
     channel: Peri<'a, AnyChannel>,
+    started_len: u16,
 }
 
 impl<'a> Transfer<'a> {
@@ -664,6 +775,37 @@ impl<'a> Transfer<'a> {
         )
     }
 
+    /// Create a new memory-to-memory DMA transfer, copying `src` into `dst`.
+    ///
+    /// `src` and `dst` must have the same length, of at most 65535 words.
+    ///
+    /// On F2, F4 and F7, only DMA2 streams can do memory-to-memory transfers: a DMA1 stream doesn't
+    /// move any data.
+    pub fn new_copy<W: Word>(
+        channel: Peri<'a, impl Channel>,
+        src: &'a [W],
+        dst: &'a mut [W],
+        options: TransferOptions,
+    ) -> Self {
+        assert_eq!(src.len(), dst.len());
+
+        // Safety: both buffers are borrowed for the lifetime of the transfer.
+        unsafe {
+            Self::new_inner(
+                channel.into(),
+                MEMORY_REQUEST,
+                Dir::MemoryToMemory,
+                src.as_ptr() as *const u32,
+                dst.as_mut_ptr() as *mut u32,
+                dst.len(),
+                true,
+                W::size(),
+                W::size(),
+                options,
+            )
+        }
+    }
+
     unsafe fn new_inner(
         channel: Peri<'a, AnyChannel>,
         _request: Request,
@@ -690,7 +832,10 @@ impl<'a> Transfer<'a> {
             options,
         );
         channel.start();
-        Self { channel }
+        Self {
+            channel,
+            started_len: mem_len as u16,
+        }
     }
 
     /// Request the transfer to stop.
@@ -703,13 +848,27 @@ impl<'a> Transfer<'a> {
     }
 
     /// Request the transfer to pause, keeping the existing configuration for this channel.
-    /// To restart the transfer, call [`start`](Self::start) again.
+    /// To continue the transfer, call [`resume`](Self::resume).
+    ///
+    /// This can be used e.g. to hold a bulk transfer around a clock reconfiguration.
     ///
     /// This doesn't immediately stop the transfer, you have to wait until [`is_running`](Self::is_running) returns false.
     pub fn request_pause(&mut self) {
         self.channel.request_pause()
     }
 
+    /// Resume a transfer paused with [`request_pause`](Self::request_pause).
+    ///
+    /// The transfer continues with the data that was not transferred yet.
+    /// Must only be called once [`is_running`](Self::is_running) returned false.
+    pub fn resume(&mut self) {
+        assert!(!self.is_running(), "DMA transfer is not paused");
+        if self.get_remaining_transfers() == 0 || self.error().is_some() {
+            return;
+        }
+        self.channel.resume(&mut self.started_len)
+    }
+
     /// Return whether this transfer is still running.
     ///
     /// If this returns `false`, it can be because either the transfer finished, or
@@ -724,6 +883,23 @@ impl<'a> Transfer<'a> {
         self.channel.get_remaining_transfers()
     }
 
+    /// Returns the error that aborted this transfer, if any.
+    ///
+    /// A transfer that was aborted by an error is no longer running, so awaiting it
+    /// completes; check this afterwards, or use [`result`](Self::result).
+    pub fn error(&self) -> Option<TransferError> {
+        self.channel.error()
+    }
+
+    /// Wait until the transfer finishes, returning an error if it was aborted by a bus error.
+    pub async fn result(mut self) -> Result<(), TransferError> {
+        (&mut self).await;
+        match self.error() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
     /// Blocking wait until the transfer finishes.
     pub fn blocking_wait(mut self) {
         while self.is_running() {}
@@ -761,6 +937,32 @@ impl<'a> Future for Transfer<'a> {
     }
 }
 
+/// Request line used for memory-to-memory transfers (no peripheral request).
+#[cfg(any(dma_v2, bdma_v2, dmamux))]
+const MEMORY_REQUEST: Request = 0;
+#[cfg(not(any(dma_v2, bdma_v2, dmamux)))]
+const MEMORY_REQUEST: Request = ();
+
+/// Copy `src` into `dst` using a DMA channel, without involving the CPU.
+///
+/// Buffers longer than a single transfer allows are copied in chunks.
+/// This is useful to move large buffers between RAM regions, e.g. into memory shared with
+/// another core, while the CPU does other work.
+///
+/// # Panics
+///
+/// Panics if `src` and `dst` have different lengths.
+pub async fn copy<W: Word>(mut channel: Peri<'_, impl Channel>, src: &[W], dst: &mut [W]) {
+    assert_eq!(src.len(), dst.len(), "Source and destination must have the same length");
+
+    for (src, dst) in src.chunks(0xFFFF).zip(dst.chunks_mut(0xFFFF)) {
+        Transfer::new_copy(channel.reborrow(), src, dst, Default::default()).await;
+    }
+
+    // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+    fence(Ordering::SeqCst);
+}
+
 // ==============================
 
 struct DmaCtrlImpl<'a>(Peri<'a, AnyChannel>);
@@ -785,6 +987,10 @@ impl<'a> DmaCtrl for DmaCtrlImpl<'a> {
     fn set_waker(&mut self, waker: &Waker) {
         STATE[self.0.id as usize].waker.register(waker);
     }
+
+    fn transfer_error(&self) -> bool {
+        STATE[self.0.id as usize].error.load(Ordering::Acquire)
+    }
 }
 
 /// Ringbuffer for receiving data using DMA circular mode.
@@ -1088,3 +1294,160 @@ impl<'a, W: Word> Drop for WritableRingBuffer<'a, W> {
         fence(Ordering::SeqCst);
     }
 }
+
+/// Double-buffered reception using DMA circular mode.
+///
+/// The buffer is split in two halves. While the DMA fills one half, the other can be processed
+/// in place, without copying. Each half becomes available on the half-transfer and
+/// transfer-complete events respectively, which makes this suitable for continuous ADC
+/// sampling or audio streams.
+pub struct ReadableDoubleBuffer<'a, W: Word> {
+    channel: Peri<'a, AnyChannel>,
+    buffer: &'a mut [W],
+    /// Number of completed laps around `buffer`, as of the last sync.
+    laps: usize,
+    /// Total number of elements written by the DMA, as of the last sync.
+    dma_pos: usize,
+    /// Total number of elements handed out to the user.
+    read_pos: usize,
+}
+
+impl<'a, W: Word> ReadableDoubleBuffer<'a, W> {
+    /// Create a new double buffer.
+    ///
+    /// `buffer` must have an even, non-zero length.
+    pub unsafe fn new(
+        channel: Peri<'a, impl Channel>,
+        _request: Request,
+        peri_addr: *mut W,
+        buffer: &'a mut [W],
+        mut options: TransferOptions,
+    ) -> Self {
+        assert!(!buffer.is_empty() && buffer.len() % 2 == 0);
+
+        let channel: Peri<'a, AnyChannel> = channel.into();
+
+        options.half_transfer_ir = true;
+        options.complete_transfer_ir = true;
+        options.circular = true;
+
+        channel.configure(
+            _request,
+            Dir::PeripheralToMemory,
+            peri_addr as *mut u32,
+            buffer.as_mut_ptr() as *mut u32,
+            buffer.len(),
+            true,
+            W::size(),
+            W::size(),
+            options,
+        );
+
+        Self {
+            channel,
+            buffer,
+            laps: 0,
+            dma_pos: 0,
+            read_pos: 0,
+        }
+    }
+
+    /// Start the DMA transfer.
+    ///
+    /// You must call this after creating it for it to work.
+    pub fn start(&mut self) {
+        self.channel.start();
+    }
+
+    /// Length of each half, in elements.
+    pub fn half_len(&self) -> usize {
+        self.buffer.len() / 2
+    }
+
+    /// Wait for the next half to be filled, and process it with `f`.
+    ///
+    /// Halves are handed out in order. An error is returned if the DMA overwrote a half
+    /// before it was fully processed, in which case the data passed to `f` may be corrupted;
+    /// the next call continues with the most recently completed half.
+    ///
+    /// [`Error::Transfer`] is returned if the DMA aborted the transfer because of a bus error.
+    pub async fn read_half<R>(&mut self, f: impl FnOnce(&[W]) -> R) -> Result<R, Error> {
+        let half = self.half_len();
+
+        poll_fn(|cx| {
+            let state = &STATE[self.channel.id as usize];
+            state.waker.register(cx.waker());
+            if state.error.load(Ordering::Acquire) {
+                return Poll::Ready(Err(Error::Transfer));
+            }
+            self.sync();
+            if self.dma_pos >= self.read_pos + half {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await?;
+
+        if self.dma_pos > self.read_pos + self.buffer.len() {
+            // Skip ahead to the most recently completed half.
+            self.read_pos = (self.dma_pos / half - 1) * half;
+            return Err(Error::Overrun);
+        }
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+        fence(Ordering::SeqCst);
+
+        let start = self.read_pos % self.buffer.len();
+        let r = f(&self.buffer[start..start + half]);
+
+        fence(Ordering::SeqCst);
+        self.sync();
+        let overrun = self.dma_pos > self.read_pos + self.buffer.len();
+        self.read_pos += half;
+
+        if overrun {
+            Err(Error::Overrun)
+        } else {
+            Ok(r)
+        }
+    }
+
+    fn sync(&mut self) {
+        let len = self.buffer.len();
+        let mut ctrl = DmaCtrlImpl(self.channel.reborrow());
+
+        // The complete count must be read before NDTR, see `DmaIndex::dma_sync`.
+        self.laps += ctrl.reset_complete_count();
+        let pos = self.laps * len + len - ctrl.get_remaining_transfers();
+
+        // NDTR already wrapped but the transfer-complete interrupt was not handled yet.
+        self.dma_pos = if pos < self.dma_pos {
+            self.laps * len + len - 1
+        } else {
+            pos
+        };
+    }
+
+    /// Request the DMA to stop.
+    ///
+    /// This doesn't immediately stop the transfer, you have to wait until [`is_running`](Self::is_running) returns false.
+    pub fn request_stop(&mut self) {
+        self.channel.request_stop()
+    }
+
+    /// Return whether DMA is still running.
+    pub fn is_running(&mut self) -> bool {
+        self.channel.is_running()
+    }
+}
+
+impl<'a, W: Word> Drop for ReadableDoubleBuffer<'a, W> {
+    fn drop(&mut self) {
+        self.request_stop();
+        while self.is_running() {}
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+        fence(Ordering::SeqCst);
+    }
+}
@@ -15,6 +15,8 @@ pub use gpdma::*;
 mod dmamux;
 #[cfg(dmamux)]
 pub(crate) use dmamux::*;
+#[cfg(dma_single_dmamux)]
+pub mod pool;
 
 mod util;
 pub(crate) use util::*;
@@ -31,6 +33,8 @@ use crate::interrupt;
 enum Dir {
     MemoryToPeripheral,
     PeripheralToMemory,
+    #[cfg(any(dma, bdma))]
+    MemoryToMemory,
 }
 
 /// DMA request type alias. (also known as DMA channel number in some chips)
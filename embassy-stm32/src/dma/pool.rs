@@ -0,0 +1,69 @@
+//! Runtime DMA channel allocation.
+//!
+//! On chips where a single DMAMUX serves all DMA channels, any channel can serve any peripheral
+//! request, with the same request number. Instead of hard-wiring a specific channel into each
+//! driver constructor, channels can be put in a [`DmaPool`] and handed out as drivers are created:
+//!
+//! ```ignore
+//! let mut pool = DmaPool::new([p.DMA1_CH1.into(), p.DMA1_CH2.into(), p.DMA1_CH3.into()]);
+//! let spi = Spi::new(p.SPI1, p.PA5, p.PA7, p.PA6, pool.alloc()?, pool.alloc()?, config);
+//! ```
+//!
+//! Chips with several DMAMUXes (e.g. H7, where DMAMUX2 serves the BDMA with its own request
+//! numbers) don't have this module.
+use embassy_hal_internal::Peri;
+
+use super::AnyChannel;
+
+/// Error returned by [`DmaPool::alloc`] when all channels are in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PoolExhausted;
+
+/// A pool of DMA channels, allocated at runtime.
+pub struct DmaPool<'d, const N: usize> {
+    channels: [Option<Peri<'d, AnyChannel>>; N],
+}
+
+impl<'d, const N: usize> DmaPool<'d, N> {
+    /// Create a new pool from a set of channels.
+    pub fn new(channels: [Peri<'d, AnyChannel>; N]) -> Self {
+        Self {
+            channels: channels.map(Some),
+        }
+    }
+
+    /// Take a channel out of the pool.
+    pub fn alloc(&mut self) -> Result<Peri<'d, AnyChannel>, PoolExhausted> {
+        self.channels.iter_mut().find_map(|c| c.take()).ok_or(PoolExhausted)
+    }
+
+    /// Return a channel to the pool.
+    ///
+    /// Drivers don't give their channels back when dropped, so only a channel that was lent to a
+    /// driver with [`Peri::reborrow`] can be returned, once that driver is dropped:
+    ///
+    /// ```ignore
+    /// let mut tx = pool.alloc()?;
+    /// let spi = Spi::new_txonly(p.SPI1, p.PA5, p.PA7, tx.reborrow(), config);
+    /// // ...
+    /// drop(spi);
+    /// pool.free(tx).unwrap();
+    /// ```
+    ///
+    /// Fails, handing the channel back, if the pool is already full.
+    pub fn free(&mut self, channel: Peri<'d, AnyChannel>) -> Result<(), Peri<'d, AnyChannel>> {
+        match self.channels.iter_mut().find(|c| c.is_none()) {
+            Some(slot) => {
+                *slot = Some(channel);
+                Ok(())
+            }
+            None => Err(channel),
+        }
+    }
+
+    /// Number of channels currently available.
+    pub fn available(&self) -> usize {
+        self.channels.iter().filter(|c| c.is_some()).count()
+    }
+}
@@ -15,6 +15,10 @@ pub trait DmaCtrl {
 
     /// Set the waker for a running poll_fn
     fn set_waker(&mut self, waker: &Waker);
+
+    /// Whether the transfer was aborted by a transfer error. The flag stays set until the
+    /// transfer is restarted.
+    fn transfer_error(&self) -> bool;
 }
 
 #[derive(Debug, PartialEq)]
@@ -26,6 +30,8 @@ pub enum Error {
     /// the driver author (or the user using raw metapac code) directly resets
     /// the channel for instance.
     DmaUnsynced,
+    /// The DMA aborted the transfer because of a bus error. The transfer must be restarted.
+    Transfer,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -109,6 +115,10 @@ impl<'a, W: Word> ReadableDmaRingBuffer<'a, W> {
 
     /// Get the available readable dma samples.
     pub fn len(&mut self, dma: &mut impl DmaCtrl) -> Result<usize, Error> {
+        if dma.transfer_error() {
+            return Err(Error::Transfer);
+        }
+
         self.write_index.dma_sync(self.cap(), dma);
         DmaIndex::normalize(&mut self.write_index, &mut self.read_index);
 
@@ -221,6 +231,10 @@ impl<'a, W: Word> WritableDmaRingBuffer<'a, W> {
 
     /// Get the remaining writable dma samples.
     pub fn len(&mut self, dma: &mut impl DmaCtrl) -> Result<usize, Error> {
+        if dma.transfer_error() {
+            return Err(Error::Transfer);
+        }
+
         self.read_index.dma_sync(self.cap(), dma);
         DmaIndex::normalize(&mut self.read_index, &mut self.write_index);
 
@@ -13,6 +13,7 @@ enum TestCircularTransferRequest {
 struct TestCircularTransfer {
     len: usize,
     requests: cell::RefCell<vec::Vec<TestCircularTransferRequest>>,
+    error: bool,
 }
 
 impl DmaCtrl for TestCircularTransfer {
@@ -37,6 +38,10 @@ impl DmaCtrl for TestCircularTransfer {
     }
 
     fn set_waker(&mut self, _waker: &Waker) {}
+
+    fn transfer_error(&self) -> bool {
+        self.error
+    }
 }
 
 impl TestCircularTransfer {
@@ -45,6 +50,7 @@ impl TestCircularTransfer {
         Self {
             requests: cell::RefCell::new(vec![]),
             len,
+            error: false,
         }
     }
 
@@ -87,4 +93,60 @@ fn dma_index_advancing_increases_as_index() {
     assert_eq!(index.as_index(CAP, 0), 1);
 }
 
+#[test]
+fn transfer_error_is_reported_by_reader() {
+    let mut dma = TestCircularTransfer::new(CAP);
+    let mut dma_buf = [0u8; CAP];
+    let mut ringbuf = ReadableDmaRingBuffer::new(&mut dma_buf);
+
+    dma.setup(vec![
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::PositionRequest(4),
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::PositionRequest(4),
+    ]);
+    let mut buf = [0u8; 2];
+    assert_eq!(ringbuf.read(&mut dma, &mut buf), Ok((2, 2)));
+
+    // The ring buffer resets itself on errors.
+    dma.error = true;
+    dma.setup(vec![
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::PositionRequest(4),
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::PositionRequest(4),
+    ]);
+    assert_eq!(ringbuf.read(&mut dma, &mut buf), Err(Error::Transfer));
+    // The error stays latched until the transfer is restarted.
+    assert_eq!(ringbuf.len(&mut dma), Err(Error::Transfer));
+
+    let mut buf = [0u8; 4];
+    let read = ringbuf.read_exact(&mut dma, &mut buf);
+    assert_eq!(embassy_futures::block_on(read), Err(Error::Transfer));
+}
+
+#[test]
+fn transfer_error_is_reported_by_writer() {
+    let mut dma = TestCircularTransfer::new(CAP);
+    let mut dma_buf = [0u8; CAP];
+    let mut ringbuf = WritableDmaRingBuffer::new(&mut dma_buf);
+
+    dma.error = true;
+    dma.setup(vec![
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::PositionRequest(0),
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::PositionRequest(0),
+    ]);
+    assert_eq!(ringbuf.write(&mut dma, &[1, 2]), Err(Error::Transfer));
+    assert_eq!(ringbuf.len(&mut dma), Err(Error::Transfer));
+
+    let write = ringbuf.write_exact(&mut dma, &[1, 2]);
+    assert_eq!(embassy_futures::block_on(write), Err(Error::Transfer));
+}
+
 mod prop_test;
@@ -32,6 +32,10 @@ impl DmaCtrl for DmaMock {
     }
 
     fn set_waker(&mut self, _waker: &Waker) {}
+
+    fn transfer_error(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone)]
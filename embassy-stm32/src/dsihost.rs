@@ -136,13 +136,11 @@ impl<'d, T: Instance> DsiHost<'d, T> {
     }
 
     fn short_write(&mut self, channel_id: u8, packet_type: PacketType, param1: u8, param2: u8) -> Result<(), Error> {
-        #[cfg(feature = "defmt")]
-        defmt::debug!("short_write: BEGIN wait for command fifo empty");
+        debug!("short_write: BEGIN wait for command fifo empty");
 
         // Wait for Command FIFO empty
         self.wait_command_fifo_empty()?;
-        #[cfg(feature = "defmt")]
-        defmt::debug!("short_write: END wait for command fifo empty");
+        debug!("short_write: END wait for command fifo empty");
 
         // Configure the packet to send a short DCS command with 0 or 1 parameters
         // Update the DSI packet header with new information
@@ -179,13 +177,11 @@ impl<'d, T: Instance> DsiHost<'d, T> {
         // params needs to have at least 2 elements, otherwise short_write should be used
         assert!(data.len() >= 2);
 
-        #[cfg(feature = "defmt")]
-        defmt::debug!("long_write: BEGIN wait for command fifo empty");
+        debug!("long_write: BEGIN wait for command fifo empty");
 
         self.wait_command_fifo_empty()?;
 
-        #[cfg(feature = "defmt")]
-        defmt::debug!("long_write: DONE wait for command fifo empty");
+        debug!("long_write: DONE wait for command fifo empty");
 
         // Note: CubeMX example "NbParams" is always one LESS than params.len()
         // DCS code (last element of params) must be on payload byte 1 and if we have only 2 more params,
@@ -0,0 +1,533 @@
+//! EEPROM emulation
+//!
+//! Wear-leveled storage of 32-bit values indexed by 16-bit keys, on top of two flash pages.
+//!
+//! Every write appends a record to the active page, so a value can be updated many times before
+//! the page is erased. When the active page is full, the latest value of each key is copied to
+//! the other page, which then becomes active, and the old page is erased.
+//!
+//! Each page starts with a header recording its state and a generation counter. The state is
+//! only advanced once the data it covers is fully written, so an interrupted write or page
+//! transfer is detected and recovered from by [`Eeprom::new`]:
+//! - a half-written record is ignored,
+//! - a page transfer interrupted before the new page was marked active is restarted,
+//! - if both pages are marked active, the one with the newest generation wins.
+//!
+//! The flash erase value must be `0xFF`.
+//!
+//! The number of distinct keys is bounded by the `KEYS` parameter of [`Eeprom`], which sizes the
+//! key set built in RAM (2 bytes per key, on the stack) while transferring to the other page.
+use embedded_storage::nor_flash::NorFlash;
+
+/// Key value that is reserved to mark free record slots.
+pub const RESERVED_KEY: u16 = 0xFFFF;
+
+const MAX_SLOT_SIZE: usize = 32;
+/// Marker written in the first header slot once a page is erased and in use.
+const RECEIVING: u32 = 0x5245_4356;
+
+/// EEPROM emulation error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// Error from the underlying flash.
+    Flash(E),
+    /// There are more distinct keys than fit in a page or in `KEYS`, nothing can be written
+    /// anymore.
+    Full,
+    /// The key is [`RESERVED_KEY`].
+    InvalidKey,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(e: E) -> Self {
+        Self::Flash(e)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PageState {
+    Erased,
+    Receiving,
+    Active(u32),
+    Invalid,
+}
+
+/// Emulated EEPROM over two flash pages, holding up to `KEYS` distinct keys.
+pub struct Eeprom<F: NorFlash, const KEYS: usize = 128> {
+    flash: F,
+    pages: [u32; 2],
+    active: usize,
+    generation: u32,
+    next_slot: u32,
+}
+
+impl<F: NorFlash, const KEYS: usize> Eeprom<F, KEYS> {
+    /// Size of a header field or record, in bytes.
+    const SLOT_SIZE: u32 = if F::WRITE_SIZE > 8 { F::WRITE_SIZE as u32 } else { 8 };
+    /// Number of record slots per page. The first two slots hold the page header.
+    const SLOTS: u32 = F::ERASE_SIZE as u32 / Self::SLOT_SIZE;
+
+    /// Open the emulated EEPROM stored in the two erase pages starting at `offset`.
+    ///
+    /// `offset` must be aligned to the flash erase size. Pages that don't hold a valid EEPROM are
+    /// formatted, and interrupted operations are completed.
+    pub fn new(flash: F, offset: u32) -> Result<Self, Error<F::Error>> {
+        assert!(F::WRITE_SIZE <= MAX_SLOT_SIZE);
+        assert_eq!(offset % F::ERASE_SIZE as u32, 0);
+
+        let mut this = Self {
+            flash,
+            pages: [offset, offset + F::ERASE_SIZE as u32],
+            active: 0,
+            generation: 0,
+            next_slot: 2,
+        };
+
+        let states = [this.page_state(0)?, this.page_state(1)?];
+        match states {
+            [PageState::Active(a), PageState::Active(b)] => {
+                // Interrupted after the new page was marked active, but before the old one was erased.
+                let (newest, generation) = if b.wrapping_sub(a) as i32 > 0 { (1, b) } else { (0, a) };
+                this.erase_page(1 - newest)?;
+                this.open(newest, generation)?;
+            }
+            [PageState::Active(generation), other] | [other, PageState::Active(generation)] => {
+                let active = if matches!(states[0], PageState::Active(_)) {
+                    0
+                } else {
+                    1
+                };
+                this.open(active, generation)?;
+                if other == PageState::Receiving {
+                    // Interrupted while copying to the other page, restart the transfer.
+                    this.transfer(None)?;
+                } else if other != PageState::Erased {
+                    this.erase_page(1 - active)?;
+                }
+            }
+            _ => this.format()?,
+        }
+
+        Ok(this)
+    }
+
+    /// Read the value stored for `key`, if any.
+    pub fn read(&mut self, key: u16) -> Result<Option<u32>, Error<F::Error>> {
+        if key == RESERVED_KEY {
+            return Err(Error::InvalidKey);
+        }
+        Ok(self.find(self.active, key, self.next_slot)?)
+    }
+
+    /// Store `value` for `key`.
+    ///
+    /// Nothing is written if the stored value is already `value`.
+    pub fn write(&mut self, key: u16, value: u32) -> Result<(), Error<F::Error>> {
+        if key == RESERVED_KEY {
+            return Err(Error::InvalidKey);
+        }
+        if self.find(self.active, key, self.next_slot)? == Some(value) {
+            return Ok(());
+        }
+
+        if self.next_slot >= Self::SLOTS {
+            // A transfer that doesn't fit costs two erases of the other page, check first.
+            if !self.fits(key)? {
+                return Err(Error::Full);
+            }
+            self.transfer(Some((key, value)))
+        } else {
+            let slot = self.next_slot;
+            self.write_record(self.active, slot, key, value)?;
+            self.next_slot += 1;
+            Ok(())
+        }
+    }
+
+    /// Erase both pages, dropping all stored values.
+    pub fn format(&mut self) -> Result<(), Error<F::Error>> {
+        self.erase_page(0)?;
+        self.erase_page(1)?;
+        self.write_header(0, 0, RECEIVING)?;
+        self.write_header(0, 1, 0)?;
+        self.active = 0;
+        self.generation = 0;
+        self.next_slot = 2;
+        Ok(())
+    }
+
+    /// Release the underlying flash.
+    pub fn release(self) -> F {
+        self.flash
+    }
+
+    fn open(&mut self, page: usize, generation: u32) -> Result<(), F::Error> {
+        self.active = page;
+        self.generation = generation;
+        self.next_slot = Self::SLOTS;
+        for slot in 2..Self::SLOTS {
+            if self.read_slot(page, slot)?.iter().all(|&b| b == 0xFF) {
+                self.next_slot = slot;
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the latest value of each key to the other page, optionally adding a new value, then
+    /// switch to the other page.
+    ///
+    /// If the values don't fit, the other page is erased again and the current one stays active.
+    fn transfer(&mut self, extra: Option<(u16, u32)>) -> Result<(), Error<F::Error>> {
+        let old = self.active;
+        let new = 1 - old;
+
+        self.erase_page(new)?;
+        self.write_header(new, 0, RECEIVING)?;
+
+        let next = match self.copy_latest(old, new, extra) {
+            Ok(next) => next,
+            Err(e) => {
+                // Don't leave a receiving page behind, it would be picked up by the next `new`.
+                self.erase_page(new)?;
+                return Err(e);
+            }
+        };
+
+        let generation = self.generation.wrapping_add(1);
+        self.write_header(new, 1, generation)?;
+        self.erase_page(old)?;
+
+        self.active = new;
+        self.generation = generation;
+        self.next_slot = next;
+        Ok(())
+    }
+
+    /// Whether the distinct keys of the active page, with `key` added, fit in `KEYS` and in a page.
+    fn fits(&mut self, key: u16) -> Result<bool, F::Error> {
+        // Keys seen so far, sorted.
+        let mut keys = [0u16; KEYS];
+        let mut count = 0;
+
+        let mut insert = |key: u16| {
+            let Err(pos) = keys[..count].binary_search(&key) else {
+                return true;
+            };
+            if count == KEYS || count as u32 >= Self::SLOTS - 2 {
+                return false;
+            }
+            keys.copy_within(pos..count, pos + 1);
+            keys[pos] = key;
+            count += 1;
+            true
+        };
+
+        if !insert(key) {
+            return Ok(false);
+        }
+        for slot in 2..self.next_slot {
+            if let Some((key, _)) = self.record(self.active, slot)? {
+                if !insert(key) {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Write `extra` and the latest value of each other key of page `old` to page `new`, in a
+    /// single pass over `old`. Returns the next free slot of `new`.
+    fn copy_latest(&mut self, old: usize, new: usize, extra: Option<(u16, u32)>) -> Result<u32, Error<F::Error>> {
+        // Keys already written to `new`, sorted.
+        let mut keys = [0u16; KEYS];
+        let mut count = 0;
+        let mut next = 2;
+
+        let mut copy = |this: &mut Self, key: u16, value: u32| -> Result<(), Error<F::Error>> {
+            let Err(pos) = keys[..count].binary_search(&key) else {
+                // Superseded by a newer record.
+                return Ok(());
+            };
+            if count == KEYS || next >= Self::SLOTS {
+                return Err(Error::Full);
+            }
+            keys.copy_within(pos..count, pos + 1);
+            keys[pos] = key;
+            count += 1;
+
+            this.write_record(new, next, key, value)?;
+            next += 1;
+            Ok(())
+        };
+
+        if let Some((key, value)) = extra {
+            copy(self, key, value)?;
+        }
+        for slot in (2..self.next_slot).rev() {
+            if let Some((key, value)) = self.record(old, slot)? {
+                copy(self, key, value)?;
+            }
+        }
+
+        Ok(next)
+    }
+
+    fn page_state(&mut self, page: usize) -> Result<PageState, F::Error> {
+        let receiving = self.read_slot(page, 0)?;
+        let active = self.read_slot(page, 1)?;
+
+        let erased = |s: &[u8; MAX_SLOT_SIZE]| s.iter().all(|&b| b == 0xFF);
+        let word = |s: &[u8; MAX_SLOT_SIZE]| {
+            let value = u32::from_le_bytes([s[0], s[1], s[2], s[3]]);
+            let check = u32::from_le_bytes([s[4], s[5], s[6], s[7]]);
+            (value == !check).then_some(value)
+        };
+
+        Ok(match (word(&receiving), word(&active)) {
+            _ if erased(&receiving) && erased(&active) => PageState::Erased,
+            (Some(RECEIVING), Some(generation)) => PageState::Active(generation),
+            (Some(RECEIVING), None) if erased(&active) => PageState::Receiving,
+            _ => PageState::Invalid,
+        })
+    }
+
+    /// Find the latest value of `key` in the first `end` slots of `page`.
+    fn find(&mut self, page: usize, key: u16, end: u32) -> Result<Option<u32>, F::Error> {
+        for slot in (2..end).rev() {
+            if let Some((k, value)) = self.record(page, slot)? {
+                if k == key {
+                    return Ok(Some(value));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read a record, ignoring free and half-written slots.
+    fn record(&mut self, page: usize, slot: u32) -> Result<Option<(u16, u32)>, F::Error> {
+        let s = self.read_slot(page, slot)?;
+        let key = u16::from_le_bytes([s[0], s[1]]);
+        let check = u16::from_le_bytes([s[2], s[3]]);
+        let value = u32::from_le_bytes([s[4], s[5], s[6], s[7]]);
+
+        let valid = key != RESERVED_KEY && check == !(key ^ value as u16 ^ (value >> 16) as u16);
+        Ok(valid.then_some((key, value)))
+    }
+
+    fn write_record(&mut self, page: usize, slot: u32, key: u16, value: u32) -> Result<(), F::Error> {
+        let check = !(key ^ value as u16 ^ (value >> 16) as u16);
+        let mut s = [0xFF; MAX_SLOT_SIZE];
+        s[0..2].copy_from_slice(&key.to_le_bytes());
+        s[2..4].copy_from_slice(&check.to_le_bytes());
+        s[4..8].copy_from_slice(&value.to_le_bytes());
+        self.write_slot(page, slot, &s)
+    }
+
+    fn write_header(&mut self, page: usize, slot: u32, value: u32) -> Result<(), F::Error> {
+        let mut s = [0xFF; MAX_SLOT_SIZE];
+        s[0..4].copy_from_slice(&value.to_le_bytes());
+        s[4..8].copy_from_slice(&(!value).to_le_bytes());
+        self.write_slot(page, slot, &s)
+    }
+
+    fn read_slot(&mut self, page: usize, slot: u32) -> Result<[u8; MAX_SLOT_SIZE], F::Error> {
+        let mut s = [0xFF; MAX_SLOT_SIZE];
+        let offset = self.pages[page] + slot * Self::SLOT_SIZE;
+        self.flash.read(offset, &mut s[..Self::SLOT_SIZE as usize])?;
+        Ok(s)
+    }
+
+    fn write_slot(&mut self, page: usize, slot: u32, s: &[u8; MAX_SLOT_SIZE]) -> Result<(), F::Error> {
+        let offset = self.pages[page] + slot * Self::SLOT_SIZE;
+        self.flash.write(offset, &s[..Self::SLOT_SIZE as usize])
+    }
+
+    fn erase_page(&mut self, page: usize) -> Result<(), F::Error> {
+        let offset = self.pages[page];
+        self.flash.erase(offset, offset + F::ERASE_SIZE as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_storage::nor_flash::{ErrorType, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+
+    use super::*;
+
+    const PAGE_SIZE: usize = 256;
+    /// Record slots per page, after the header.
+    const RECORDS: u32 = PAGE_SIZE as u32 / 8 - 2;
+
+    #[derive(Debug, PartialEq)]
+    struct MockError;
+
+    impl NorFlashError for MockError {
+        fn kind(&self) -> NorFlashErrorKind {
+            NorFlashErrorKind::Other
+        }
+    }
+
+    /// RAM-backed flash. Like real flash, writes can only clear bits.
+    struct MockFlash {
+        mem: [u8; 2 * PAGE_SIZE],
+        erases: usize,
+    }
+
+    impl MockFlash {
+        fn new() -> Self {
+            Self {
+                mem: [0xFF; 2 * PAGE_SIZE],
+                erases: 0,
+            }
+        }
+    }
+
+    impl ErrorType for MockFlash {
+        type Error = MockError;
+    }
+
+    impl ReadNorFlash for MockFlash {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            bytes.copy_from_slice(&self.mem[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.mem.len()
+        }
+    }
+
+    impl NorFlash for MockFlash {
+        const WRITE_SIZE: usize = 8;
+        const ERASE_SIZE: usize = PAGE_SIZE;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            self.mem[from as usize..to as usize].fill(0xFF);
+            self.erases += 1;
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            for (m, b) in self.mem[offset..offset + bytes.len()].iter_mut().zip(bytes) {
+                *m &= *b;
+            }
+            Ok(())
+        }
+    }
+
+    fn reopen<const KEYS: usize>(eeprom: Eeprom<MockFlash, KEYS>) -> Eeprom<MockFlash, KEYS> {
+        Eeprom::new(eeprom.release(), 0).unwrap()
+    }
+
+    #[test]
+    fn values_survive_reopening() {
+        let mut eeprom: Eeprom<_> = Eeprom::new(MockFlash::new(), 0).unwrap();
+        assert_eq!(eeprom.read(1), Ok(None));
+
+        eeprom.write(1, 10).unwrap();
+        eeprom.write(2, 20).unwrap();
+        eeprom.write(1, 11).unwrap();
+        assert_eq!(eeprom.read(1), Ok(Some(11)));
+
+        let mut eeprom = reopen(eeprom);
+        assert_eq!(eeprom.read(1), Ok(Some(11)));
+        assert_eq!(eeprom.read(2), Ok(Some(20)));
+        assert_eq!(eeprom.read(RESERVED_KEY), Err(Error::InvalidKey));
+    }
+
+    #[test]
+    fn transfer_keeps_latest_values() {
+        let mut eeprom: Eeprom<_> = Eeprom::new(MockFlash::new(), 0).unwrap();
+
+        // Enough updates to go through several page transfers.
+        for i in 0..10 * RECORDS {
+            eeprom.write((i % 5) as u16, i).unwrap();
+        }
+
+        let mut eeprom = reopen(eeprom);
+        for key in 0..5 {
+            let latest = (0..10 * RECORDS).rev().find(|i| i % 5 == key as u32).unwrap();
+            assert_eq!(eeprom.read(key), Ok(Some(latest)));
+        }
+    }
+
+    #[test]
+    fn full_transfer_is_rolled_back() {
+        let mut eeprom: Eeprom<_> = Eeprom::new(MockFlash::new(), 0).unwrap();
+
+        for key in 0..RECORDS as u16 {
+            eeprom.write(key, key as u32).unwrap();
+        }
+        assert_eq!(eeprom.write(RECORDS as u16, 0), Err(Error::Full));
+
+        // The stored values are still usable after a reset.
+        let mut eeprom = reopen(eeprom);
+        for key in 0..RECORDS as u16 {
+            assert_eq!(eeprom.read(key), Ok(Some(key as u32)));
+        }
+        assert_eq!(eeprom.read(RECORDS as u16), Ok(None));
+    }
+
+    #[test]
+    fn full_write_erases_nothing() {
+        let mut eeprom: Eeprom<_> = Eeprom::new(MockFlash::new(), 0).unwrap();
+
+        for key in 0..RECORDS as u16 {
+            eeprom.write(key, key as u32).unwrap();
+        }
+        let erases = eeprom.flash.erases;
+        assert_eq!(eeprom.write(RECORDS as u16, 0), Err(Error::Full));
+        assert_eq!(eeprom.write(RECORDS as u16 + 1, 0), Err(Error::Full));
+        assert_eq!(eeprom.flash.erases, erases);
+    }
+
+    #[test]
+    fn key_limit_is_enforced_on_transfer() {
+        let mut eeprom: Eeprom<_, 2> = Eeprom::new(MockFlash::new(), 0).unwrap();
+
+        for key in 0..3 {
+            eeprom.write(key, 0).unwrap();
+        }
+        let result = (0..RECORDS).try_for_each(|i| eeprom.write(0, i + 1));
+        assert_eq!(result, Err(Error::Full));
+
+        let mut eeprom = reopen(eeprom);
+        assert_eq!(eeprom.read(2), Ok(Some(0)));
+    }
+
+    #[test]
+    fn interrupted_transfer_is_restarted() {
+        let mut eeprom: Eeprom<_> = Eeprom::new(MockFlash::new(), 0).unwrap();
+        eeprom.write(1, 10).unwrap();
+        eeprom.write(1, 11).unwrap();
+
+        // Simulate a reset after the other page was marked receiving and got a record.
+        eeprom.write_header(1, 0, RECEIVING).unwrap();
+        eeprom.write_record(1, 2, 1, 10).unwrap();
+
+        let mut eeprom = reopen(eeprom);
+        assert_eq!(eeprom.active, 1);
+        assert_eq!(eeprom.read(1), Ok(Some(11)));
+    }
+
+    #[test]
+    fn newest_active_page_wins() {
+        let mut eeprom: Eeprom<_> = Eeprom::new(MockFlash::new(), 0).unwrap();
+        eeprom.write(1, 10).unwrap();
+
+        // Simulate a reset after the transfer, before the old page was erased.
+        eeprom.write_header(1, 0, RECEIVING).unwrap();
+        eeprom.write_record(1, 2, 1, 20).unwrap();
+        eeprom.write_header(1, 1, 1).unwrap();
+
+        let mut eeprom = reopen(eeprom);
+        assert_eq!(eeprom.read(1), Ok(Some(20)));
+        assert_eq!(eeprom.page_state(0), Ok(PageState::Erased));
+    }
+}
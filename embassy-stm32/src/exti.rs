@@ -17,17 +17,17 @@ const EXTI_COUNT: usize = 16;
 static EXTI_WAKERS: [AtomicWaker; EXTI_COUNT] = [const { AtomicWaker::new() }; EXTI_COUNT];
 
 #[cfg(all(exti_w, feature = "_core-cm0p"))]
-fn cpu_regs() -> pac::exti::Cpu {
+pub(crate) fn cpu_regs() -> pac::exti::Cpu {
     EXTI.cpu(1)
 }
 
 #[cfg(all(exti_w, not(feature = "_core-cm0p")))]
-fn cpu_regs() -> pac::exti::Cpu {
+pub(crate) fn cpu_regs() -> pac::exti::Cpu {
     EXTI.cpu(0)
 }
 
 #[cfg(not(exti_w))]
-fn cpu_regs() -> pac::exti::Exti {
+pub(crate) fn cpu_regs() -> pac::exti::Exti {
     EXTI
 }
 
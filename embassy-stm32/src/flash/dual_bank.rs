@@ -0,0 +1,237 @@
+//! Dual-bank A/B firmware update
+//!
+//! The application runs from the bank mapped at the flash start, and a new image is written to the
+//! other bank. [`DualBankUpdater::swap`] then flips the `BFB2` option bit and reloads the option
+//! bytes, so the system bootloader starts the new image from the other bank with the banks swapped.
+//!
+//! The update state is kept in an RTC backup register. The new image must confirm that it works
+//! with [`DualBankUpdater::mark_booted`]. If it resets before doing so, the next call to
+//! [`DualBankUpdater::check_boot`] swaps back to the previous image.
+//!
+//! The image can be checked with the CRC unit before swapping. On chips with the HASH and PKA
+//! peripherals, [`DualBankUpdater::verify_signature`] checks an ECDSA P-256 signature of its
+//! SHA-256 digest. Otherwise, signatures are verified in software on
+//! [`DualBankUpdater::inactive_image`].
+//!
+//! ```rust,ignore
+//! let mut updater = DualBankUpdater::new(&mut flash, &rtc, 0);
+//! updater.check_boot()?;
+//!
+//! updater.erase_inactive()?;
+//! updater.write_inactive(0, &image)?;
+//! if updater.verify_crc(&mut crc, image.len() as u32, expected_crc) {
+//!     updater.swap()?;
+//! }
+//!
+//! // In the new image, once it's known to work:
+//! updater.mark_booted();
+//! ```
+
+use super::option_bytes::{lock_option_bytes, unlock_option_bytes};
+use super::{family, Blocking, Error, Flash, FlashBank, BANK1_REGION, FLASH_BASE};
+use crate::crc::Crc;
+#[cfg(all(hash, pka))]
+use crate::hash::{self, Algorithm, DataType, Hash};
+use crate::pac;
+#[cfg(all(hash, pka))]
+use crate::pka::{self, Pka, Point, Signature};
+use crate::rtc::Rtc;
+
+/// The low bit holds the bank the swap boots from.
+const STATE_SWAPPING: u32 = 0x5357_4150;
+const STATE_TESTING: u32 = 0x5445_5354;
+const STATE_ROLLED_BACK: u32 = 0x524F_4C4C;
+
+/// State of a firmware update.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UpdateState {
+    /// No update in progress. The running image is confirmed.
+    Idle,
+    /// The banks are being swapped to start a new image.
+    Swapping,
+    /// A new image is running, and hasn't been confirmed with [`DualBankUpdater::mark_booted`] yet.
+    Testing,
+    /// A new image failed to confirm, and the previous image was restored.
+    RolledBack,
+}
+
+impl UpdateState {
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            _ if bits & !1 == STATE_SWAPPING => Self::Swapping,
+            STATE_TESTING => Self::Testing,
+            STATE_ROLLED_BACK => Self::RolledBack,
+            _ => Self::Idle,
+        }
+    }
+
+    fn to_bits(self) -> u32 {
+        match self {
+            Self::Idle => 0,
+            Self::Swapping => STATE_SWAPPING,
+            Self::Testing => STATE_TESTING,
+            Self::RolledBack => STATE_ROLLED_BACK,
+        }
+    }
+}
+
+/// Dual-bank firmware updater.
+pub struct DualBankUpdater<'a, 'd> {
+    flash: &'a mut Flash<'d, Blocking>,
+    rtc: &'a Rtc,
+    register: usize,
+}
+
+impl<'a, 'd> DualBankUpdater<'a, 'd> {
+    /// Create an updater, keeping the update state in the RTC backup register `register`.
+    pub fn new(flash: &'a mut Flash<'d, Blocking>, rtc: &'a Rtc, register: usize) -> Self {
+        assert!(register < Rtc::BACKUP_REGISTER_COUNT);
+        Self { flash, rtc, register }
+    }
+
+    /// Physical bank the application is running from.
+    pub fn active_bank(&self) -> FlashBank {
+        match pac::SYSCFG.memrmp().read().fb_mode() {
+            false => FlashBank::Bank1,
+            true => FlashBank::Bank2,
+        }
+    }
+
+    /// Size of a bank, which is the maximum image size.
+    pub fn bank_size(&self) -> u32 {
+        BANK1_REGION.size
+    }
+
+    /// Current update state.
+    pub fn state(&self) -> UpdateState {
+        UpdateState::from_bits(self.rtc.read_backup_register(self.register).unwrap_or(0))
+    }
+
+    fn set_state(&self, state: UpdateState) {
+        self.rtc.write_backup_register(self.register, state.to_bits());
+    }
+
+    fn inactive_bank(&self) -> FlashBank {
+        match self.active_bank() {
+            FlashBank::Bank1 => FlashBank::Bank2,
+            _ => FlashBank::Bank1,
+        }
+    }
+
+    /// Handle a pending update. Call this early after every boot.
+    ///
+    /// After a swap, the state goes to [`UpdateState::Testing`]. If the state was already
+    /// [`UpdateState::Testing`], the new image reset without confirming: the banks are swapped back,
+    /// which resets the device, and the previous image then sees [`UpdateState::RolledBack`]. If the
+    /// swap didn't happen, the previous image is still running and sees [`UpdateState::RolledBack`]
+    /// too.
+    pub fn check_boot(&mut self) -> Result<UpdateState, Error> {
+        match self.state() {
+            UpdateState::Swapping => {
+                let bits = self.rtc.read_backup_register(self.register).unwrap_or(0);
+                let target = if bits & 1 != 0 {
+                    FlashBank::Bank2
+                } else {
+                    FlashBank::Bank1
+                };
+                let state = if self.active_bank() == target {
+                    UpdateState::Testing
+                } else {
+                    warn!("Bank swap didn't happen, still running the previous image");
+                    UpdateState::RolledBack
+                };
+                self.set_state(state);
+                Ok(state)
+            }
+            UpdateState::Testing => {
+                warn!("Update not confirmed, rolling back");
+                self.set_state(UpdateState::RolledBack);
+                self.swap_banks()
+            }
+            state => Ok(state),
+        }
+    }
+
+    /// Confirm that the running image works, ending the update.
+    pub fn mark_booted(&mut self) {
+        if self.state() != UpdateState::Idle {
+            self.set_state(UpdateState::Idle);
+        }
+    }
+
+    /// Erase the inactive bank.
+    pub fn erase_inactive(&mut self) -> Result<(), Error> {
+        let size = self.bank_size();
+        self.flash.blocking_erase(size, 2 * size)
+    }
+
+    /// Write `data` to the inactive bank, at `offset` from the bank start.
+    pub fn write_inactive(&mut self, offset: u32, data: &[u8]) -> Result<(), Error> {
+        if offset + data.len() as u32 > self.bank_size() {
+            return Err(Error::Size);
+        }
+        self.flash.blocking_write(self.bank_size() + offset, data)
+    }
+
+    /// The first `len` bytes of the inactive bank.
+    pub fn inactive_image(&self, len: u32) -> &[u8] {
+        assert!(len <= self.bank_size());
+        let start = FLASH_BASE as u32 + self.bank_size();
+        unsafe { core::slice::from_raw_parts(start as *const u8, len as usize) }
+    }
+
+    /// Check the CRC of the first `len` bytes of the inactive bank, with the configuration of `crc`.
+    pub fn verify_crc(&self, crc: &mut Crc<'_>, len: u32, expected: u32) -> bool {
+        crc.reset();
+        crc.feed_bytes(self.inactive_image(len)) == expected
+    }
+
+    /// Verify the ECDSA P-256 `signature` by `public_key` of the SHA-256 digest of the first `len`
+    /// bytes of the inactive bank.
+    #[cfg(all(hash, pka))]
+    pub async fn verify_signature<H: hash::Instance, P: pka::Instance, M: crate::mode::Mode>(
+        &self,
+        hash: &mut Hash<'_, H, M>,
+        pka: &mut Pka<'_, P>,
+        len: u32,
+        public_key: &Point,
+        signature: &Signature,
+    ) -> Result<bool, pka::Error> {
+        let mut ctx = hash.start(Algorithm::SHA256, DataType::Width8, None);
+        hash.update_blocking(&mut ctx, self.inactive_image(len));
+        let mut digest = [0; 32];
+        hash.finish_blocking(ctx, &mut digest);
+
+        pka.ecdsa_verify(public_key, &digest, signature).await
+    }
+
+    /// Start the image in the inactive bank.
+    ///
+    /// The image must have been verified. This resets the device, and only returns on error.
+    pub fn swap(&mut self) -> Result<UpdateState, Error> {
+        let target = (self.inactive_bank() == FlashBank::Bank2) as u32;
+        self.rtc.write_backup_register(self.register, STATE_SWAPPING | target);
+        self.swap_banks()
+    }
+
+    fn swap_banks(&mut self) -> Result<UpdateState, Error> {
+        // BFB2 boots from bank 2. The bank that's not mapped at the flash start is the other one.
+        let boot_bank2 = self.active_bank() == FlashBank::Bank1;
+
+        family::with_flash_access(|| unsafe {
+            family::clear_all_err();
+            family::wait_ready_blocking()?;
+            unlock_option_bytes();
+
+            pac::FLASH.optr().modify(|w| w.set_bfb2(boot_bank2));
+            pac::FLASH.cr().modify(|w| w.set_optstrt(true));
+            let ret = family::wait_ready_blocking();
+
+            lock_option_bytes();
+            ret
+        })?;
+
+        self.flash.launch_option_bytes()
+    }
+}
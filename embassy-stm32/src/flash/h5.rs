@@ -16,8 +16,7 @@ pub(crate) unsafe fn lock() {
 pub(crate) unsafe fn unlock() {
     // TODO: check locked first
     while pac::FLASH.nssr().read().bsy() {
-        #[cfg(feature = "defmt")]
-        defmt::trace!("busy")
+        trace!("busy");
     }
 
     // only unlock if locked to begin with
@@ -1,10 +1,27 @@
 use core::ptr::write_volatile;
 use core::sync::atomic::{fence, Ordering};
 
+#[cfg(any(flash_l4, flash_wb))]
+use embassy_sync::waitqueue::AtomicWaker;
+
 use super::{FlashSector, WRITE_SIZE};
 use crate::flash::Error;
 use crate::pac;
 
+#[cfg(any(flash_l4, flash_wb))]
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+#[cfg(any(flash_l4, flash_wb))]
+pub(crate) unsafe fn on_interrupt() {
+    // Clear IRQ flags
+    pac::FLASH.sr().write(|w| {
+        w.set_operr(true);
+        w.set_eop(true);
+    });
+
+    WAKER.wake();
+}
+
 pub(crate) unsafe fn lock() {
     #[cfg(any(flash_wl, flash_wb, flash_l4))]
     pac::FLASH.cr().modify(|w| w.set_lock(true));
@@ -69,7 +86,99 @@ pub(crate) unsafe fn disable_blocking_write() {
     pac::FLASH.nscr().write(|w| w.set_nspg(false));
 }
 
+#[cfg(any(flash_l4, flash_wb))]
+pub(crate) unsafe fn enable_write() {
+    assert_eq!(0, WRITE_SIZE % 4);
+
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(true);
+        w.set_eopie(true);
+        w.set_errie(true);
+    });
+}
+
+#[cfg(any(flash_l4, flash_wb))]
+pub(crate) unsafe fn disable_write() {
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+}
+
+/// Flash access arbitration with CPU2 on STM32WB.
+///
+/// When the wireless stack runs on CPU2, it relies on hardware semaphores to protect its
+/// radio timing from flash operations stalling the bus (see AN5289, "Flash memory management").
+#[cfg(flash_wb)]
+mod cpu2 {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::hsem::shared::{self, BLOCK_FLASH_REQ_BY_CPU2_SEMID, FLASH_SEMID};
+
+    /// Nesting depth of [`acquire`], the semaphore is only released by the outermost [`release`].
+    static DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+    /// Wait until CPU1 is allowed to use the flash, then take the flash semaphore.
+    pub(super) fn acquire() {
+        // The depth is only raised once the semaphore is held, so that a nested caller (e.g. from
+        // an interrupt) never sees a non-zero depth without it.
+        critical_section::with(|_| {
+            let depth = DEPTH.load(Ordering::Relaxed);
+            if depth == 0 {
+                while shared::is_locked(BLOCK_FLASH_REQ_BY_CPU2_SEMID) {}
+                shared::blocking_lock(FLASH_SEMID);
+
+                // CPU2 may have suspended an operation started just before taking the semaphore.
+                while crate::pac::FLASH.sr().read().pesd() {}
+            }
+            DEPTH.store(depth + 1, Ordering::Relaxed);
+        });
+    }
+
+    /// Release the flash semaphore.
+    pub(super) fn release() {
+        critical_section::with(|_| {
+            let depth = DEPTH.load(Ordering::Relaxed) - 1;
+            DEPTH.store(depth, Ordering::Relaxed);
+            if depth == 0 {
+                shared::unlock(FLASH_SEMID);
+            }
+        });
+    }
+}
+
+/// Run `f` with exclusive access to the flash, arbitrated against CPU2 on STM32WB.
+pub(crate) fn with_flash_access<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(flash_wb)]
+    cpu2::acquire();
+    let r = f();
+    #[cfg(flash_wb)]
+    cpu2::release();
+    r
+}
+
 pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    with_flash_access(|| blocking_write_inner(start_address, buf))
+}
+
+unsafe fn blocking_write_inner(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    write_start(start_address, buf);
+    wait_ready_blocking()
+}
+
+#[cfg(any(flash_l4, flash_wb))]
+pub(crate) async unsafe fn write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    #[cfg(flash_wb)]
+    cpu2::acquire();
+    #[cfg(flash_wb)]
+    let _release = embassy_hal_internal::drop::OnDrop::new(cpu2::release);
+
+    write_start(start_address, buf);
+    wait_ready().await
+}
+
+unsafe fn write_start(start_address: u32, buf: &[u8; WRITE_SIZE]) {
     let mut address = start_address;
     for val in buf.chunks(4) {
         write_volatile(address as *mut u32, u32::from_le_bytes(unwrap!(val.try_into())));
@@ -78,11 +187,66 @@ pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE])
         // prevents parallelism errors
         fence(Ordering::SeqCst);
     }
+}
 
-    wait_ready_blocking()
+#[cfg(any(flash_l4, flash_wb))]
+pub(crate) async unsafe fn erase_sector(sector: &FlashSector) -> Result<(), Error> {
+    #[cfg(flash_wb)]
+    cpu2::acquire();
+    #[cfg(flash_wb)]
+    let _release = embassy_hal_internal::drop::OnDrop::new(cpu2::release);
+
+    #[cfg(flash_l4)]
+    let (idx, bank) = page_index(sector);
+    #[cfg(flash_wb)]
+    let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
+
+    trace!("Erasing page number {}", idx);
+
+    pac::FLASH.cr().modify(|w| {
+        w.set_per(true);
+        w.set_pnb(idx as u8);
+        #[cfg(flash_l4)]
+        w.set_bker(bank);
+        w.set_eopie(true);
+        w.set_errie(true);
+    });
+
+    pac::FLASH.cr().modify(|w| {
+        #[cfg(flash_wb)]
+        w.set_strt(true);
+        #[cfg(flash_l4)]
+        w.set_start(true);
+    });
+
+    let ret: Result<(), Error> = wait_ready().await;
+
+    pac::FLASH.cr().modify(|w| {
+        w.set_per(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+
+    clear_all_err();
+    ret
+}
+
+/// Page number within its bank and physical bank (`true` for bank 2) of `sector`.
+///
+/// When the banks are swapped (`FB_MODE`), bank 2 is mapped at the flash start and the erase has
+/// to select the other physical bank.
+#[cfg(flash_l4)]
+fn page_index(sector: &FlashSector) -> (u32, bool) {
+    let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
+    let (idx, bank) = if idx > 255 { (idx - 256, true) } else { (idx, false) };
+    (idx, bank != pac::SYSCFG.memrmp().read().fb_mode())
 }
 
 pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), Error> {
+    with_flash_access(|| blocking_erase_sector_inner(sector))
+}
+
+unsafe fn blocking_erase_sector_inner(sector: &FlashSector) -> Result<(), Error> {
     #[cfg(any(flash_l0, flash_l1))]
     {
         pac::FLASH.pecr().modify(|w| {
@@ -95,10 +259,10 @@ pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), E
 
     #[cfg(any(flash_wl, flash_wb, flash_l4, flash_l5))]
     {
-        let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
-
         #[cfg(flash_l4)]
-        let (idx, bank) = if idx > 255 { (idx - 256, true) } else { (idx, false) };
+        let (idx, bank) = page_index(sector);
+        #[cfg(not(flash_l4))]
+        let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
 
         #[cfg(flash_l5)]
         let (idx, bank) = if pac::FLASH.optr().read().dbank() {
@@ -162,6 +326,24 @@ pub(crate) unsafe fn clear_all_err() {
     pac::FLASH.nssr().modify(|_| {});
 }
 
+#[cfg(any(flash_l4, flash_wb))]
+pub(crate) async fn wait_ready() -> Result<(), Error> {
+    use core::future::poll_fn;
+    use core::task::Poll;
+
+    poll_fn(|cx| {
+        WAKER.register(cx.waker());
+
+        if pac::FLASH.sr().read().bsy() {
+            Poll::Pending
+        } else {
+            // Not busy anymore, so this only collects the error flags.
+            Poll::Ready(unsafe { wait_ready_blocking() })
+        }
+    })
+    .await
+}
+
 pub(crate) unsafe fn wait_ready_blocking() -> Result<(), Error> {
     loop {
         #[cfg(not(flash_l5))]
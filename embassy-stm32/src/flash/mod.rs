@@ -1,25 +1,37 @@
 //! Flash memory (FLASH)
 use embedded_storage::nor_flash::{NorFlashError, NorFlashErrorKind};
 
-#[cfg(flash_f4)]
+#[cfg(any(flash_f4, flash_l4, flash_wb))]
 mod asynch;
 #[cfg(flash)]
 mod common;
+#[cfg(all(flash_l4, flash_dual_bank))]
+mod dual_bank;
 #[cfg(eeprom)]
 mod eeprom;
+#[cfg(any(flash_l4, flash_wb))]
+mod option_bytes;
+mod ram;
 
-#[cfg(flash_f4)]
+#[cfg(any(flash_f4, flash_l4, flash_wb))]
 pub use asynch::InterruptHandler;
 #[cfg(flash)]
 pub use common::*;
+#[cfg(all(flash_l4, flash_dual_bank))]
+pub use dual_bank::*;
 #[cfg(eeprom)]
 #[allow(unused_imports)]
 pub use eeprom::*;
+#[cfg(any(flash_l4, flash_wb))]
+pub use option_bytes::*;
+pub use ram::*;
 
 pub use crate::_generated::flash_regions::*;
 #[cfg(eeprom)]
 pub use crate::_generated::{EEPROM_BASE, EEPROM_SIZE};
 pub use crate::_generated::{FLASH_BASE, FLASH_SIZE, MAX_ERASE_SIZE, WRITE_SIZE};
+#[cfg(flash_wb)]
+pub use crate::_generated::{SRAM1_BASE, SRAM2A_BASE, SRAM2A_SIZE, SRAM2B_BASE, SRAM2B_SIZE};
 
 /// Get all flash regions.
 pub fn get_flash_regions() -> &'static [&'static FlashRegion] {
@@ -0,0 +1,388 @@
+//! Option bytes
+//!
+//! Option bytes are changed in two explicit steps: [`Flash::stage_option_bytes`] programs the
+//! new values into the option-byte area, and [`Flash::launch_option_bytes`] reloads them, which
+//! resets the device. Until the launch step (or the next power-on reset), the running
+//! configuration is unchanged.
+
+use super::{family, Error, Flash, BANK1_REGION};
+use crate::pac;
+
+/// PCROP granularity, in bytes.
+#[cfg(all(flash_l4, not(rcc_l4plus)))]
+const PCROP_UNIT: u32 = 8;
+#[cfg(all(flash_l4, rcc_l4plus))]
+const PCROP_UNIT: u32 = 16;
+#[cfg(flash_wb)]
+const PCROP_UNIT: u32 = 2048;
+
+/// Register value used to disable a PCROP area (start above end).
+#[cfg(flash_l4)]
+const PCROP_DISABLED: u16 = 0xFFFF;
+#[cfg(flash_wb)]
+const PCROP_DISABLED: u16 = 0x1FF;
+
+/// Readout protection level.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RdpLevel {
+    /// No protection.
+    Level0,
+    /// Flash readout protection. Going back to level 0 mass-erases the flash.
+    Level1,
+    /// Chip protection. Debug and option-byte changes are disabled **permanently**.
+    Level2,
+}
+
+impl RdpLevel {
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            0xAA => Self::Level0,
+            0xCC => Self::Level2,
+            _ => Self::Level1,
+        }
+    }
+
+    fn to_bits(self) -> u8 {
+        match self {
+            Self::Level0 => 0xAA,
+            Self::Level1 => 0xBB,
+            Self::Level2 => 0xCC,
+        }
+    }
+}
+
+/// Acknowledgement of the irreversible consequences of an RDP level change.
+///
+/// See [`Flash::set_rdp_level`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RdpConfirmation {
+    /// Going to level 2 permanently disables debug access and option byte changes.
+    PermanentlyLockChip,
+    /// Going from level 1 back to level 0 mass-erases the flash, including the running firmware.
+    MassEraseFlash,
+}
+
+/// Brown-out reset threshold.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BorLevel {
+    /// Around 1.7 V.
+    Level0,
+    /// Around 2.0 V.
+    Level1,
+    /// Around 2.2 V.
+    Level2,
+    /// Around 2.5 V.
+    Level3,
+    /// Around 2.8 V.
+    Level4,
+}
+
+impl BorLevel {
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            0 => Self::Level0,
+            1 => Self::Level1,
+            2 => Self::Level2,
+            3 => Self::Level3,
+            _ => Self::Level4,
+        }
+    }
+}
+
+/// Protected flash area, as offsets from the flash start.
+///
+/// `start` is inclusive, `end` is exclusive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ProtectedArea {
+    /// Start offset.
+    pub start: u32,
+    /// End offset.
+    pub end: u32,
+}
+
+/// Option bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OptionBytes {
+    /// Readout protection level.
+    pub rdp: RdpLevel,
+    /// Brown-out reset level.
+    pub bor_level: BorLevel,
+    /// Boot configuration, `nBOOT0`. Only used when `nswboot0` is false.
+    pub nboot0: bool,
+    /// Boot configuration, `nBOOT1`.
+    pub nboot1: bool,
+    /// Take the boot configuration from the BOOT0 pin when true, from `nboot0` otherwise.
+    pub nswboot0: bool,
+    /// Write-protected area A of bank 1. Must be page aligned.
+    pub write_protection: Option<ProtectedArea>,
+    /// Proprietary code readout protected area of bank 1.
+    pub pcrop: Option<ProtectedArea>,
+    /// Erase the PCROP area when the readout protection is regressed from level 1 to level 0.
+    pub pcrop_erase_on_rdp_regression: bool,
+}
+
+impl<'d, MODE> Flash<'d, MODE> {
+    /// Read the currently loaded option bytes.
+    pub fn option_bytes(&self) -> OptionBytes {
+        let optr = pac::FLASH.optr().read();
+
+        let page_size = BANK1_REGION.erase_size;
+        let wrp = pac::FLASH.wrp1ar().read();
+        let write_protection = (wrp.wrp1a_strt() <= wrp.wrp1a_end()).then(|| ProtectedArea {
+            start: wrp.wrp1a_strt() as u32 * page_size,
+            end: (wrp.wrp1a_end() as u32 + 1) * page_size,
+        });
+
+        #[cfg(flash_l4)]
+        let (pcrop_start, pcrop_end) = (
+            pac::FLASH.pcrop1sr().read().pcrop1_strt(),
+            pac::FLASH.pcrop1er().read().pcrop1_end(),
+        );
+        #[cfg(flash_l4)]
+        let pcrop_rdp = pac::FLASH.pcrop1er().read().pcrop_rdp();
+
+        #[cfg(flash_wb)]
+        let (pcrop_start, pcrop_end) = (
+            pac::FLASH.pcrop1asr().read().pcrop1a_strt(),
+            pac::FLASH.pcrop1aer().read().pcrop1a_end(),
+        );
+        #[cfg(flash_wb)]
+        let pcrop_rdp = pac::FLASH.pcrop1aer().read().pcrop_rdp();
+
+        let pcrop = (pcrop_start <= pcrop_end).then(|| ProtectedArea {
+            start: pcrop_start as u32 * PCROP_UNIT,
+            end: (pcrop_end as u32 + 1) * PCROP_UNIT,
+        });
+
+        OptionBytes {
+            rdp: RdpLevel::from_bits(optr.rdp()),
+            bor_level: BorLevel::from_bits(optr.bor_lev()),
+            nboot0: optr.nboot0(),
+            nboot1: optr.nboot1(),
+            nswboot0: optr.nswboot0(),
+            write_protection,
+            pcrop,
+            pcrop_erase_on_rdp_regression: pcrop_rdp,
+        }
+    }
+
+    /// Program new option bytes.
+    ///
+    /// The new values are stored in flash, but only take effect after [`Self::launch_option_bytes`]
+    /// or the next power-on reset.
+    ///
+    /// The readout protection level can't be changed here, because some changes are irreversible:
+    /// `ob.rdp` must be the current level, otherwise [`Error::Protected`] is returned. Use
+    /// [`Self::set_rdp_level`] to change it.
+    pub fn stage_option_bytes(&mut self, ob: &OptionBytes) -> Result<(), Error> {
+        if ob.rdp != self.rdp_level() {
+            return Err(Error::Protected);
+        }
+        self.program_option_bytes(ob, None)
+    }
+
+    /// Program the option bytes, changing the readout protection level only if `rdp` is set.
+    fn program_option_bytes(&mut self, ob: &OptionBytes, rdp: Option<RdpLevel>) -> Result<(), Error> {
+        let page_size = BANK1_REGION.erase_size;
+        let (wrp_start, wrp_end) = match ob.write_protection {
+            Some(area) => {
+                check_area(&area, page_size)?;
+                ((area.start / page_size) as u8, (area.end / page_size - 1) as u8)
+            }
+            None => (0xFF, 0x00),
+        };
+        let (pcrop_start, pcrop_end) = match ob.pcrop {
+            Some(area) => {
+                check_area(&area, PCROP_UNIT)?;
+                ((area.start / PCROP_UNIT) as u16, (area.end / PCROP_UNIT - 1) as u16)
+            }
+            None => (PCROP_DISABLED, 0),
+        };
+
+        family::with_flash_access(|| unsafe {
+            family::clear_all_err();
+            family::wait_ready_blocking()?;
+            unlock_option_bytes();
+
+            pac::FLASH.optr().modify(|w| {
+                if let Some(rdp) = rdp {
+                    w.set_rdp(rdp.to_bits());
+                }
+                w.set_bor_lev(ob.bor_level as u8);
+                w.set_nboot0(ob.nboot0);
+                w.set_nboot1(ob.nboot1);
+                w.set_nswboot0(ob.nswboot0);
+            });
+            pac::FLASH.wrp1ar().modify(|w| {
+                w.set_wrp1a_strt(wrp_start);
+                w.set_wrp1a_end(wrp_end);
+            });
+
+            #[cfg(flash_l4)]
+            {
+                pac::FLASH.pcrop1sr().modify(|w| w.set_pcrop1_strt(pcrop_start));
+                pac::FLASH.pcrop1er().modify(|w| {
+                    w.set_pcrop1_end(pcrop_end);
+                    w.set_pcrop_rdp(ob.pcrop_erase_on_rdp_regression);
+                });
+            }
+
+            #[cfg(flash_wb)]
+            {
+                pac::FLASH.pcrop1asr().modify(|w| w.set_pcrop1a_strt(pcrop_start));
+                pac::FLASH.pcrop1aer().modify(|w| {
+                    w.set_pcrop1a_end(pcrop_end);
+                    w.set_pcrop_rdp(ob.pcrop_erase_on_rdp_regression);
+                });
+            }
+
+            pac::FLASH.cr().modify(|w| w.set_optstrt(true));
+            let ret = family::wait_ready_blocking();
+
+            lock_option_bytes();
+            ret
+        })
+    }
+
+    /// Read the current readout protection level.
+    pub fn rdp_level(&self) -> RdpLevel {
+        RdpLevel::from_bits(pac::FLASH.optr().read().rdp())
+    }
+
+    /// Stage a new readout protection level, keeping the other option bytes.
+    ///
+    /// Like with [`Self::stage_option_bytes`], the new level only takes effect after
+    /// [`Self::launch_option_bytes`] or the next power-on reset. Nothing is staged if the level
+    /// doesn't change.
+    ///
+    /// Changes that can't be undone must be confirmed, otherwise [`Error::Protected`] is returned:
+    /// - going to [`RdpLevel::Level2`] requires [`RdpConfirmation::PermanentlyLockChip`],
+    /// - going from [`RdpLevel::Level1`] to [`RdpLevel::Level0`] requires
+    ///   [`RdpConfirmation::MassEraseFlash`].
+    ///
+    /// At level 2, the level can't be changed anymore and [`Error::Protected`] is returned.
+    pub fn set_rdp_level(&mut self, level: RdpLevel, confirmation: Option<RdpConfirmation>) -> Result<(), Error> {
+        let mut ob = self.option_bytes();
+
+        let required = match (ob.rdp, level) {
+            (current, level) if current == level => return Ok(()),
+            (RdpLevel::Level2, _) => return Err(Error::Protected),
+            (_, RdpLevel::Level2) => Some(RdpConfirmation::PermanentlyLockChip),
+            (RdpLevel::Level1, RdpLevel::Level0) => Some(RdpConfirmation::MassEraseFlash),
+            _ => None,
+        };
+        if required.is_some() && confirmation != required {
+            return Err(Error::Protected);
+        }
+
+        match level {
+            RdpLevel::Level0 => warn!("RDP regression to level 0 staged, the flash will be mass-erased on launch"),
+            RdpLevel::Level1 => {}
+            RdpLevel::Level2 => warn!("RDP level 2 staged, the chip will be locked permanently on launch"),
+        }
+
+        ob.rdp = level;
+        self.program_option_bytes(&ob, Some(level))
+    }
+
+    /// Reload the option bytes, applying the values programmed with [`Self::stage_option_bytes`].
+    ///
+    /// This resets the device.
+    pub fn launch_option_bytes(&mut self) -> ! {
+        unsafe {
+            family::clear_all_err();
+            let _ = family::wait_ready_blocking();
+            unlock_option_bytes();
+        }
+
+        pac::FLASH.cr().modify(|w| w.set_obl_launch(true));
+
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+}
+
+fn check_area(area: &ProtectedArea, unit: u32) -> Result<(), Error> {
+    if area.start >= area.end || area.end > BANK1_REGION.size {
+        return Err(Error::Size);
+    }
+    if area.start % unit != 0 || area.end % unit != 0 {
+        return Err(Error::Unaligned);
+    }
+    Ok(())
+}
+
+pub(super) unsafe fn unlock_option_bytes() {
+    family::unlock();
+
+    if pac::FLASH.cr().read().optlock() {
+        pac::FLASH.optkeyr().write_value(0x0819_2A3B);
+        pac::FLASH.optkeyr().write_value(0x4C5D_6E7F);
+    }
+}
+
+pub(super) unsafe fn lock_option_bytes() {
+    pac::FLASH.cr().modify(|w| w.set_optlock(true));
+    family::lock();
+}
+
+/// Memory reserved by CPU2 on STM32WB, as configured by the secure option bytes.
+///
+/// The wireless stack installer sets these boundaries when flashing a stack. Everything at or
+/// above a secure start address belongs to CPU2 and cannot be accessed from CPU1.
+#[cfg(flash_wb)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Cpu2MemoryMap {
+    /// Start address of the secure flash area, or `None` if flash security is disabled.
+    pub secure_flash_start: Option<u32>,
+    /// CPU2 boot reset vector.
+    pub cpu2_boot_vector: u32,
+    /// Start address of the secure part of SRAM2a, or `None` if disabled.
+    pub secure_sram2a_start: Option<u32>,
+    /// Start address of the secure part of SRAM2b, or `None` if disabled.
+    pub secure_sram2b_start: Option<u32>,
+    /// Address of the IPCC mailbox data buffer in SRAM2.
+    pub ipcc_buffer: u32,
+}
+
+#[cfg(flash_wb)]
+impl Cpu2MemoryMap {
+    /// Flash available to the application, in bytes from the flash start.
+    pub fn application_flash_size(&self) -> u32 {
+        match self.secure_flash_start {
+            Some(start) => start - super::FLASH_BASE as u32,
+            None => super::FLASH_SIZE as u32,
+        }
+    }
+}
+
+/// Read the CPU2 memory boundaries from the secure option bytes.
+#[cfg(flash_wb)]
+pub fn cpu2_memory_map() -> Cpu2MemoryMap {
+    use super::{SRAM1_BASE, SRAM2A_BASE, SRAM2B_BASE};
+
+    let sfr = pac::FLASH.sfr().read();
+    let srrvr = pac::FLASH.srrvr().read();
+
+    // C2OPT selects whether the CPU2 boot vector is an offset in flash or in SRAM.
+    let boot_base = match srrvr.c2opt() {
+        true => super::FLASH_BASE,
+        false => SRAM1_BASE,
+    };
+
+    Cpu2MemoryMap {
+        secure_flash_start: (!sfr.fsd())
+            .then(|| super::FLASH_BASE as u32 + sfr.sfsa() as u32 * BANK1_REGION.erase_size),
+        cpu2_boot_vector: boot_base as u32 + srrvr.sbrv() * 4,
+        secure_sram2a_start: (!srrvr.brsd()).then(|| SRAM2A_BASE as u32 + srrvr.sbrsa() as u32 * 1024),
+        secure_sram2b_start: (!srrvr.nbrsd()).then(|| SRAM2B_BASE as u32 + srrvr.snbrsa() as u32 * 1024),
+        ipcc_buffer: SRAM2A_BASE as u32 + pac::FLASH.ipccbr().read().ipccdba() as u32 * 8,
+    }
+}
@@ -0,0 +1,73 @@
+//! Flash-safe execution
+//!
+//! While the flash is being programmed or erased, instruction fetches from the same bank stall
+//! until the operation completes, and code relying on flash contents that are being rewritten
+//! (such as in-application firmware updates on single-bank parts) will fault. Code that must run
+//! during such operations has to live in RAM: put it in a [`ram_function!`] and run it inside
+//! [`with_flash_safe_interrupts`], which keeps interrupts whose handlers live in flash masked.
+
+use cortex_m::interrupt::InterruptNumber;
+use cortex_m::peripheral::NVIC;
+
+use crate::interrupt::Interrupt;
+
+/// Place a function in RAM.
+///
+/// The function is linked in the `.data` section, which the runtime copies to RAM at startup.
+/// It is never inlined, so its body stays in RAM even when called from flash. Everything it calls
+/// must be inlined or be a RAM function too.
+///
+/// ```rust,ignore
+/// embassy_stm32::ram_function! {
+///     fn program(address: u32, value: u32) {
+///         unsafe { core::ptr::write_volatile(address as *mut u32, value) };
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! ram_function {
+    ($item:item) => {
+        #[link_section = ".data.ram_function"]
+        #[inline(never)]
+        $item
+    };
+}
+
+/// Run `f` with all interrupts masked in the NVIC, except the ones in `allowed`.
+///
+/// `allowed` lists the interrupts whose handlers (and everything they call) are placed in RAM,
+/// for example with [`ram_function!`]. All other interrupts are kept pending until `f` returns,
+/// then the previously enabled interrupts are re-enabled.
+pub fn with_flash_safe_interrupts<R>(allowed: &[Interrupt], f: impl FnOnce() -> R) -> R {
+    let nvic = unsafe { &*NVIC::PTR };
+    let mut enabled = [0u32; 16];
+    let words = nvic.iser.len().min(enabled.len());
+
+    critical_section::with(|_| {
+        for (i, saved) in enabled.iter_mut().enumerate().take(words) {
+            let mut mask = nvic.iser[i].read();
+            *saved = mask;
+            for irq in allowed {
+                let n = irq.number() as usize;
+                if n / 32 == i {
+                    mask &= !(1 << (n % 32));
+                }
+            }
+            unsafe { nvic.icer[i].write(mask) };
+        }
+    });
+
+    // Make sure no masked interrupt fires after this point.
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+
+    let r = f();
+
+    critical_section::with(|_| {
+        for (i, saved) in enabled.iter().enumerate().take(words) {
+            unsafe { nvic.iser[i].write(*saved) };
+        }
+    });
+
+    r
+}
@@ -863,6 +863,104 @@ foreach_pin!(
     };
 );
 
+/// Declare a struct holding the configured GPIO pins of a board.
+///
+/// Each field is written as `name: Kind<PIN, args...>`, where `Kind` is one of [`Output`],
+/// [`OutputOpenDrain`], [`Input`], [`Flex`] or [`ExtiInput`](crate::exti::ExtiInput), and the
+/// optional arguments are those of the driver's `new`:
+///
+/// - `Output<PIN, Level, Speed>` and `OutputOpenDrain<PIN, Level, Speed>`, defaulting to
+///   `Level::Low` and `Speed::Low`,
+/// - `Input<PIN, Pull>` and `ExtiInput<PIN, Pull>`, defaulting to `Pull::None`,
+/// - `Flex<PIN>`.
+///
+/// The generated `new` takes the pins in declaration order, and a `(pin, exti_channel)` tuple
+/// for an `ExtiInput`.
+///
+/// ```rust,ignore
+/// use embassy_stm32::gpio::{Level, Pull, Speed};
+///
+/// embassy_stm32::board_pins! {
+///     /// Nucleo board pins.
+///     pub struct Board {
+///         led: Output<PA5>,
+///         cs: Output<PB6, Level::High, Speed::VeryHigh>,
+///         button: ExtiInput<PC13, Pull::Up>,
+///     }
+/// }
+///
+/// let mut board = Board::new(p.PA5, p.PB6, (p.PC13, p.EXTI13));
+/// board.led.set_high();
+/// ```
+#[macro_export]
+macro_rules! board_pins {
+    (
+        $(#[$outer:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$attr:meta])*
+                $field:ident: $kind:ident<$pin:ident $(, $arg:path)* $(,)?>
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$outer])*
+        $vis struct $name<'d> {
+            $(
+                $(#[$attr])*
+                pub $field: $crate::board_pins!(@type $kind),
+            )*
+        }
+
+        impl<'d> $name<'d> {
+            /// Configure all pins.
+            pub fn new($($field: $crate::board_pins!(@param $kind $pin)),*) -> Self {
+                Self {
+                    $($field: $crate::board_pins!(@new $kind $field $(, $arg)*),)*
+                }
+            }
+        }
+    };
+
+    (@type Output) => { $crate::gpio::Output<'d> };
+    (@type OutputOpenDrain) => { $crate::gpio::OutputOpenDrain<'d> };
+    (@type Input) => { $crate::gpio::Input<'d> };
+    (@type Flex) => { $crate::gpio::Flex<'d> };
+    (@type ExtiInput) => { $crate::exti::ExtiInput<'d> };
+
+    (@param ExtiInput $pin:ident) => {
+        (
+            $crate::Peri<'d, $crate::peripherals::$pin>,
+            $crate::Peri<'d, <$crate::peripherals::$pin as $crate::gpio::Pin>::ExtiChannel>,
+        )
+    };
+    (@param $kind:ident $pin:ident) => { $crate::Peri<'d, $crate::peripherals::$pin> };
+
+    (@new Output $p:ident $(, $level:path $(, $speed:path)?)?) => {
+        $crate::gpio::Output::new(
+            $p,
+            $crate::board_pins!(@or $($level)?, $crate::gpio::Level::Low),
+            $crate::board_pins!(@or $($($speed)?)?, $crate::gpio::Speed::Low),
+        )
+    };
+    (@new OutputOpenDrain $p:ident $(, $level:path $(, $speed:path)?)?) => {
+        $crate::gpio::OutputOpenDrain::new(
+            $p,
+            $crate::board_pins!(@or $($level)?, $crate::gpio::Level::Low),
+            $crate::board_pins!(@or $($($speed)?)?, $crate::gpio::Speed::Low),
+        )
+    };
+    (@new Input $p:ident $(, $pull:path)?) => {
+        $crate::gpio::Input::new($p, $crate::board_pins!(@or $($pull)?, $crate::gpio::Pull::None))
+    };
+    (@new ExtiInput $p:ident $(, $pull:path)?) => {
+        $crate::exti::ExtiInput::new($p.0, $p.1, $crate::board_pins!(@or $($pull)?, $crate::gpio::Pull::None))
+    };
+    (@new Flex $p:ident) => { $crate::gpio::Flex::new($p) };
+
+    (@or , $default:path) => { $default };
+    (@or $value:path, $default:path) => { $value };
+}
+
 pub(crate) unsafe fn init(_cs: CriticalSection) {
     #[cfg(afio)]
     crate::rcc::enable_and_reset_with_cs::<crate::peripherals::AFIO>(_cs);
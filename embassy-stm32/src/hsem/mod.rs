@@ -12,6 +12,7 @@ use crate::Peri;
 
 /// HSEM error.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum HsemError {
     /// Locking the semaphore failed.
     LockFailed,
@@ -62,6 +63,92 @@ pub fn get_current_coreid() -> CoreId {
     }
 }
 
+/// Semaphores used by the HAL to share hardware with the other core.
+///
+/// They don't go through [`HardwareSemaphore`], as the drivers using them don't own the HSEM
+/// peripheral. The HSEM clock is enabled on first use and left on.
+///
+/// The semaphore IDs are the ones the STM32WB wireless stack on CPU2 uses (see AN5289, "Hardware
+/// semaphores").
+#[cfg(stm32wb)]
+pub(crate) mod shared {
+    use core::sync::atomic::{compiler_fence, AtomicBool, Ordering};
+
+    use super::get_current_coreid;
+    use crate::pac::HSEM;
+
+    /// Semaphore guarding the PKA between both cores.
+    pub(crate) const PKA_SEMID: usize = 1;
+    /// Semaphore guarding the flash between both cores.
+    pub(crate) const FLASH_SEMID: usize = 2;
+    /// Semaphore guarding `RCC_CR`, `RCC_CFGR`, `RCC_EXTCFGR`, `RCC_SMPSCR` and the PWR supply
+    /// configuration.
+    pub(crate) const RCC_SEMID: usize = 3;
+    /// Semaphore guarding the Stop mode entry and exit of the low-power executor.
+    #[cfg(feature = "low-power")]
+    pub(crate) const STOP_SEMID: usize = 4;
+    /// Semaphore guarding the CLK48 clock, HSI48 and the CLK48 mux.
+    pub(crate) const CLK48_SEMID: usize = 5;
+    /// Semaphore taken by CPU2 when CPU1 must not start a flash operation.
+    pub(crate) const BLOCK_FLASH_REQ_BY_CPU2_SEMID: usize = 6;
+
+    fn enable_clock() {
+        static ENABLED: AtomicBool = AtomicBool::new(false);
+        critical_section::with(|_| {
+            if !ENABLED.load(Ordering::Relaxed) {
+                crate::rcc::enable::<crate::peripherals::HSEM>();
+                ENABLED.store(true, Ordering::Relaxed);
+            }
+        });
+    }
+
+    /// Take semaphore `sem_id` with the 1-step procedure, waiting while the other core holds it.
+    pub(crate) fn blocking_lock(sem_id: usize) {
+        enable_clock();
+        loop {
+            // Reading RLR takes the semaphore if it is free.
+            let r = HSEM.rlr(sem_id).read();
+            if r.lock() && r.coreid() == get_current_coreid() as u8 && r.procid() == 0 {
+                break;
+            }
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    /// Try to take semaphore `sem_id` with the 1-step procedure, returning whether it was taken.
+    pub(crate) fn try_lock(sem_id: usize) -> bool {
+        enable_clock();
+        let r = HSEM.rlr(sem_id).read();
+        let locked = r.lock() && r.coreid() == get_current_coreid() as u8 && r.procid() == 0;
+        compiler_fence(Ordering::SeqCst);
+        locked
+    }
+
+    /// Release semaphore `sem_id`, taken with [`blocking_lock`].
+    pub(crate) fn unlock(sem_id: usize) {
+        compiler_fence(Ordering::SeqCst);
+        HSEM.r(sem_id).write(|w| {
+            w.set_coreid(get_current_coreid() as u8);
+            w.set_procid(0);
+            w.set_lock(false);
+        });
+    }
+
+    /// Run `f` holding semaphore `sem_id`.
+    pub(crate) fn with_lock<R>(sem_id: usize, f: impl FnOnce() -> R) -> R {
+        blocking_lock(sem_id);
+        let r = f();
+        unlock(sem_id);
+        r
+    }
+
+    /// Whether semaphore `sem_id` is taken, by either core.
+    pub(crate) fn is_locked(sem_id: usize) -> bool {
+        enable_clock();
+        HSEM.r(sem_id).read().lock()
+    }
+}
+
 /// Translates the core ID to an index into the interrupt registers.
 #[inline(always)]
 fn core_id_to_index(core: CoreId) -> usize {
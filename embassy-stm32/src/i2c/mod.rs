@@ -119,6 +119,7 @@ struct I2CDropGuard<'d> {
     info: &'static Info,
     scl: Option<Peri<'d, AnyPin>>,
     sda: Option<Peri<'d, AnyPin>>,
+    suspended: bool,
 }
 impl<'d> Drop for I2CDropGuard<'d> {
     fn drop(&mut self) {
@@ -129,7 +130,9 @@ impl<'d> Drop for I2CDropGuard<'d> {
             x.set_as_disconnected()
         }
 
-        self.info.rcc.disable();
+        if !self.suspended {
+            self.info.rcc.disable();
+        }
     }
 }
 
@@ -222,6 +225,7 @@ impl<'d, M: Mode> I2c<'d, M, Master> {
                 info: T::info(),
                 scl,
                 sda,
+                suspended: false,
             },
         };
         this.enable_and_init(freq, config);
@@ -242,6 +246,26 @@ impl<'d, M: Mode, IM: MasterMode> I2c<'d, M, IM> {
             deadline: Instant::now() + self.timeout,
         }
     }
+
+    /// Gate the peripheral clock while the bus is idle.
+    ///
+    /// The configuration is kept, and the I2C no longer keeps the chip out of the low-power stop
+    /// modes. It must be [resumed](Self::resume) before the next transfer; in slave mode it does
+    /// not respond to its address in the meantime. Does nothing if the I2C is already suspended.
+    pub fn suspend(&mut self) {
+        if !self._drop_guard.suspended {
+            self._drop_guard.suspended = true;
+            self.info.rcc.disable();
+        }
+    }
+
+    /// Ungate the peripheral clock after [`suspend`](Self::suspend).
+    pub fn resume(&mut self) {
+        if self._drop_guard.suspended {
+            self._drop_guard.suspended = false;
+            self.info.rcc.enable();
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
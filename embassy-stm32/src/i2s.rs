@@ -60,12 +60,9 @@ pub enum Error {
 }
 
 impl From<ringbuffer::Error> for Error {
-    fn from(#[allow(unused)] err: ringbuffer::Error) -> Self {
-        #[cfg(feature = "defmt")]
-        {
-            if err == ringbuffer::Error::DmaUnsynced {
-                defmt::error!("Ringbuffer broken invariants detected!");
-            }
+    fn from(err: ringbuffer::Error) -> Self {
+        if err == ringbuffer::Error::DmaUnsynced {
+            error!("Ringbuffer broken invariants detected!");
         }
         Self::Overrun
     }
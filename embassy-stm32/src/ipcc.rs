@@ -1,14 +1,16 @@
 //! Inter-Process Communication Controller (IPCC)
 
 use core::future::poll_fn;
+use core::marker::PhantomData;
 use core::sync::atomic::{compiler_fence, Ordering};
 use core::task::Poll;
 
+use embassy_hal_internal::drop::OnDrop;
 use embassy_sync::waitqueue::AtomicWaker;
 
 use crate::interrupt::typelevel::Interrupt;
 use crate::peripherals::IPCC;
-use crate::{interrupt, rcc};
+use crate::{interrupt, rcc, Peri};
 
 /// Interrupt handler.
 pub struct ReceiveInterruptHandler {}
@@ -76,7 +78,8 @@ impl interrupt::typelevel::Handler<interrupt::typelevel::IPCC_C1_TX> for Transmi
 
 /// IPCC config.
 #[non_exhaustive]
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Config {
     // TODO: add IPCC peripheral configuration, if any, here
     // reserved for future use
@@ -84,7 +87,8 @@ pub struct Config {
 
 /// Channel.
 #[allow(missing_docs)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(C)]
 pub enum IpccChannel {
     Channel1 = 0,
@@ -104,9 +108,6 @@ impl Ipcc {
         rcc::enable_and_reset::<IPCC>();
         IPCC::set_cpu2(true);
 
-        // set RF wake-up clock = LSE
-        crate::pac::RCC.csr().modify(|w| w.set_rfwkpsel(0b01));
-
         let regs = IPCC::regs();
 
         regs.cpu(0).cr().modify(|w| {
@@ -122,7 +123,29 @@ impl Ipcc {
         unsafe { crate::interrupt::typelevel::IPCC_C1_TX::enable() };
     }
 
+    /// Enable IPCC and split it into owned channel handles, so each channel can be used from a
+    /// different task.
+    ///
+    /// The ownership is advisory: the handles can't conflict with each other, but [`Self::send`],
+    /// [`Self::flush`] and [`Self::receive`] still reach every channel without a handle. They stay
+    /// public for `embassy-stm32-wpan`, which owns the channels of the wireless stack. Don't split
+    /// the IPCC when using it, or only use the channels it leaves free.
+    pub fn split<'d>(_ipcc: Peri<'d, IPCC>, config: Config) -> IpccChannels<'d> {
+        Self::enable(config);
+
+        IpccChannels {
+            ch1: IpccChannelHandle::new(IpccChannel::Channel1),
+            ch2: IpccChannelHandle::new(IpccChannel::Channel2),
+            ch3: IpccChannelHandle::new(IpccChannel::Channel3),
+            ch4: IpccChannelHandle::new(IpccChannel::Channel4),
+            ch5: IpccChannelHandle::new(IpccChannel::Channel5),
+            ch6: IpccChannelHandle::new(IpccChannel::Channel6),
+        }
+    }
+
     /// Send data to an IPCC channel. The closure is called to write the data when appropriate.
+    ///
+    /// This doesn't check that the channel is free of [`IpccChannelHandle`]s, see [`Self::split`].
     pub async fn send(channel: IpccChannel, f: impl FnOnce()) {
         let regs = IPCC::regs();
 
@@ -137,6 +160,8 @@ impl Ipcc {
     }
 
     /// Wait for the tx channel to become clear
+    ///
+    /// This doesn't check that the channel is free of [`IpccChannelHandle`]s, see [`Self::split`].
     pub async fn flush(channel: IpccChannel) {
         let regs = IPCC::regs();
 
@@ -145,6 +170,9 @@ impl Ipcc {
             trace!("ipcc: ch {}: wait for tx free", channel as u8);
         }
 
+        // Mask the interrupt again if the future is dropped before the channel is free.
+        let on_drop = OnDrop::new(|| regs.cpu(0).mr().modify(|w| w.set_chfm(channel as usize, true)));
+
         poll_fn(|cx| {
             IPCC::state().tx_waker_for(channel).register(cx.waker());
             // If bit is set to 1 then interrupt is disabled; we want to enable the interrupt
@@ -162,9 +190,13 @@ impl Ipcc {
             }
         })
         .await;
+
+        on_drop.defuse();
     }
 
     /// Receive data from an IPCC channel. The closure is called to read the data when appropriate.
+    ///
+    /// This doesn't check that the channel is free of [`IpccChannelHandle`]s, see [`Self::split`].
     pub async fn receive<R>(channel: IpccChannel, mut f: impl FnMut() -> Option<R>) -> R {
         let regs = IPCC::regs();
 
@@ -174,6 +206,9 @@ impl Ipcc {
                 trace!("ipcc: ch {}: wait for rx occupied", channel as u8);
             }
 
+            // Mask the interrupt again if the future is dropped before data arrives.
+            let on_drop = OnDrop::new(|| regs.cpu(0).mr().modify(|w| w.set_chom(channel as usize, true)));
+
             poll_fn(|cx| {
                 IPCC::state().rx_waker_for(channel).register(cx.waker());
                 // If bit is set to 1 then interrupt is disabled; we want to enable the interrupt
@@ -183,7 +218,7 @@ impl Ipcc {
 
                 if regs.cpu(1).sr().read().chf(channel as usize) {
                     // If bit is set to 1 then interrupt is disabled; we want to disable the interrupt
-                    regs.cpu(0).mr().modify(|w| w.set_chfm(channel as usize, true));
+                    regs.cpu(0).mr().modify(|w| w.set_chom(channel as usize, true));
 
                     Poll::Ready(())
                 } else {
@@ -192,6 +227,8 @@ impl Ipcc {
             })
             .await;
 
+            on_drop.defuse();
+
             trace!("ipcc: ch {}: read data", channel as u8);
 
             match f() {
@@ -207,6 +244,52 @@ impl Ipcc {
     }
 }
 
+/// IPCC channel handles, returned by [`Ipcc::split`].
+#[allow(missing_docs)]
+pub struct IpccChannels<'d> {
+    pub ch1: IpccChannelHandle<'d>,
+    pub ch2: IpccChannelHandle<'d>,
+    pub ch3: IpccChannelHandle<'d>,
+    pub ch4: IpccChannelHandle<'d>,
+    pub ch5: IpccChannelHandle<'d>,
+    pub ch6: IpccChannelHandle<'d>,
+}
+
+/// Owned handle to one IPCC channel, in both directions.
+pub struct IpccChannelHandle<'d> {
+    channel: IpccChannel,
+    _phantom: PhantomData<&'d mut IPCC>,
+}
+
+impl<'d> IpccChannelHandle<'d> {
+    fn new(channel: IpccChannel) -> Self {
+        Self {
+            channel,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The channel this handle owns.
+    pub fn channel(&self) -> IpccChannel {
+        self.channel
+    }
+
+    /// Send data to CPU2. The closure is called to write the data when appropriate.
+    pub async fn send(&mut self, f: impl FnOnce()) {
+        Ipcc::send(self.channel, f).await
+    }
+
+    /// Wait for CPU2 to read the last data sent.
+    pub async fn flush(&mut self) {
+        Ipcc::flush(self.channel).await
+    }
+
+    /// Receive data from CPU2. The closure is called to read the data when appropriate.
+    pub async fn receive<R>(&mut self, f: impl FnMut() -> Option<R>) -> R {
+        Ipcc::receive(self.channel, f).await
+    }
+}
+
 impl SealedInstance for crate::peripherals::IPCC {
     fn regs() -> crate::pac::ipcc::Ipcc {
         crate::pac::IPCC
@@ -0,0 +1,346 @@
+//! Infrared transmitter (IRTIM)
+//!
+//! The IRTIM combines two timers on the IR_OUT pin: TIM17 generates the carrier, and TIM16 gates
+//! it with the envelope of the signal. TIM16 counts microseconds and times each mark and space of
+//! the envelope itself, software only queues the next one in the preload registers while the
+//! current one is sent. [`IrTim::blocking_send_nec`] and [`IrTim::blocking_send_rc5`] encode the two
+//! most common remote control protocols.
+#![macro_use]
+
+use crate::gpio::{AfType, AnyPin, OutputType, SealedPin as _, Speed};
+use crate::pac::timer::vals::Ocm;
+use crate::peripherals::{TIM16, TIM17};
+use crate::time::Hertz;
+use crate::timer::low_level::Timer;
+use crate::Peri;
+
+/// Envelope timer tick.
+const ENVELOPE_TICK: Hertz = Hertz(1_000_000);
+
+/// IRTIM configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct Config {
+    /// Carrier frequency.
+    pub carrier_frequency: Hertz,
+    /// Carrier duty cycle, in percent.
+    pub carrier_duty_percent: u8,
+    /// Invert the IR_OUT output, for LEDs driven active low.
+    pub invert: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            carrier_frequency: Hertz(38_000),
+            carrier_duty_percent: 33,
+            invert: false,
+        }
+    }
+}
+
+/// IRTIM configuration error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The carrier duty cycle is above 100 %.
+    InvalidDutyCycle,
+    /// The carrier frequency is zero or above the TIM17 clock.
+    InvalidCarrierFrequency,
+}
+
+/// IRTIM send error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A duration is 0 or above 65535 µs.
+    InvalidDuration,
+    /// The address or command doesn't fit in the frame.
+    InvalidFrame,
+}
+
+/// IRTIM driver.
+pub struct IrTim<'d> {
+    carrier: Timer<'d, TIM17>,
+    envelope: Timer<'d, TIM16>,
+    _pin: Peri<'d, AnyPin>,
+}
+
+impl<'d> IrTim<'d> {
+    /// Create a new IRTIM driver.
+    pub fn new(
+        envelope: Peri<'d, TIM16>,
+        carrier: Peri<'d, TIM17>,
+        pin: Peri<'d, impl OutPin>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        if config.carrier_duty_percent > 100 {
+            return Err(ConfigError::InvalidDutyCycle);
+        }
+        if config.carrier_frequency.0 == 0 || config.carrier_frequency > crate::rcc::frequency::<TIM17>() {
+            return Err(ConfigError::InvalidCarrierFrequency);
+        }
+
+        let carrier = Timer::new(carrier);
+        let mut envelope = Timer::new(envelope);
+
+        carrier.set_frequency(config.carrier_frequency);
+        let compare = (carrier.get_max_compare_value() + 1) * config.carrier_duty_percent as u32 / 100;
+        let regs = carrier.regs_1ch_cmp();
+        regs.ccmr_output(0).modify(|w| {
+            w.set_ocm(0, Ocm::PWM_MODE1);
+            w.set_ocpe(0, true);
+        });
+        regs.ccr(0).modify(|w| w.set_ccr(compare as u16));
+        regs.ccer().modify(|w| w.set_cce(0, true));
+        carrier.set_moe(true);
+        carrier.start();
+
+        envelope.set_tick_freq(ENVELOPE_TICK);
+        envelope.set_autoreload_preload(true);
+        let regs = envelope.regs_1ch_cmp();
+        regs.ccmr_output(0).modify(|w| {
+            w.set_ocm(0, Ocm::FORCE_INACTIVE);
+            w.set_ocpe(0, true);
+        });
+        regs.ccer().modify(|w| w.set_cce(0, true));
+        envelope.set_moe(true);
+
+        crate::pac::SYSCFG.cfgr1().modify(|w| {
+            w.set_ir_mod(crate::pac::syscfg::vals::IrMod::TIM16);
+            w.set_ir_pol(config.invert);
+        });
+
+        pin.set_as_af(pin.af_num(), AfType::output(OutputType::PushPull, Speed::Low));
+
+        Ok(Self {
+            carrier,
+            envelope,
+            _pin: pin.into(),
+        })
+    }
+
+    /// Send the carrier while `on`.
+    pub fn set_mark(&mut self, on: bool) {
+        let ocm = if on { Ocm::FORCE_ACTIVE } else { Ocm::FORCE_INACTIVE };
+        self.envelope
+            .regs_1ch_cmp()
+            .ccmr_output(0)
+            .modify(|w| w.set_ocm(0, ocm));
+    }
+
+    /// Send a raw signal: alternating mark and space durations in microseconds, starting with a mark.
+    ///
+    /// Fails with [`Error::InvalidDuration`] unless each duration is between 1 and 65535 µs. Blocks
+    /// until the signal has been sent. TIM16 times the envelope edges, an interrupt only delays
+    /// them if it lasts longer than a whole mark or space.
+    pub fn blocking_send_raw(&mut self, durations_us: &[u32]) -> Result<(), Error> {
+        if durations_us.iter().any(|d| !(1..=0xFFFF).contains(d)) {
+            return Err(Error::InvalidDuration);
+        }
+        self.send_durations(durations_us);
+        Ok(())
+    }
+
+    fn send_durations(&mut self, durations_us: &[u32]) {
+        let regs = self.envelope.regs_1ch_cmp();
+        // A final space stops the carrier after the last mark.
+        let mut segments = durations_us
+            .iter()
+            .enumerate()
+            .map(|(i, duration)| (i % 2 == 0, *duration))
+            .chain(core::iter::once((false, 1)));
+
+        // Load the first segment and queue the second one before starting.
+        self.envelope.stop();
+        self.queue_segment(unwrap!(segments.next()));
+        regs.egr().write(|w| w.set_ug(true));
+        regs.sr().modify(|w| w.set_uif(false));
+        regs.ccmr_output(0).modify(|w| w.set_ocm(0, Ocm::PWM_MODE1));
+        if let Some(segment) = segments.next() {
+            self.queue_segment(segment);
+        }
+        self.envelope.start();
+
+        // Each update event starts the queued segment, queue the next one meanwhile.
+        for segment in segments {
+            self.wait_segment_start();
+            self.queue_segment(segment);
+        }
+        self.wait_segment_start();
+
+        self.envelope.stop();
+        self.set_mark(false);
+    }
+
+    /// Write the mark or space of `duration_us` to the preload registers, for the next update event.
+    fn queue_segment(&self, (mark, duration_us): (bool, u32)) {
+        let regs = self.envelope.regs_1ch_cmp();
+        regs.arr().write(|w| w.set_arr((duration_us - 1) as u16));
+        // In PWM mode 1, the output is active while the counter is below the compare value.
+        regs.ccr(0).write(|w| w.set_ccr(if mark { 0xFFFF } else { 0 }));
+    }
+
+    fn wait_segment_start(&self) {
+        let regs = self.envelope.regs_1ch_cmp();
+        while !regs.sr().read().uif() {}
+        regs.sr().modify(|w| w.set_uif(false));
+    }
+
+    /// Send an NEC frame with the given address and command.
+    pub fn blocking_send_nec(&mut self, address: u8, command: u8) {
+        self.send_durations(&nec_durations(address, command));
+    }
+
+    /// Send an NEC repeat code, to be sent every 108 ms while a key is held.
+    pub fn blocking_send_nec_repeat(&mut self) {
+        self.send_durations(&[9000, 2250, 562]);
+    }
+
+    /// Send an RC5 frame with the given toggle bit, 5-bit address and 7-bit command (extended RC5).
+    ///
+    /// Fails with [`Error::InvalidFrame`] if `address` is above 31 or `command` above 127.
+    pub fn blocking_send_rc5(&mut self, toggle: bool, address: u8, command: u8) -> Result<(), Error> {
+        let (durations, len) = rc5_durations(toggle, address, command).ok_or(Error::InvalidFrame)?;
+        self.send_durations(&durations[..len]);
+        Ok(())
+    }
+}
+
+/// Mark and space durations of an NEC frame, in microseconds.
+fn nec_durations(address: u8, command: u8) -> [u32; 67] {
+    const UNIT: u32 = 562;
+
+    let data = u32::from_le_bytes([address, !address, command, !command]);
+    let mut durations = [0; 2 + 32 * 2 + 1];
+    durations[0] = 16 * UNIT;
+    durations[1] = 8 * UNIT;
+    for bit in 0..32 {
+        durations[2 + bit * 2] = UNIT;
+        durations[3 + bit * 2] = if data & (1 << bit) != 0 { 3 * UNIT } else { UNIT };
+    }
+    durations[66] = UNIT;
+    durations
+}
+
+/// Mark and space durations of an RC5 frame, in microseconds, and their number. `None` if the
+/// address or command doesn't fit.
+fn rc5_durations(toggle: bool, address: u8, command: u8) -> Option<([u32; 28], usize)> {
+    const HALF_BIT: u32 = 889;
+    if address >= 32 || command >= 128 {
+        return None;
+    }
+
+    // Start bit, inverted command bit 6, toggle, address, command bits 5 to 0, MSB first.
+    let frame: u16 = (1 << 13)
+        | (((command & 0x40) == 0) as u16) << 12
+        | (toggle as u16) << 11
+        | (address as u16) << 6
+        | (command & 0x3F) as u16;
+
+    // Manchester encoding: a one is a space then a mark, a zero a mark then a space. The
+    // frame always starts with a one, so skip its first space half-bit.
+    let mut durations = [0; 28];
+    let mut len = 0;
+    let mut mark = false;
+    for bit in (0..14).rev() {
+        let one = frame & (1 << bit) != 0;
+        for half_mark in [!one, one] {
+            if len == 0 && !half_mark {
+                continue;
+            }
+            if len > 0 && half_mark == mark {
+                durations[len - 1] += HALF_BIT;
+            } else {
+                durations[len] = HALF_BIT;
+                len += 1;
+                mark = half_mark;
+            }
+        }
+    }
+    // A trailing space doesn't need to be timed.
+    if !mark {
+        len -= 1;
+    }
+
+    Some((durations, len))
+}
+
+impl<'d> Drop for IrTim<'d> {
+    fn drop(&mut self) {
+        self.envelope.stop();
+        self.set_mark(false);
+        self.carrier.stop();
+        self._pin.set_as_disconnected();
+    }
+}
+
+pub(crate) trait SealedOutPin: crate::gpio::Pin {
+    fn af_num(&self) -> u8;
+}
+
+/// IR_OUT pin.
+#[allow(private_bounds)]
+pub trait OutPin: SealedOutPin {}
+
+macro_rules! impl_irtim_out_pin {
+    ($pin:ident, $af:expr) => {
+        impl crate::irtim::SealedOutPin for crate::peripherals::$pin {
+            fn af_num(&self) -> u8 {
+                $af
+            }
+        }
+
+        impl crate::irtim::OutPin for crate::peripherals::$pin {}
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_encode_nec() {
+        let durations = nec_durations(0x04, 0x08);
+        assert_eq!([8992, 4496], durations[..2]);
+        // Every bit starts with a one-unit mark, and the frame ends with one.
+        for bit in 0..33 {
+            assert_eq!(562, durations[2 + bit * 2]);
+        }
+        // Address, inverted address, command, inverted command, LSB first.
+        let space = |bit: usize| durations[3 + bit * 2];
+        assert_eq!(562, space(0));
+        assert_eq!(1686, space(2));
+        assert_eq!(1686, space(8));
+        assert_eq!(562, space(8 + 2));
+        assert_eq!(562, space(16));
+        assert_eq!(1686, space(16 + 3));
+        assert_eq!(1686, space(24));
+        assert_eq!(562, space(24 + 3));
+    }
+
+    #[test]
+    fn can_encode_rc5() {
+        // All ones: a mark and a space per bit, without the leading space.
+        let (durations, len) = rc5_durations(true, 0x1F, 0x3F).unwrap();
+        assert_eq!(27, len);
+        assert!(durations[..len].iter().all(|&d| d == 889));
+
+        // Start bit 1, field bit 1, then zeros: the mark of the first zero merges with the
+        // previous mark, and the trailing space is dropped.
+        let (durations, len) = rc5_durations(false, 0, 0).unwrap();
+        assert_eq!(25, len);
+        assert_eq!([889, 889, 1778, 889], durations[..4]);
+        assert!(durations[4..len].iter().all(|&d| d == 889));
+
+        // Extended RC5: command bit 6 is sent inverted in the field bit.
+        let (durations, len) = rc5_durations(false, 0, 0x40).unwrap();
+        assert_eq!(25, len);
+        assert_eq!(1778, durations[0]);
+        assert!(durations[1..len].iter().all(|&d| d == 889));
+
+        assert!(rc5_durations(false, 32, 0).is_none());
+        assert!(rc5_durations(false, 0, 128).is_none());
+    }
+}
@@ -0,0 +1,309 @@
+//! Segment LCD controller (LCD)
+//!
+//! Drives passive glass LCDs with up to 8 common and 44 segment lines. The LCD is clocked from
+//! the RTC clock, which must be configured (LSE or LSI) in the RCC configuration.
+//!
+//! The display content is held in the LCD RAM, one bit per segment and common line. New content
+//! is written with [`Lcd::write_com`], then transferred to the display with [`Lcd::update`].
+use embassy_hal_internal::PeripheralType;
+
+use crate::gpio::{AfType, AnyPin, OutputType, SealedPin as _, Speed};
+use crate::time::Hertz;
+use crate::{peripherals, rcc, Peri};
+
+/// Duty ratio, which sets the number of common lines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Duty {
+    /// Static duty, COM0 only.
+    Static = 0,
+    /// 1/2 duty, COM0 to COM1.
+    Half = 1,
+    /// 1/3 duty, COM0 to COM2.
+    Third = 2,
+    /// 1/4 duty, COM0 to COM3.
+    Quarter = 3,
+    /// 1/8 duty, COM0 to COM7.
+    Eighth = 4,
+}
+
+impl Duty {
+    /// Number of common lines.
+    pub fn num_com(&self) -> usize {
+        match self {
+            Self::Static => 1,
+            Self::Half => 2,
+            Self::Third => 3,
+            Self::Quarter => 4,
+            Self::Eighth => 8,
+        }
+    }
+}
+
+/// Bias, which sets the number of voltage levels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Bias {
+    /// 1/4 bias.
+    Quarter = 0,
+    /// 1/2 bias.
+    Half = 1,
+    /// 1/3 bias.
+    Third = 2,
+}
+
+/// LCD voltage source.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VoltageSource {
+    /// Internal step-up converter.
+    Internal,
+    /// External voltage on the VLCD pin.
+    External,
+}
+
+/// LCD configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct Config {
+    /// Duty ratio.
+    pub duty: Duty,
+    /// Bias.
+    pub bias: Bias,
+    /// Target frame rate. The closest achievable rate is used.
+    pub frame_rate: Hertz,
+    /// Contrast, from 0 (lowest voltage) to 7 (highest voltage).
+    pub contrast: u8,
+    /// Number of dead phases inserted between frames, from 0 to 7.
+    pub dead_time: u8,
+    /// Pulse-on duration, from 0 to 7, in pulses of the prescaled clock.
+    pub pulse_on_duration: u8,
+    /// Enable the low resistance divider during the whole pulse-on duration. This drives larger
+    /// glasses at the cost of a higher current consumption.
+    pub high_drive: bool,
+    /// Voltage source.
+    pub voltage_source: VoltageSource,
+    /// Remap SEG\[43:40\] to SEG\[31:28\] and the corresponding pins, on packages without them.
+    pub mux_segment: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            duty: Duty::Quarter,
+            bias: Bias::Third,
+            frame_rate: Hertz(30),
+            contrast: 4,
+            dead_time: 0,
+            pulse_on_duration: 4,
+            high_drive: false,
+            voltage_source: VoltageSource::Internal,
+            mux_segment: false,
+        }
+    }
+}
+
+/// LCD configuration error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The contrast, dead time or pulse-on duration is above 7.
+    InvalidParameter,
+    /// The RTC clock, which clocks the LCD, is not running.
+    ClockNotRunning,
+}
+
+/// LCD error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The common line isn't used with the configured duty ratio.
+    InvalidCom,
+}
+
+/// LCD segment or common line pin.
+pub struct LcdPin<'d, T: Instance> {
+    pin: Peri<'d, AnyPin>,
+    af: u8,
+    _phantom: core::marker::PhantomData<T>,
+}
+
+impl<'d, T: Instance, P: SegComPin<T>> From<Peri<'d, P>> for LcdPin<'d, T> {
+    fn from(pin: Peri<'d, P>) -> Self {
+        let af = pin.af_num();
+        Self {
+            pin: pin.into(),
+            af,
+            _phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+/// LCD driver.
+pub struct Lcd<'d, T: Instance> {
+    _peri: Peri<'d, T>,
+    duty: Duty,
+}
+
+impl<'d, T: Instance> Lcd<'d, T> {
+    /// Create a new LCD driver, using the segment and common line `pins`.
+    ///
+    /// The display starts blank.
+    pub fn new(peri: Peri<'d, T>, config: Config, pins: &[LcdPin<'d, T>]) -> Result<Self, ConfigError> {
+        if config.contrast > 7 || config.dead_time > 7 || config.pulse_on_duration > 7 {
+            return Err(ConfigError::InvalidParameter);
+        }
+        let Some(lcd_clk) = unsafe { crate::rcc::get_freqs() }.rtc.to_hertz() else {
+            return Err(ConfigError::ClockNotRunning);
+        };
+
+        rcc::enable_and_reset::<T>();
+
+        for pin in pins {
+            pin.pin
+                .set_as_af(pin.af, AfType::output(OutputType::PushPull, Speed::Low));
+        }
+
+        let (ps, div) = frame_rate_dividers(lcd_clk, config.duty, config.frame_rate);
+
+        let regs = T::regs();
+        regs.fcr().modify(|w| {
+            w.set_ps(ps);
+            w.set_div(div);
+            w.set_cc(config.contrast);
+            w.set_dead(config.dead_time);
+            w.set_pon(config.pulse_on_duration);
+            w.set_hd(config.high_drive);
+        });
+        // The FCR register is in the LCD clock domain.
+        while !regs.sr().read().fcrsf() {}
+
+        regs.cr().modify(|w| {
+            w.set_duty(config.duty as u8);
+            w.set_bias(config.bias as u8);
+            w.set_vsel(config.voltage_source == VoltageSource::External);
+            w.set_mux_seg(config.mux_segment);
+        });
+
+        regs.cr().modify(|w| w.set_lcden(true));
+        while !regs.sr().read().ens() {}
+        // Wait for the step-up converter to be ready.
+        while !regs.sr().read().rdy() {}
+
+        let mut this = Self {
+            _peri: peri,
+            duty: config.duty,
+        };
+        this.clear();
+        this.update();
+        Ok(this)
+    }
+
+    /// Set the segments of common line `com` in the LCD RAM, one bit per segment line.
+    ///
+    /// Blocks while a previous update request is still being processed, during which the LCD RAM
+    /// is write-protected. The display only shows the new content after [`Self::update`].
+    pub fn write_com(&mut self, com: usize, segments: u64) -> Result<(), Error> {
+        if com >= self.duty.num_com() {
+            return Err(Error::InvalidCom);
+        }
+        self.write_com_inner(com, segments);
+        Ok(())
+    }
+
+    fn write_com_inner(&mut self, com: usize, segments: u64) {
+        self.wait_ram_writable();
+
+        let ram = T::regs().ram_com(com);
+        ram.low().write_value(segments as u32);
+        ram.high().write_value((segments >> 32) as u32);
+    }
+
+    /// Clear the LCD RAM.
+    pub fn clear(&mut self) {
+        for com in 0..self.duty.num_com() {
+            self.write_com_inner(com, 0);
+        }
+    }
+
+    /// Request the LCD RAM content to be shown on the display, at the next frame.
+    ///
+    /// The LCD RAM can't be written until the request is processed.
+    pub fn update(&mut self) {
+        let regs = T::regs();
+        regs.clr().write(|w| w.set_uddc(true));
+        regs.sr().write(|w| w.set_udr(true));
+    }
+
+    /// Wait until the last update request has been shown on the display.
+    pub fn blocking_wait_update(&mut self) {
+        let regs = T::regs();
+        while regs.sr().read().udr() {}
+        if regs.cr().read().lcden() {
+            while !regs.sr().read().udd() {}
+            regs.clr().write(|w| w.set_uddc(true));
+        }
+    }
+
+    /// Set the contrast, from 0 (lowest voltage) to 7 (highest voltage).
+    pub fn set_contrast(&mut self, contrast: u8) -> Result<(), ConfigError> {
+        if contrast > 7 {
+            return Err(ConfigError::InvalidParameter);
+        }
+        let regs = T::regs();
+        regs.fcr().modify(|w| w.set_cc(contrast));
+        while !regs.sr().read().fcrsf() {}
+        Ok(())
+    }
+
+    fn wait_ram_writable(&self) {
+        while T::regs().sr().read().udr() {}
+    }
+}
+
+impl<'d, T: Instance> Drop for Lcd<'d, T> {
+    fn drop(&mut self) {
+        T::regs().cr().modify(|w| w.set_lcden(false));
+        rcc::disable::<T>();
+    }
+}
+
+/// Find the prescaler and divider giving the frame rate closest to `target`.
+///
+/// The frame rate is `lcd_clk / (2^ps * (16 + div)) * duty`.
+fn frame_rate_dividers(lcd_clk: Hertz, duty: Duty, target: Hertz) -> (u8, u8) {
+    let mut best = (0, 0, u32::MAX);
+    for ps in 0..=15u8 {
+        for div in 0..=15u8 {
+            let rate = lcd_clk.0 / ((1 << ps) * (16 + div as u32) * duty.num_com() as u32);
+            let error = rate.abs_diff(target.0);
+            if error < best.2 {
+                best = (ps, div, error);
+            }
+        }
+    }
+    (best.0, best.1)
+}
+
+trait SealedInstance {
+    fn regs() -> crate::pac::lcd::Lcd;
+}
+
+/// LCD instance trait.
+#[allow(private_bounds)]
+pub trait Instance: SealedInstance + PeripheralType + rcc::RccPeripheral + 'static {}
+
+pin_trait!(SegComPin, Instance);
+
+foreach_peripheral!(
+    (lcd, $inst:ident) => {
+        impl SealedInstance for peripherals::$inst {
+            fn regs() -> crate::pac::lcd::Lcd {
+                crate::pac::$inst
+            }
+        }
+
+        impl Instance for peripherals::$inst {}
+    };
+);
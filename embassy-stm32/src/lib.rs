@@ -53,23 +53,38 @@ pub mod timer;
 
 #[cfg(adc)]
 pub mod adc;
+#[cfg(all(aes, any(stm32l4, stm32wb)))]
+pub mod aes;
+#[cfg(any(stm32l4, stm32wb, stm32wl))]
+pub mod bootloader;
 #[cfg(can)]
 pub mod can;
+#[cfg(all(comp, any(stm32l4, stm32wb)))]
+pub mod comp;
 // FIXME: Cordic driver cause stm32u5a5zj crash
 #[cfg(all(cordic, not(any(stm32u5a5, stm32u5a9))))]
 pub mod cordic;
 #[cfg(crc)]
 pub mod crc;
+#[cfg(crs)]
+pub mod crs;
 #[cfg(cryp)]
 pub mod cryp;
 #[cfg(dac)]
 pub mod dac;
+#[cfg(dbgmcu)]
+pub mod dbgmcu;
 #[cfg(dcmi)]
 pub mod dcmi;
+#[cfg(all(feature = "defmt-buffer", usart))]
+pub mod defmt_buffer;
+pub mod delay;
 #[cfg(dsihost)]
 pub mod dsihost;
 #[cfg(dts)]
 pub mod dts;
+#[cfg(all(flash, not(eeprom)))]
+pub mod eeprom;
 #[cfg(eth)]
 pub mod eth;
 #[cfg(feature = "exti")]
@@ -91,16 +106,26 @@ pub mod i2c;
 pub mod i2s;
 #[cfg(stm32wb)]
 pub mod ipcc;
+#[cfg(stm32wb)]
+pub mod irtim;
+#[cfg(all(lcd, stm32l1))]
+pub mod lcd;
 #[cfg(feature = "low-power")]
 pub mod low_power;
 #[cfg(lptim)]
 pub mod lptim;
 #[cfg(ltdc)]
 pub mod ltdc;
+#[cfg(all(feature = "time", any(stm32l4, stm32wb)))]
+pub mod monitor;
+#[cfg(flash_wb)]
+pub mod mpu;
 #[cfg(opamp)]
 pub mod opamp;
 #[cfg(octospi)]
 pub mod ospi;
+#[cfg(all(pka, any(stm32wb, stm32l4)))]
+pub mod pka;
 #[cfg(quadspi)]
 pub mod qspi;
 #[cfg(rng)]
@@ -115,6 +140,12 @@ pub mod sdmmc;
 pub mod spdifrx;
 #[cfg(spi)]
 pub mod spi;
+#[cfg(stm32wl)]
+pub mod subghz;
+#[cfg(any(dbgmcu_l4, dbgmcu_wb))]
+pub mod swo;
+#[cfg(all(adc_v3, any(stm32l4, stm32wb)))]
+pub mod trigger;
 #[cfg(tsc)]
 pub mod tsc;
 #[cfg(ucpd)]
@@ -125,6 +156,8 @@ pub mod uid;
 pub mod usart;
 #[cfg(any(usb, otg))]
 pub mod usb;
+#[cfg(not(armv6m))]
+pub mod vector_table;
 #[cfg(iwdg)]
 pub mod wdg;
 #[cfg(xspi)]
@@ -231,7 +264,8 @@ pub use crate::pac::NVIC_PRIO_BITS;
 
 /// `embassy-stm32` global configuration.
 #[non_exhaustive]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Config {
     /// RCC config.
     pub rcc: rcc::Config,
@@ -319,6 +353,19 @@ pub fn init(config: Config) -> Peripherals {
     init_hw(config)
 }
 
+/// Initialize the `embassy-stm32` HAL, checking the clock configuration first.
+///
+/// Unlike [`init`], this returns an error instead of panicking if `config.rcc` is invalid. The
+/// hardware is left untouched then, so this can be called again with another configuration.
+#[cfg(all(
+    not(feature = "_dual-core"),
+    any(stm32l0, stm32l1, stm32l4, stm32l5, stm32wb, stm32wl, stm32u0)
+))]
+pub fn try_init(config: Config) -> Result<Peripherals, rcc::RccError> {
+    config.rcc.validate()?;
+    Ok(init_hw(config))
+}
+
 #[cfg(feature = "_dual-core")]
 mod dual_core {
     use core::cell::UnsafeCell;
@@ -488,39 +535,14 @@ mod dual_core {
 pub use dual_core::*;
 
 fn init_hw(config: Config) -> Peripherals {
+    #[cfg(any(stm32l4, stm32wb, stm32wl))]
+    bootloader::jump_if_requested();
+
     critical_section::with(|cs| {
         let p = Peripherals::take_with_cs(cs);
 
         #[cfg(dbgmcu)]
-        crate::pac::DBGMCU.cr().modify(|cr| {
-            #[cfg(dbgmcu_h5)]
-            {
-                cr.set_stop(config.enable_debug_during_sleep);
-                cr.set_standby(config.enable_debug_during_sleep);
-            }
-            #[cfg(any(dbgmcu_f0, dbgmcu_c0, dbgmcu_g0, dbgmcu_u0, dbgmcu_u5, dbgmcu_wba, dbgmcu_l5))]
-            {
-                cr.set_dbg_stop(config.enable_debug_during_sleep);
-                cr.set_dbg_standby(config.enable_debug_during_sleep);
-            }
-            #[cfg(any(
-                dbgmcu_f1, dbgmcu_f2, dbgmcu_f3, dbgmcu_f4, dbgmcu_f7, dbgmcu_g4, dbgmcu_f7, dbgmcu_l0, dbgmcu_l1,
-                dbgmcu_l4, dbgmcu_wb, dbgmcu_wl
-            ))]
-            {
-                cr.set_dbg_sleep(config.enable_debug_during_sleep);
-                cr.set_dbg_stop(config.enable_debug_during_sleep);
-                cr.set_dbg_standby(config.enable_debug_during_sleep);
-            }
-            #[cfg(dbgmcu_h7)]
-            {
-                cr.set_d1dbgcken(config.enable_debug_during_sleep);
-                cr.set_d3dbgcken(config.enable_debug_during_sleep);
-                cr.set_dbgsleep_d1(config.enable_debug_during_sleep);
-                cr.set_dbgstby_d1(config.enable_debug_during_sleep);
-                cr.set_dbgstop_d1(config.enable_debug_during_sleep);
-            }
-        });
+        dbgmcu::set_debug_during_sleep(config.enable_debug_during_sleep);
 
         #[cfg(not(any(stm32f1, stm32wb, stm32wl)))]
         rcc::enable_and_reset_with_cs::<peripherals::SYSCFG>(cs);
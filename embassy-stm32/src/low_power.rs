@@ -124,10 +124,10 @@ pub enum StopMode {
     Stop2,
 }
 
-#[cfg(any(stm32l4, stm32l5, stm32u5, stm32u0))]
+#[cfg(any(stm32l4, stm32l5, stm32u5, stm32u0, stm32wb, stm32wl))]
 use stm32_metapac::pwr::vals::Lpms;
 
-#[cfg(any(stm32l4, stm32l5, stm32u5, stm32u0))]
+#[cfg(any(stm32l4, stm32l5, stm32u5, stm32u0, stm32wb, stm32wl))]
 impl Into<Lpms> for StopMode {
     fn into(self) -> Lpms {
         match self {
@@ -152,6 +152,12 @@ pub struct Executor {
     not_send: PhantomData<*mut ()>,
     scb: SCB,
     time_driver: &'static RtcDriver,
+    /// RCC_CR and RCC_CFGR before the last Stop mode entry.
+    #[cfg(stm32wb)]
+    saved_clocks: Option<(crate::pac::rcc::regs::Cr, crate::pac::rcc::regs::Cfgr)>,
+    /// The Stop mode semaphore is held since the last Stop mode entry.
+    #[cfg(stm32wb)]
+    holds_stop_sem: bool,
 }
 
 impl Executor {
@@ -165,6 +171,10 @@ impl Executor {
                 not_send: PhantomData,
                 scb: cortex_m::Peripherals::steal().SCB,
                 time_driver: get_driver(),
+                #[cfg(stm32wb)]
+                saved_clocks: None,
+                #[cfg(stm32wb)]
+                holds_stop_sem: false,
             });
 
             let executor = EXECUTOR.as_mut().unwrap();
@@ -198,7 +208,7 @@ impl Executor {
 
     #[allow(unused_variables)]
     fn configure_stop(&mut self, stop_mode: StopMode) {
-        #[cfg(any(stm32l4, stm32l5, stm32u5, stm32u0))]
+        #[cfg(any(stm32l4, stm32l5, stm32u5, stm32u0, stm32wb, stm32wl))]
         crate::pac::PWR.cr1().modify(|m| m.set_lpms(stop_mode.into()));
         #[cfg(stm32h5)]
         crate::pac::PWR.pmcr().modify(|v| {
@@ -233,7 +243,81 @@ impl Executor {
         self.configure_stop(stop_mode);
 
         #[cfg(not(feature = "low-power-debug-with-sleep"))]
-        self.scb.set_sleepdeep();
+        {
+            #[cfg(stm32wb)]
+            self.enter_stop_wb();
+            self.scb.set_sleepdeep();
+        }
+    }
+
+    /// Prepare the clocks for Stop mode, following the CPU1 procedure of AN5289.
+    ///
+    /// The system only enters Stop mode once both cores are in deep sleep, and wakes up on HSI. If
+    /// CPU2 is running, CPU1 takes the Stop mode semaphore and keeps the clocks, otherwise it
+    /// switches to HSI itself so that both cores agree on the clock after wakeup.
+    #[cfg(stm32wb)]
+    fn enter_stop_wb(&mut self) {
+        use crate::hsem::shared;
+        use crate::pac::rcc::vals::Stopwuck;
+        use crate::pac::{PWR, RCC};
+        use crate::rcc::Sysclk;
+
+        shared::with_lock(shared::RCC_SEMID, || {
+            self.saved_clocks = Some((RCC.cr().read(), RCC.cfgr().read()));
+            RCC.cfgr().modify(|w| w.set_stopwuck(Stopwuck::HSI16));
+
+            self.holds_stop_sem = shared::try_lock(shared::STOP_SEMID);
+            if self.holds_stop_sem {
+                let extscr = PWR.extscr().read();
+                if !(extscr.c2ds() || extscr.c2sbf()) {
+                    // CPU2 is running and keeps the clocks up.
+                    return;
+                }
+                shared::unlock(shared::STOP_SEMID);
+                self.holds_stop_sem = false;
+            }
+
+            RCC.cr().modify(|w| w.set_hsion(true));
+            while !RCC.cr().read().hsirdy() {}
+            RCC.cfgr().modify(|w| w.set_sw(Sysclk::HSI));
+            while RCC.cfgr().read().sws() != Sysclk::HSI {}
+        });
+    }
+
+    /// Restore the clocks saved by [`Self::enter_stop_wb`], following the CPU1 procedure of AN5289.
+    #[cfg(stm32wb)]
+    fn leave_stop_wb(&mut self) {
+        use crate::hsem::shared;
+        use crate::pac::RCC;
+
+        if core::mem::take(&mut self.holds_stop_sem) {
+            shared::unlock(shared::STOP_SEMID);
+        }
+        let Some((cr, cfgr)) = self.saved_clocks.take() else {
+            return;
+        };
+
+        shared::with_lock(shared::RCC_SEMID, || {
+            if RCC.cfgr().read().sws() == cfgr.sws() {
+                return;
+            }
+            // Stop mode turned HSE and the PLLs off, their configuration is retained.
+            if cr.hseon() {
+                RCC.cr().modify(|w| w.set_hseon(true));
+                while !RCC.cr().read().hserdy() {}
+            }
+            if cr.pllon() {
+                RCC.cr().modify(|w| w.set_pllon(true));
+                while !RCC.cr().read().pllrdy() {}
+            }
+            #[cfg(not(stm32wb1x))]
+            if cr.pllsai1on() {
+                RCC.cr().modify(|w| w.set_pllsai1on(true));
+                while !RCC.cr().read().pllsai1rdy() {}
+            }
+            RCC.cfgr().modify(|w| w.set_sw(cfgr.sws()));
+            while RCC.cfgr().read().sws() != cfgr.sws() {}
+        });
     }
 
     /// Run the executor.
@@ -263,6 +347,8 @@ impl Executor {
                 executor.inner.poll();
                 self.configure_pwr();
                 asm!("wfe");
+                #[cfg(stm32wb)]
+                self.leave_stop_wb();
             };
         }
     }
@@ -96,6 +96,17 @@ macro_rules! dma_trait_impl {
     };
 }
 
+#[allow(unused)]
+macro_rules! dma_trait_impl_any {
+    (crate::$mod:ident::$trait:ident$(<$mode:ident>)?, $instance:ident, $request:expr) => {
+        impl crate::$mod::$trait<crate::peripherals::$instance $(, crate::$mod::$mode)?> for crate::dma::AnyChannel {
+            fn request(&self) -> crate::dma::Request {
+                $request
+            }
+        }
+    };
+}
+
 #[allow(unused)]
 macro_rules! new_dma_nonopt {
     ($name:ident) => {{
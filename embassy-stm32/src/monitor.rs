@@ -0,0 +1,166 @@
+//! System health monitor
+//!
+//! Periodically samples the supply voltage (through VREFINT), the backup battery voltage and the
+//! die temperature with the ADC, and publishes the readings and threshold alerts to a channel.
+//!
+//! ```rust,ignore
+//! static EVENTS: Channel<CriticalSectionRawMutex, Event, 4> = Channel::new();
+//!
+//! #[embassy_executor::task]
+//! async fn monitor(mut adc: Adc<'static, ADC1>) {
+//!     let mut config = Config::default();
+//!     config.thresholds.vdd_min_mv = Some(2_000);
+//!     run(&mut adc, config, EVENTS.sender()).await
+//! }
+//! ```
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::Sender;
+use embassy_time::{Duration, Timer};
+
+use crate::adc::{self, Adc};
+
+/// Alert thresholds. `None` disables the corresponding alert.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Thresholds {
+    /// Minimum supply voltage, in mV.
+    pub vdd_min_mv: Option<u32>,
+    /// Minimum backup battery voltage, in mV.
+    pub vbat_min_mv: Option<u32>,
+    /// Minimum die temperature, in °C.
+    pub temperature_min: Option<f32>,
+    /// Maximum die temperature, in °C.
+    pub temperature_max: Option<f32>,
+}
+
+/// Health monitor configuration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct Config {
+    /// Sampling period.
+    pub period: Duration,
+    /// Sample the backup battery voltage. The VBAT channel is only enabled during the measurement.
+    pub sample_vbat: bool,
+    /// Alert thresholds.
+    pub thresholds: Thresholds,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            period: Duration::from_secs(10),
+            sample_vbat: false,
+            thresholds: Thresholds::default(),
+        }
+    }
+}
+
+/// Readings of one sampling period.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Readings {
+    /// Supply voltage, in mV.
+    pub vdd_mv: u32,
+    /// Backup battery voltage, in mV, if sampled.
+    pub vbat_mv: Option<u32>,
+    /// Die temperature, in °C.
+    pub temperature: f32,
+}
+
+/// Threshold alert.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Alert {
+    /// The supply voltage is below [`Thresholds::vdd_min_mv`].
+    VddLow,
+    /// The backup battery voltage is below [`Thresholds::vbat_min_mv`].
+    VbatLow,
+    /// The die temperature is below [`Thresholds::temperature_min`].
+    TemperatureLow,
+    /// The die temperature is above [`Thresholds::temperature_max`].
+    TemperatureHigh,
+}
+
+impl Alert {
+    const ALL: [Alert; 4] = [
+        Alert::VddLow,
+        Alert::VbatLow,
+        Alert::TemperatureLow,
+        Alert::TemperatureHigh,
+    ];
+
+    fn is_active(&self, readings: &Readings, thresholds: &Thresholds) -> bool {
+        match self {
+            Self::VddLow => thresholds.vdd_min_mv.is_some_and(|min| readings.vdd_mv < min),
+            Self::VbatLow => matches!(
+                (readings.vbat_mv, thresholds.vbat_min_mv),
+                (Some(vbat), Some(min)) if vbat < min
+            ),
+            Self::TemperatureLow => thresholds.temperature_min.is_some_and(|min| readings.temperature < min),
+            Self::TemperatureHigh => thresholds.temperature_max.is_some_and(|max| readings.temperature > max),
+        }
+    }
+}
+
+/// Health monitor event.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// New readings, published every sampling period.
+    Readings(Readings),
+    /// A threshold was crossed. Published once when the alert becomes active.
+    Alert(Alert, Readings),
+    /// A previously published alert is not active anymore.
+    AlertCleared(Alert, Readings),
+}
+
+/// Sample the health readings once.
+///
+/// The ADC must use the 12-bit resolution. Its sampling time is set to a value suitable for the
+/// internal channels.
+pub fn sample<T: adc::Instance>(adc: &mut Adc<'_, T>, sample_vbat: bool) -> Readings {
+    let (vdd_mv, temperature) = adc.blocking_read_vdda_and_temperature();
+
+    let vbat_mv = sample_vbat.then(|| {
+        let mut vbat = adc.enable_vbat();
+        let mv = vbat.to_mv(adc.blocking_read(&mut vbat), vdd_mv);
+        adc.disable_vbat();
+        mv
+    });
+
+    Readings {
+        vdd_mv,
+        vbat_mv,
+        temperature,
+    }
+}
+
+/// Sample the health readings every [`Config::period`] and publish them to `events`.
+pub async fn run<T: adc::Instance, M: RawMutex, const N: usize>(
+    adc: &mut Adc<'_, T>,
+    config: Config,
+    events: Sender<'_, M, Event, N>,
+) -> ! {
+    let mut active = [false; Alert::ALL.len()];
+
+    loop {
+        let readings = sample(adc, config.sample_vbat);
+        events.send(Event::Readings(readings)).await;
+
+        for (alert, active) in Alert::ALL.iter().zip(active.iter_mut()) {
+            let now = alert.is_active(&readings, &config.thresholds);
+            if now != *active {
+                *active = now;
+                if now {
+                    warn!("monitor: {:?}", alert);
+                    events.send(Event::Alert(*alert, readings)).await;
+                } else {
+                    events.send(Event::AlertCleared(*alert, readings)).await;
+                }
+            }
+        }
+
+        Timer::after(config.period).await;
+    }
+}
@@ -0,0 +1,138 @@
+//! Memory protection of the CPU2 memory on STM32WB
+//!
+//! CPU1 can't access the memory secured for CPU2, but a stray access only shows up as a bus
+//! fault deep in unrelated code. [`protect_cpu2_memory`] programs the MPU so that such accesses
+//! fault immediately as memory management faults, and maps SRAM2a, which holds the IPCC mailbox
+//! buffers shared with CPU2, as strongly-ordered memory.
+//!
+//! ```rust,ignore
+//! let mut cp = cortex_m::Peripherals::take().unwrap();
+//! unsafe { embassy_stm32::mpu::protect_cpu2_memory(&mut cp.MPU, &flash::cpu2_memory_map(), 0) };
+//! ```
+use cortex_m::peripheral::MPU;
+
+use crate::flash::{Cpu2MemoryMap, FLASH_BASE, FLASH_SIZE, SRAM2A_BASE, SRAM2A_SIZE, SRAM2B_BASE, SRAM2B_SIZE};
+
+/// Number of MPU regions used by [`protect_cpu2_memory`].
+pub const CPU2_REGIONS: u8 = 4;
+
+const RASR_ENABLE: u32 = 1 << 0;
+const RASR_XN: u32 = 1 << 28;
+const RASR_AP_NO_ACCESS: u32 = 0b000 << 24;
+const RASR_AP_FULL_ACCESS: u32 = 0b011 << 24;
+/// TEX = 0, C = 0, B = 0.
+const RASR_STRONGLY_ORDERED: u32 = 0;
+
+const CTRL_ENABLE: u32 = 1 << 0;
+const CTRL_PRIVDEFENA: u32 = 1 << 2;
+
+/// Program the MPU to protect the memory secured for CPU2, then enable the MPU.
+///
+/// Uses the regions `first_region..first_region + CPU2_REGIONS`. Unused regions are disabled.
+/// Memory outside these regions keeps the default memory map.
+///
+/// The MPU protects whole subregions of 1/8 of a power of two size. The start of each secure area
+/// is rounded up to the next subregion, so the protected part may be slightly smaller than the
+/// secure area, but application memory is never protected.
+///
+/// # Safety
+///
+/// The other enabled MPU regions must not conflict with these ones.
+pub unsafe fn protect_cpu2_memory(mpu: &mut MPU, map: &Cpu2MemoryMap, first_region: u8) {
+    let flash_end = FLASH_BASE as u32 + FLASH_SIZE as u32;
+    let sram2a_end = (SRAM2A_BASE + SRAM2A_SIZE) as u32;
+    let sram2b_end = (SRAM2B_BASE + SRAM2B_SIZE) as u32;
+    let regions = [
+        // Lower regions first: higher region numbers take precedence where they overlap.
+        Some((
+            SRAM2A_BASE as u32,
+            sram2a_end,
+            RASR_AP_FULL_ACCESS | RASR_STRONGLY_ORDERED | RASR_XN,
+        )),
+        map.secure_sram2a_start
+            .map(|start| (start, sram2a_end, RASR_AP_NO_ACCESS | RASR_XN)),
+        map.secure_sram2b_start
+            .map(|start| (start, sram2b_end, RASR_AP_NO_ACCESS | RASR_XN)),
+        map.secure_flash_start
+            .map(|start| (start, flash_end, RASR_AP_NO_ACCESS | RASR_XN)),
+    ];
+
+    cortex_m::asm::dmb();
+    mpu.ctrl.write(0);
+
+    for (i, region) in regions.iter().enumerate() {
+        mpu.rnr.write((first_region as usize + i) as u32);
+        match region.and_then(|(start, end, attributes)| region_registers(start, end, attributes)) {
+            Some((rbar, rasr)) => {
+                mpu.rbar.write(rbar);
+                mpu.rasr.write(rasr);
+            }
+            None => mpu.rasr.write(0),
+        }
+    }
+
+    mpu.ctrl.write(CTRL_ENABLE | CTRL_PRIVDEFENA);
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+}
+
+/// RBAR and RASR values for a region covering `start..end`, where `end` is aligned to a power of
+/// two at least as large as the area.
+fn region_registers(start: u32, end: u32, attributes: u32) -> Option<(u32, u32)> {
+    if start >= end {
+        return None;
+    }
+
+    // Regions of less than 256 bytes have no subregions.
+    let size = (end - start).next_power_of_two().max(256);
+    let base = end - size;
+    let subregion = size / 8;
+    // Subregions below the (rounded up) start are disabled.
+    let disabled = (start - base).div_ceil(subregion);
+    if disabled >= 8 {
+        return None;
+    }
+    let srd = (1u32 << disabled) - 1;
+
+    let rasr = attributes | (srd << 8) | ((size.trailing_zeros() - 1) << 1) | RASR_ENABLE;
+    Some((base, rasr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_compute_region_registers() {
+        // Aligned 4 KiB region: no disabled subregion, SIZE = 11.
+        assert_eq!(
+            Some((0x2000_0000, (11 << 1) | RASR_ENABLE)),
+            region_registers(0x2000_0000, 0x2000_1000, 0)
+        );
+        // The first 512 B subregion is partly below the start, so it is disabled too.
+        assert_eq!(
+            Some((0x2000_0000, RASR_XN | (0b1 << 8) | (11 << 1) | RASR_ENABLE)),
+            region_registers(0x2000_0100, 0x2000_1000, RASR_XN)
+        );
+        assert_eq!(
+            Some((0x0800_0000, (0b111 << 8) | (17 << 1) | RASR_ENABLE)),
+            region_registers(0x0801_8000, 0x0804_0000, 0)
+        );
+    }
+
+    #[test]
+    fn can_compute_small_region_registers() {
+        assert_eq!(
+            Some((0x2000_0000, (7 << 1) | RASR_ENABLE)),
+            region_registers(0x2000_0000, 0x2000_0100, 0)
+        );
+    }
+
+    #[test]
+    fn rejects_empty_and_unrepresentable_regions() {
+        assert_eq!(None, region_registers(0x2000_1000, 0x2000_1000, 0));
+        assert_eq!(None, region_registers(0x2000_2000, 0x2000_1000, 0));
+        // Less than a subregion of a 256 B region is left.
+        assert_eq!(None, region_registers(0x2000_00F1, 0x2000_0100, 0));
+    }
+}
@@ -76,6 +76,17 @@ impl<'d, T: Instance> OpAmp<'d, T> {
         Self { _inner: opamp }
     }
 
+    /// Enable or disable low-power mode.
+    ///
+    /// Low-power mode reduces consumption at the cost of bandwidth and slew rate.
+    /// It only takes effect the next time the opamp is configured.
+    #[cfg(stm32l4)]
+    pub fn set_low_power(&mut self, low_power: bool) {
+        T::regs().csr().modify(|w| {
+            w.set_opalpm(low_power);
+        });
+    }
+
     /// Configure the OpAmp as a buffer for the provided input pin,
     /// outputting to the provided output pin, and enable the opamp.
     ///
@@ -422,13 +433,11 @@ impl<'d, T: Instance> OpAmp<'d, T> {
 
             T::regs().csr().modify(|w| match pair {
                 OpAmpDifferentialPair::P => {
-                    #[cfg(feature = "defmt")]
-                    defmt::debug!("opamp p calibration. offset: {}", mid);
+                    debug!("opamp p calibration. offset: {}", mid);
                     w.set_trimoffsetp(mid);
                 }
                 OpAmpDifferentialPair::N => {
-                    #[cfg(feature = "defmt")]
-                    defmt::debug!("opamp n calibration. offset: {}", mid);
+                    debug!("opamp n calibration. offset: {}", mid);
                     w.set_trimoffsetn(mid);
                 }
             });
@@ -0,0 +1,504 @@
+//! Public key accelerator (PKA)
+//!
+//! Elliptic curve operations on the NIST P-256 curve: ECDSA signature and verification, and
+//! scalar multiplication for ECDH key agreement.
+//!
+//! All scalars and coordinates are 32-byte big-endian numbers.
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::{Peri, PeripheralType};
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::interrupt::typelevel::Interrupt;
+use crate::{interrupt, pac, peripherals, rcc};
+
+static PKA_WAKER: AtomicWaker = AtomicWaker::new();
+
+/// Size of P-256 operands, in bytes.
+const SIZE: usize = 32;
+/// Size of P-256 operands, in bits.
+const NB_BITS: u32 = 256;
+
+/// Offset of the PKA RAM from the peripheral base address.
+const RAM_OFFSET: usize = 0x400;
+
+mod p256 {
+    use super::SIZE;
+
+    /// Prime modulus.
+    pub const P: [u8; SIZE] = [
+        0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+    /// Absolute value of the `a` coefficient, which is negative (-3).
+    pub const A_ABS: [u8; SIZE] = {
+        let mut a = [0; SIZE];
+        a[SIZE - 1] = 3;
+        a
+    };
+    /// `b` coefficient.
+    pub const B: [u8; SIZE] = [
+        0x5A, 0xC6, 0x35, 0xD8, 0xAA, 0x3A, 0x93, 0xE7, 0xB3, 0xEB, 0xBD, 0x55, 0x76, 0x98, 0x86, 0xBC, 0x65, 0x1D,
+        0x06, 0xB0, 0xCC, 0x53, 0xB0, 0xF6, 0x3B, 0xCE, 0x3C, 0x3E, 0x27, 0xD2, 0x60, 0x4B,
+    ];
+    /// Order of the base point.
+    pub const N: [u8; SIZE] = [
+        0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xBC, 0xE6,
+        0xFA, 0xAD, 0xA7, 0x17, 0x9E, 0x84, 0xF3, 0xB9, 0xCA, 0xC2, 0xFC, 0x63, 0x25, 0x51,
+    ];
+    /// Base point x coordinate.
+    pub const GX: [u8; SIZE] = [
+        0x6B, 0x17, 0xD1, 0xF2, 0xE1, 0x2C, 0x42, 0x47, 0xF8, 0xBC, 0xE6, 0xE5, 0x63, 0xA4, 0x40, 0xF2, 0x77, 0x03,
+        0x7D, 0x81, 0x2D, 0xEB, 0x33, 0xA0, 0xF4, 0xA1, 0x39, 0x45, 0xD8, 0x98, 0xC2, 0x96,
+    ];
+    /// Base point y coordinate.
+    pub const GY: [u8; SIZE] = [
+        0x4F, 0xE3, 0x42, 0xE2, 0xFE, 0x1A, 0x7F, 0x9B, 0x8E, 0xE7, 0xEB, 0x4A, 0x7C, 0x0F, 0x9E, 0x16, 0x2B, 0xCE,
+        0x33, 0x57, 0x6B, 0x31, 0x5E, 0xCE, 0xCB, 0xB6, 0x40, 0x68, 0x37, 0xBF, 0x51, 0xF5,
+    ];
+}
+
+/// Operation modes.
+mod mode {
+    pub const MONTGOMERY_PARAM: u8 = 0x01;
+    pub const ECC_MUL: u8 = 0x20;
+    pub const ECDSA_SIGN: u8 = 0x24;
+    pub const ECDSA_VERIFY: u8 = 0x26;
+    pub const POINT_CHECK: u8 = 0x28;
+}
+
+/// PKA RAM layout for each operation, as byte offsets from the peripheral base address.
+mod ram {
+    pub mod montgomery_param {
+        pub const IN_MOD_NB_BITS: usize = 0x404;
+        pub const IN_MODULUS: usize = 0xD5C;
+        pub const OUT_PARAMETER: usize = 0x594;
+    }
+
+    pub mod ecc_mul {
+        pub const IN_EXP_NB_BITS: usize = 0x400;
+        pub const IN_OP_NB_BITS: usize = 0x404;
+        pub const IN_A_COEFF_SIGN: usize = 0x408;
+        pub const IN_A_COEFF: usize = 0x40C;
+        pub const IN_MOD_GF: usize = 0x460;
+        pub const IN_K: usize = 0x508;
+        pub const IN_POINT_X: usize = 0x55C;
+        pub const IN_POINT_Y: usize = 0x5B0;
+        pub const OUT_RESULT_X: usize = 0x55C;
+        pub const OUT_RESULT_Y: usize = 0x5B0;
+    }
+
+    pub mod point_check {
+        pub const IN_MOD_NB_BITS: usize = 0x404;
+        pub const IN_A_COEFF_SIGN: usize = 0x408;
+        pub const IN_A_COEFF: usize = 0x40C;
+        pub const IN_B_COEFF: usize = 0x7FC;
+        pub const IN_MOD_GF: usize = 0x460;
+        pub const IN_MONTGOMERY_PARAM: usize = 0x4B4;
+        pub const IN_POINT_X: usize = 0x55C;
+        pub const IN_POINT_Y: usize = 0x5B0;
+        pub const OUT_ERROR: usize = 0x400;
+    }
+
+    pub mod ecdsa_sign {
+        pub const IN_ORDER_NB_BITS: usize = 0x400;
+        pub const IN_MOD_NB_BITS: usize = 0x404;
+        pub const IN_A_COEFF_SIGN: usize = 0x408;
+        pub const IN_A_COEFF: usize = 0x40C;
+        pub const IN_MOD_GF: usize = 0x460;
+        pub const IN_K: usize = 0x508;
+        pub const IN_POINT_X: usize = 0x55C;
+        pub const IN_POINT_Y: usize = 0x5B0;
+        pub const IN_HASH_E: usize = 0xDE8;
+        pub const IN_PRIVATE_KEY_D: usize = 0xE3C;
+        pub const IN_ORDER_N: usize = 0xE94;
+        pub const OUT_ERROR: usize = 0xEE8;
+        pub const OUT_SIGNATURE_R: usize = 0x700;
+        pub const OUT_SIGNATURE_S: usize = 0x754;
+    }
+
+    pub mod ecdsa_verify {
+        pub const IN_ORDER_NB_BITS: usize = 0x404;
+        pub const IN_MOD_NB_BITS: usize = 0x4B4;
+        pub const IN_A_COEFF_SIGN: usize = 0x45C;
+        pub const IN_A_COEFF: usize = 0x460;
+        pub const IN_MOD_GF: usize = 0x4B8;
+        pub const IN_POINT_X: usize = 0x5E8;
+        pub const IN_POINT_Y: usize = 0x63C;
+        pub const IN_PUBLIC_KEY_X: usize = 0xF40;
+        pub const IN_PUBLIC_KEY_Y: usize = 0xF94;
+        pub const IN_SIGNATURE_R: usize = 0x1098;
+        pub const IN_SIGNATURE_S: usize = 0xA44;
+        pub const IN_HASH_E: usize = 0xFE8;
+        pub const IN_ORDER_N: usize = 0xD5C;
+        pub const OUT_RESULT: usize = 0x5B0;
+    }
+}
+
+/// PKA interrupt handler.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let sr = T::regs().sr().read();
+        if sr.procendf() || sr.ramerrf() || sr.addrerrf() {
+            T::regs().cr().modify(|w| {
+                w.set_procendie(false);
+                w.set_ramerrie(false);
+                w.set_addrerrie(false);
+            });
+            PKA_WAKER.wake();
+        }
+    }
+}
+
+/// PKA error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The PKA RAM was accessed while an operation was running.
+    Ram,
+    /// An access to an invalid PKA RAM address was made.
+    Address,
+    /// The operation failed, for example because of an invalid nonce or private key.
+    Operation,
+    /// The point is not on the curve.
+    InvalidPoint,
+}
+
+/// Point on the P-256 curve, in affine coordinates.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Point {
+    /// X coordinate.
+    pub x: [u8; SIZE],
+    /// Y coordinate.
+    pub y: [u8; SIZE],
+}
+
+/// ECDSA signature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Signature {
+    /// R part.
+    pub r: [u8; SIZE],
+    /// S part.
+    pub s: [u8; SIZE],
+}
+
+/// PKA driver.
+pub struct Pka<'d, T: Instance> {
+    _peripheral: Peri<'d, T>,
+}
+
+impl<'d, T: Instance> Pka<'d, T> {
+    /// Create a new PKA driver.
+    pub fn new(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+    ) -> Self {
+        rcc::enable_and_reset::<T>();
+        Self::enable();
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        Self { _peripheral: peri }
+    }
+
+    /// Enable the PKA, with all interrupts disabled.
+    fn enable() {
+        // The PKA RAM is erased after reset or disable, enabling fails until it's done.
+        while {
+            T::regs().cr().write(|w| w.set_en(true));
+            !T::regs().cr().read().en()
+        } {}
+    }
+
+    /// Compute the public key matching `private_key`.
+    pub async fn public_key(&mut self, private_key: &[u8; SIZE]) -> Result<Point, Error> {
+        let g = Point {
+            x: p256::GX,
+            y: p256::GY,
+        };
+        self.ecc_mul(private_key, &g).await
+    }
+
+    /// Compute the ECDH shared secret, which is the x coordinate of `private_key * public_key`.
+    ///
+    /// `public_key` is checked to be on the curve first.
+    pub async fn ecdh(&mut self, private_key: &[u8; SIZE], public_key: &Point) -> Result<[u8; SIZE], Error> {
+        self.check_point(public_key).await?;
+        Ok(self.ecc_mul(private_key, public_key).await?.x)
+    }
+
+    /// Multiply `point` by the scalar `k`.
+    pub async fn ecc_mul(&mut self, k: &[u8; SIZE], point: &Point) -> Result<Point, Error> {
+        use ram::ecc_mul::*;
+
+        let _lock = Self::lock().await;
+
+        write_word::<T>(IN_EXP_NB_BITS, NB_BITS);
+        write_word::<T>(IN_OP_NB_BITS, NB_BITS);
+        write_word::<T>(IN_A_COEFF_SIGN, 1);
+        write_operand::<T>(IN_A_COEFF, &p256::A_ABS);
+        write_operand::<T>(IN_MOD_GF, &p256::P);
+        write_operand::<T>(IN_K, k);
+        write_operand::<T>(IN_POINT_X, &point.x);
+        write_operand::<T>(IN_POINT_Y, &point.y);
+
+        self.run(mode::ECC_MUL).await?;
+
+        Ok(Point {
+            x: read_operand::<T>(OUT_RESULT_X),
+            y: read_operand::<T>(OUT_RESULT_Y),
+        })
+    }
+
+    /// Check that `point` is on the curve.
+    pub async fn check_point(&mut self, point: &Point) -> Result<(), Error> {
+        use ram::point_check::*;
+
+        let _lock = Self::lock().await;
+
+        // The point check works in the Montgomery domain and needs R² mod p as an input.
+        let r2 = self.montgomery_param(&p256::P).await?;
+
+        write_word::<T>(IN_MOD_NB_BITS, NB_BITS);
+        write_word::<T>(IN_A_COEFF_SIGN, 1);
+        write_operand::<T>(IN_A_COEFF, &p256::A_ABS);
+        write_operand::<T>(IN_B_COEFF, &p256::B);
+        write_operand::<T>(IN_MOD_GF, &p256::P);
+        write_operand::<T>(IN_MONTGOMERY_PARAM, &r2);
+        write_operand::<T>(IN_POINT_X, &point.x);
+        write_operand::<T>(IN_POINT_Y, &point.y);
+
+        self.run(mode::POINT_CHECK).await?;
+
+        match read_word::<T>(OUT_ERROR) {
+            0 => Ok(()),
+            _ => Err(Error::InvalidPoint),
+        }
+    }
+
+    /// Compute the Montgomery parameter R² mod `modulus`.
+    async fn montgomery_param(&mut self, modulus: &[u8; SIZE]) -> Result<[u8; SIZE], Error> {
+        use ram::montgomery_param::*;
+
+        write_word::<T>(IN_MOD_NB_BITS, NB_BITS);
+        write_operand::<T>(IN_MODULUS, modulus);
+
+        self.run(mode::MONTGOMERY_PARAM).await?;
+
+        Ok(read_operand::<T>(OUT_PARAMETER))
+    }
+
+    /// Sign `hash` with `private_key`.
+    ///
+    /// `k` must be a fresh secret random number for each signature, otherwise the private key can
+    /// be recovered from the signatures. Use the `rng` driver to generate it.
+    pub async fn ecdsa_sign(
+        &mut self,
+        private_key: &[u8; SIZE],
+        hash: &[u8; SIZE],
+        k: &[u8; SIZE],
+    ) -> Result<Signature, Error> {
+        use ram::ecdsa_sign::*;
+
+        let _lock = Self::lock().await;
+
+        write_word::<T>(IN_ORDER_NB_BITS, NB_BITS);
+        write_word::<T>(IN_MOD_NB_BITS, NB_BITS);
+        write_word::<T>(IN_A_COEFF_SIGN, 1);
+        write_operand::<T>(IN_A_COEFF, &p256::A_ABS);
+        write_operand::<T>(IN_MOD_GF, &p256::P);
+        write_operand::<T>(IN_K, k);
+        write_operand::<T>(IN_POINT_X, &p256::GX);
+        write_operand::<T>(IN_POINT_Y, &p256::GY);
+        write_operand::<T>(IN_HASH_E, hash);
+        write_operand::<T>(IN_PRIVATE_KEY_D, private_key);
+        write_operand::<T>(IN_ORDER_N, &p256::N);
+
+        self.run(mode::ECDSA_SIGN).await?;
+
+        if read_word::<T>(OUT_ERROR) != 0 {
+            return Err(Error::Operation);
+        }
+
+        Ok(Signature {
+            r: read_operand::<T>(OUT_SIGNATURE_R),
+            s: read_operand::<T>(OUT_SIGNATURE_S),
+        })
+    }
+
+    /// Verify that `signature` is a valid signature of `hash` by `public_key`.
+    pub async fn ecdsa_verify(
+        &mut self,
+        public_key: &Point,
+        hash: &[u8; SIZE],
+        signature: &Signature,
+    ) -> Result<bool, Error> {
+        use ram::ecdsa_verify::*;
+
+        let _lock = Self::lock().await;
+
+        write_word::<T>(IN_ORDER_NB_BITS, NB_BITS);
+        write_word::<T>(IN_MOD_NB_BITS, NB_BITS);
+        write_word::<T>(IN_A_COEFF_SIGN, 1);
+        write_operand::<T>(IN_A_COEFF, &p256::A_ABS);
+        write_operand::<T>(IN_MOD_GF, &p256::P);
+        write_operand::<T>(IN_POINT_X, &p256::GX);
+        write_operand::<T>(IN_POINT_Y, &p256::GY);
+        write_operand::<T>(IN_PUBLIC_KEY_X, &public_key.x);
+        write_operand::<T>(IN_PUBLIC_KEY_Y, &public_key.y);
+        write_operand::<T>(IN_SIGNATURE_R, &signature.r);
+        write_operand::<T>(IN_SIGNATURE_S, &signature.s);
+        write_operand::<T>(IN_HASH_E, hash);
+        write_operand::<T>(IN_ORDER_N, &p256::N);
+
+        self.run(mode::ECDSA_VERIFY).await?;
+
+        Ok(read_word::<T>(OUT_RESULT) == 0)
+    }
+
+    /// Take the PKA for one operation, until the returned guard is dropped.
+    ///
+    /// On WB, the wireless stack on CPU2 uses the PKA too, so this takes the PKA semaphore, waiting
+    /// while CPU2 holds it.
+    async fn lock() -> OnDrop<impl FnOnce()> {
+        #[cfg(stm32wb)]
+        poll_fn(|cx| {
+            if crate::hsem::shared::try_lock(crate::hsem::shared::PKA_SEMID) {
+                Poll::Ready(())
+            } else {
+                // The semaphore interrupt belongs to the HSEM driver, poll until it's free.
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await;
+
+        OnDrop::new(|| {
+            #[cfg(stm32wb)]
+            crate::hsem::shared::unlock(crate::hsem::shared::PKA_SEMID);
+        })
+    }
+
+    /// Start an operation and wait for it to complete.
+    async fn run(&mut self, mode: u8) -> Result<(), Error> {
+        let regs = T::regs();
+
+        regs.clrfr().write(|w| {
+            w.set_procendfc(true);
+            w.set_ramerrfc(true);
+            w.set_addrerrfc(true);
+        });
+        regs.cr().modify(|w| {
+            w.set_mode(mode);
+            w.set_procendie(true);
+            w.set_ramerrie(true);
+            w.set_addrerrie(true);
+            w.set_start(true);
+        });
+
+        // Abort the operation if the future is dropped, clearing EN stops the PKA.
+        let on_drop = OnDrop::new(|| {
+            regs.cr().modify(|w| {
+                w.set_procendie(false);
+                w.set_ramerrie(false);
+                w.set_addrerrie(false);
+                w.set_en(false);
+            });
+            Self::enable();
+        });
+
+        let sr = poll_fn(|cx| {
+            PKA_WAKER.register(cx.waker());
+
+            let sr = regs.sr().read();
+            if sr.procendf() || sr.ramerrf() || sr.addrerrf() {
+                Poll::Ready(sr)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+        on_drop.defuse();
+
+        regs.clrfr().write(|w| {
+            w.set_procendfc(true);
+            w.set_ramerrfc(true);
+            w.set_addrerrfc(true);
+        });
+
+        if sr.ramerrf() {
+            Err(Error::Ram)
+        } else if sr.addrerrf() {
+            Err(Error::Address)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'d, T: Instance> Drop for Pka<'d, T> {
+    fn drop(&mut self) {
+        T::regs().cr().modify(|w| w.set_en(false));
+        rcc::disable::<T>();
+    }
+}
+
+fn write_word<T: Instance>(offset: usize, value: u32) {
+    T::regs().ram((offset - RAM_OFFSET) / 4).write_value(value);
+}
+
+fn read_word<T: Instance>(offset: usize) -> u32 {
+    T::regs().ram((offset - RAM_OFFSET) / 4).read()
+}
+
+/// Write a big-endian number. The PKA expects the least significant word first, followed by an
+/// additional zero word.
+fn write_operand<T: Instance>(offset: usize, value: &[u8; SIZE]) {
+    for (i, word) in value.rchunks_exact(4).enumerate() {
+        write_word::<T>(offset + i * 4, u32::from_be_bytes(unwrap!(word.try_into())));
+    }
+    write_word::<T>(offset + SIZE, 0);
+}
+
+fn read_operand<T: Instance>(offset: usize) -> [u8; SIZE] {
+    let mut value = [0; SIZE];
+    for (i, word) in value.rchunks_exact_mut(4).enumerate() {
+        word.copy_from_slice(&read_word::<T>(offset + i * 4).to_be_bytes());
+    }
+    value
+}
+
+trait SealedInstance {
+    fn regs() -> pac::pka::Pka;
+}
+
+/// PKA instance trait.
+#[allow(private_bounds)]
+pub trait Instance: SealedInstance + PeripheralType + crate::rcc::RccPeripheral + 'static + Send {
+    /// Interrupt for this PKA instance.
+    type Interrupt: interrupt::typelevel::Interrupt;
+}
+
+foreach_interrupt!(
+    ($inst:ident, pka, PKA, GLOBAL, $irq:ident) => {
+        impl Instance for peripherals::$inst {
+            type Interrupt = crate::interrupt::typelevel::$irq;
+        }
+
+        impl SealedInstance for peripherals::$inst {
+            fn regs() -> crate::pac::pka::Pka {
+                crate::pac::$inst
+            }
+        }
+    };
+);
@@ -0,0 +1,264 @@
+//! SPI NOR flash on QSPI
+//!
+//! Drives a standard SPI NOR flash (Winbond W25Q, Macronix MX25, ...) through indirect mode
+//! commands, and implements the `embedded-storage` traits on top of it.
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+
+use super::enums::{DummyCycles, QspiWidth};
+use super::{Instance, Qspi, TransferConfig};
+use crate::mode::Mode as PeriMode;
+
+const WRITE_ENABLE: u8 = 0x06;
+const READ_STATUS: u8 = 0x05;
+const SECTOR_ERASE: u8 = 0x20;
+const BLOCK_ERASE: u8 = 0xD8;
+const CHIP_ERASE: u8 = 0xC7;
+const PAGE_PROGRAM: u8 = 0x02;
+const QUAD_PAGE_PROGRAM: u8 = 0x32;
+const FAST_READ: u8 = 0x0B;
+const QUAD_OUTPUT_FAST_READ: u8 = 0x6B;
+
+/// Write in progress bit of the status register.
+const STATUS_WIP: u8 = 0x01;
+
+/// Program page size, in bytes.
+pub const PAGE_SIZE: u32 = 256;
+/// Erase sector size, in bytes.
+pub const SECTOR_SIZE: u32 = 4096;
+/// Erase block size, in bytes.
+pub const BLOCK_SIZE: u32 = 65536;
+
+/// SPI NOR flash error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The operation is out of the flash bounds.
+    OutOfBounds,
+    /// The operation is not aligned to the erase size.
+    NotAligned,
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Self::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Self::NotAligned => NorFlashErrorKind::NotAligned,
+        }
+    }
+}
+
+/// SPI NOR flash driver.
+pub struct QspiFlash<'d, T: Instance, M: PeriMode> {
+    qspi: Qspi<'d, T, M>,
+    capacity: u32,
+    quad: bool,
+    memory_mapped: bool,
+}
+
+impl<'d, T: Instance, M: PeriMode> QspiFlash<'d, T, M> {
+    /// Create a new SPI NOR flash driver of `capacity` bytes.
+    ///
+    /// With `quad`, data is read and programmed over four lines. The quad mode of the flash (for
+    /// example the QE bit of the status register) must then already be enabled.
+    ///
+    /// The address size of the QSPI configuration must match the address mode of the flash.
+    pub fn new(qspi: Qspi<'d, T, M>, capacity: u32, quad: bool) -> Self {
+        Self {
+            qspi,
+            capacity,
+            quad,
+            memory_mapped: false,
+        }
+    }
+
+    /// Flash capacity, in bytes.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Release the QSPI driver, out of memory map mode.
+    pub fn release(mut self) -> Qspi<'d, T, M> {
+        self.disable_memory_map();
+        self.qspi
+    }
+
+    /// Read data starting at `address`.
+    pub fn blocking_read(&mut self, address: u32, buf: &mut [u8]) -> Result<(), Error> {
+        self.check_bounds(address, buf.len())?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        self.disable_memory_map();
+        let transaction = self.read_transaction(Some(address));
+        self.qspi.blocking_read(buf, transaction);
+        Ok(())
+    }
+
+    /// Program data starting at `address`, which must have been erased.
+    ///
+    /// The data is split in page program commands.
+    pub fn blocking_write(&mut self, address: u32, buf: &[u8]) -> Result<(), Error> {
+        self.check_bounds(address, buf.len())?;
+        self.disable_memory_map();
+
+        let mut address = address;
+        let mut buf = buf;
+        while !buf.is_empty() {
+            // A page program wraps around at the page end.
+            let len = buf.len().min((PAGE_SIZE - address % PAGE_SIZE) as usize);
+            let (chunk, rest) = buf.split_at(len);
+
+            self.write_enable();
+            let transaction = TransferConfig {
+                iwidth: QspiWidth::SING,
+                awidth: QspiWidth::SING,
+                dwidth: if self.quad { QspiWidth::QUAD } else { QspiWidth::SING },
+                instruction: if self.quad { QUAD_PAGE_PROGRAM } else { PAGE_PROGRAM },
+                address: Some(address),
+                dummy: DummyCycles::_0,
+            };
+            self.qspi.blocking_write(chunk, transaction);
+            self.wait_ready();
+
+            address += len as u32;
+            buf = rest;
+        }
+        Ok(())
+    }
+
+    /// Erase the range from `from` to `to`, which must be aligned to [`SECTOR_SIZE`].
+    ///
+    /// Whole 64 KiB blocks are erased with block erase commands, which are much faster.
+    pub fn blocking_erase(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        if from > to || to > self.capacity {
+            return Err(Error::OutOfBounds);
+        }
+        if from % SECTOR_SIZE != 0 || to % SECTOR_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        self.disable_memory_map();
+
+        let mut address = from;
+        while address < to {
+            let (instruction, size) = if address % BLOCK_SIZE == 0 && to - address >= BLOCK_SIZE {
+                (BLOCK_ERASE, BLOCK_SIZE)
+            } else {
+                (SECTOR_ERASE, SECTOR_SIZE)
+            };
+            self.write_enable();
+            self.qspi.blocking_command(TransferConfig {
+                iwidth: QspiWidth::SING,
+                awidth: QspiWidth::SING,
+                instruction,
+                address: Some(address),
+                ..Default::default()
+            });
+            self.wait_ready();
+            address += size;
+        }
+        Ok(())
+    }
+
+    /// Erase the whole flash.
+    pub fn blocking_erase_chip(&mut self) {
+        self.disable_memory_map();
+        self.write_enable();
+        self.qspi.blocking_command(TransferConfig {
+            iwidth: QspiWidth::SING,
+            instruction: CHIP_ERASE,
+            ..Default::default()
+        });
+        self.wait_ready();
+    }
+
+    /// Map the flash in the QSPI memory region, using the same read command as
+    /// [`Self::blocking_read`].
+    ///
+    /// The other operations of this driver leave memory map mode first, call this again after them.
+    pub fn enable_memory_map(&mut self) {
+        let transaction = self.read_transaction(None);
+        self.qspi.enable_memory_map(&transaction);
+        self.memory_mapped = true;
+    }
+
+    /// Leave memory map mode, if enabled.
+    pub fn disable_memory_map(&mut self) {
+        if self.memory_mapped {
+            self.qspi.disable_memory_map();
+            self.memory_mapped = false;
+        }
+    }
+
+    fn read_transaction(&self, address: Option<u32>) -> TransferConfig {
+        TransferConfig {
+            iwidth: QspiWidth::SING,
+            awidth: QspiWidth::SING,
+            dwidth: if self.quad { QspiWidth::QUAD } else { QspiWidth::SING },
+            instruction: if self.quad { QUAD_OUTPUT_FAST_READ } else { FAST_READ },
+            address,
+            dummy: DummyCycles::_8,
+        }
+    }
+
+    fn write_enable(&mut self) {
+        self.qspi.blocking_command(TransferConfig {
+            iwidth: QspiWidth::SING,
+            instruction: WRITE_ENABLE,
+            ..Default::default()
+        });
+    }
+
+    fn wait_ready(&mut self) {
+        loop {
+            let mut status = [0; 1];
+            self.qspi.blocking_read(
+                &mut status,
+                TransferConfig {
+                    iwidth: QspiWidth::SING,
+                    dwidth: QspiWidth::SING,
+                    instruction: READ_STATUS,
+                    ..Default::default()
+                },
+            );
+            if status[0] & STATUS_WIP == 0 {
+                return;
+            }
+        }
+    }
+
+    fn check_bounds(&self, address: u32, len: usize) -> Result<(), Error> {
+        match address.checked_add(len as u32) {
+            Some(end) if end <= self.capacity => Ok(()),
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+}
+
+impl<'d, T: Instance, M: PeriMode> ErrorType for QspiFlash<'d, T, M> {
+    type Error = Error;
+}
+
+impl<'d, T: Instance, M: PeriMode> ReadNorFlash for QspiFlash<'d, T, M> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.blocking_read(offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+}
+
+impl<'d, T: Instance, M: PeriMode> NorFlash for QspiFlash<'d, T, M> {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.blocking_write(offset, bytes)
+    }
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.blocking_erase(from, to)
+    }
+}
@@ -3,6 +3,7 @@
 #![macro_use]
 
 pub mod enums;
+pub mod flash;
 
 use core::marker::PhantomData;
 
@@ -219,6 +220,15 @@ impl<'d, T: Instance, M: PeriMode> Qspi<'d, T, M> {
         });
     }
 
+    /// Disable memory map mode
+    ///
+    /// Aborts the prefetch in progress, if any. Indirect transactions can be issued again afterwards.
+    pub fn disable_memory_map(&mut self) {
+        T::REGS.cr().modify(|v| v.set_abort(true));
+        while T::REGS.sr().read().busy() {}
+        T::REGS.fcr().modify(|v| v.set_ctcf(true));
+    }
+
     fn setup_transaction(&mut self, fmode: QspiMode, transaction: &TransferConfig, data_len: Option<usize>) {
         match (transaction.address, transaction.awidth) {
             (Some(_), QspiWidth::NONE) => panic!("QSPI address can't be sent with an address width of NONE"),
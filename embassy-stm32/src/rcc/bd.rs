@@ -10,13 +10,15 @@ pub const LSI_FREQ: Hertz = Hertz(40_000);
 pub const LSI_FREQ: Hertz = Hertz(32_000);
 
 #[allow(dead_code)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum LseMode {
     Oscillator(LseDrive),
     Bypass,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct LseConfig {
     pub frequency: Hertz,
     pub mode: LseMode,
@@ -26,7 +28,8 @@ pub struct LseConfig {
 }
 
 #[allow(dead_code)]
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum LseDrive {
     #[cfg(not(stm32h5))] // ES0565: LSE Low drive mode is not functional
     Low = 0,
@@ -63,7 +66,7 @@ type Bdcr = crate::pac::rcc::regs::Csr1;
 fn unlock() {}
 
 #[cfg(not(any(stm32c0)))]
-fn unlock() {
+pub(super) fn unlock() {
     #[cfg(any(stm32f0, stm32f1, stm32f2, stm32f3, stm32l0, stm32l1))]
     let cr = crate::pac::PWR.cr();
     #[cfg(not(any(stm32f0, stm32f1, stm32f2, stm32f3, stm32l0, stm32l1, stm32u5, stm32h5, stm32wba)))]
@@ -75,7 +78,7 @@ fn unlock() {
     while !cr.read().dbp() {}
 }
 
-fn bdcr() -> Reg<Bdcr, RW> {
+pub(super) fn bdcr() -> Reg<Bdcr, RW> {
     #[cfg(any(rtc_v2l0, rtc_v2l1))]
     return crate::pac::RCC.csr();
     #[cfg(not(any(rtc_v2l0, rtc_v2l1, stm32c0)))]
@@ -84,10 +87,14 @@ fn bdcr() -> Reg<Bdcr, RW> {
     return crate::pac::RCC.csr1();
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct LsConfig {
     pub rtc: RtcClockSource,
     pub lsi: bool,
+    /// Enable LSI2, the low-drift LSI. When it runs, it clocks the RTC and IWDG instead of LSI1.
+    #[cfg(rcc_wb)]
+    pub lsi2: bool,
     pub lse: Option<LseConfig>,
 }
 
@@ -113,6 +120,8 @@ impl LsConfig {
                 peripherals_clocked: false,
             }),
             lsi: false,
+            #[cfg(rcc_wb)]
+            lsi2: false,
         }
     }
 
@@ -120,6 +129,8 @@ impl LsConfig {
         Self {
             rtc: RtcClockSource::LSI,
             lsi: true,
+            #[cfg(rcc_wb)]
+            lsi2: false,
             lse: None,
         }
     }
@@ -128,6 +139,8 @@ impl LsConfig {
         Self {
             rtc: RtcClockSource::DISABLE,
             lsi: false,
+            #[cfg(rcc_wb)]
+            lsi2: false,
             lse: None,
         }
     }
@@ -143,7 +156,10 @@ impl LsConfig {
     pub(crate) fn init(&self) -> Option<Hertz> {
         let rtc_clk = match self.rtc {
             RtcClockSource::LSI => {
+                #[cfg(not(rcc_wb))]
                 assert!(self.lsi);
+                #[cfg(rcc_wb)]
+                assert!(self.lsi || self.lsi2);
                 Some(LSI_FREQ)
             }
             RtcClockSource::LSE => Some(self.lse.as_ref().unwrap().frequency),
@@ -193,6 +209,12 @@ impl LsConfig {
             while !csr.read().lsi1rdy() {}
         }
 
+        #[cfg(rcc_wb)]
+        if self.lsi2 {
+            crate::pac::RCC.csr().modify(|w| w.set_lsi2on(true));
+            while !crate::pac::RCC.csr().read().lsi2rdy() {}
+        }
+
         // backup domain configuration (LSEON, RTCEN, RTCSEL) is kept across resets.
         // once set, changing it requires a backup domain reset.
         // first check if the configuration matches what we want.
@@ -222,6 +244,14 @@ impl LsConfig {
             return rtc_clk;
         }
 
+        // The LSE CSS locks the LSE and the RTC clock selection until it detects a failure. Keep it,
+        // resetting the backup domain would turn it off.
+        #[cfg(any(rtc_v2h7, rtc_v2l4, rtc_v2wb, rtc_v3, rtc_v3u5))]
+        if reg.lsecsson() && !reg.lsecssd() {
+            warn!("LSE CSS is on, keeping the current LSE and RTC clock configuration");
+            return rtc_clk;
+        }
+
         // If not OK, reset backup domain and configure it.
         #[cfg(not(any(rcc_l0, rcc_l0_v2, rcc_l1, stm32h5, stm32h7rs, stm32c0)))]
         {
@@ -273,9 +303,6 @@ impl LsConfig {
 
         if self.rtc != RtcClockSource::DISABLE {
             bdcr().modify(|w| {
-                #[cfg(any(rtc_v2h7, rtc_v2l4, rtc_v2wb, rtc_v3, rtc_v3u5))]
-                assert!(!w.lsecsson(), "RTC is not compatible with LSE CSS, yet.");
-
                 #[cfg(not(rcc_wba))]
                 w.set_rtcen(true);
                 w.set_rtcsel(self.rtc);
@@ -10,7 +10,8 @@ use crate::time::Hertz;
 pub const HSI_FREQ: Hertz = Hertz(48_000_000);
 
 /// HSE Mode
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum HseMode {
     /// crystal/ceramic oscillator (HSEBYP=0)
     Oscillator,
@@ -19,7 +20,8 @@ pub enum HseMode {
 }
 
 /// HSE Configuration
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Hse {
     /// HSE frequency.
     pub freq: Hertz,
@@ -28,7 +30,8 @@ pub struct Hse {
 }
 
 /// HSI Configuration
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Hsi {
     /// Division factor for HSISYS clock. Default is 4.
     pub sys_div: HsiSysDiv,
@@ -38,7 +41,8 @@ pub struct Hsi {
 
 /// Clocks configutation
 #[non_exhaustive]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Config {
     /// HSI Configuration
     pub hsi: Option<Hsi>,
@@ -0,0 +1,118 @@
+//! Clock configuration common to all families.
+//!
+//! Each family has its own [`Config`], because the oscillators, PLLs and buses differ. The system
+//! clock source and the AHB/APB prescalers exist everywhere though, so [`CommonConfig`] describes
+//! them in a portable way and [`Config::set_common`] applies it. The rest of [`Config`] is the
+//! per-family extension, which must still enable the selected source (for example `hse` or `pll`).
+//!
+//! ```rust,ignore
+//! let mut config = embassy_stm32::Config::default();
+//! config.rcc.hse = Some(Hse { .. });
+//! config.rcc.set_common(rcc::CommonConfig {
+//!     sys: rcc::SysClkSource::HSE,
+//!     ..Default::default()
+//! })
+//! .unwrap();
+//! ```
+use super::{AHBPrescaler, APBPrescaler, Config, Sysclk};
+
+/// System clock source.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SysClkSource {
+    /// High speed internal oscillator. On C0 this is HSISYS, HSI after its divider.
+    HSI,
+    /// High speed external oscillator.
+    HSE,
+    /// Multi-speed internal oscillator (MSIS on U5).
+    MSI,
+    /// Low power internal oscillator (H5/H7).
+    CSI,
+    /// 48 MHz internal oscillator (F0).
+    HSI48,
+    /// Low speed internal oscillator (C0/G0).
+    LSI,
+    /// Low speed external oscillator (C0/G0).
+    LSE,
+    /// The PLL output that feeds the system clock, PLL1_P or PLL1_R depending on the family.
+    PLL,
+}
+
+/// The system clock source doesn't exist on this chip.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UnsupportedSysClk(pub SysClkSource);
+
+/// System clock source and bus prescalers, common to all families.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CommonConfig {
+    pub sys: SysClkSource,
+    pub ahb_pre: AHBPrescaler,
+    pub apb1_pre: APBPrescaler,
+    /// Ignored on families with a single APB bus (C0, F0, G0, U0).
+    pub apb2_pre: APBPrescaler,
+}
+
+impl Default for CommonConfig {
+    fn default() -> Self {
+        Self {
+            sys: SysClkSource::HSI,
+            ahb_pre: AHBPrescaler::DIV1,
+            apb1_pre: APBPrescaler::DIV1,
+            apb2_pre: APBPrescaler::DIV1,
+        }
+    }
+}
+
+impl TryFrom<SysClkSource> for Sysclk {
+    type Error = UnsupportedSysClk;
+
+    fn try_from(source: SysClkSource) -> Result<Self, Self::Error> {
+        Ok(match source {
+            #[cfg(not(stm32c0))]
+            SysClkSource::HSI => Sysclk::HSI,
+            #[cfg(stm32c0)]
+            SysClkSource::HSI => Sysclk::HSISYS,
+            SysClkSource::HSE => Sysclk::HSE,
+            #[cfg(any(stm32l0, stm32l1, stm32l4, stm32l5, stm32wb, stm32wl, stm32u0))]
+            SysClkSource::MSI => Sysclk::MSI,
+            #[cfg(stm32u5)]
+            SysClkSource::MSI => Sysclk::MSIS,
+            #[cfg(any(stm32h5, stm32h7, stm32h7rs))]
+            SysClkSource::CSI => Sysclk::CSI,
+            #[cfg(all(any(stm32f0, stm32f1, stm32f3), crs))]
+            SysClkSource::HSI48 => Sysclk::HSI48,
+            #[cfg(any(stm32c0, stm32g0))]
+            SysClkSource::LSI => Sysclk::LSI,
+            #[cfg(any(stm32c0, stm32g0))]
+            SysClkSource::LSE => Sysclk::LSE,
+            #[cfg(any(stm32f0, stm32f1, stm32f3, stm32f2, stm32f4, stm32f7, stm32h5, stm32h7, stm32h7rs))]
+            SysClkSource::PLL => Sysclk::PLL1_P,
+            #[cfg(any(
+                stm32g0, stm32g4, stm32l0, stm32l1, stm32l4, stm32l5, stm32wb, stm32wl, stm32u0, stm32u5
+            ))]
+            SysClkSource::PLL => Sysclk::PLL1_R,
+            // The WBA PLL isn't supported yet.
+            #[allow(unreachable_patterns)]
+            source => return Err(UnsupportedSysClk(source)),
+        })
+    }
+}
+
+impl Config {
+    /// Set the system clock source and the bus prescalers from a [`CommonConfig`].
+    ///
+    /// Fails if the source doesn't exist on this chip. Whether it is actually enabled is only
+    /// checked when the clocks are initialized.
+    pub fn set_common(&mut self, common: CommonConfig) -> Result<(), UnsupportedSysClk> {
+        self.sys = common.sys.try_into()?;
+        self.ahb_pre = common.ahb_pre;
+        self.apb1_pre = common.apb1_pre;
+        #[cfg(not(any(stm32c0, stm32f0, stm32g0, stm32u0)))]
+        {
+            self.apb2_pre = common.apb2_pre;
+        }
+        Ok(())
+    }
+}
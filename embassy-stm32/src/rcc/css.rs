@@ -0,0 +1,177 @@
+//! Clock security system
+//!
+//! The LSE clock security system detects a failure of the LSE crystal, for example a broken or
+//! shorted crystal. When it fires, the LSE is no longer used by the RTC, so RTC-based time stops
+//! advancing until the application switches the RTC to another clock.
+//!
+//! ```rust,ignore
+//! bind_interrupts!(struct Irqs {
+//!     RCC => rcc::CssInterruptHandler;
+//! });
+//!
+//! rcc::enable_lse_css(Irqs).unwrap();
+//! rcc::wait_for_lse_failure().await;
+//! warn!("LSE failed");
+//! ```
+//!
+//! The HSE clock security system reports a failure of the HSE through the NMI. The hardware then
+//! turns the HSE and the PLLs clocked by it off, and switches SYSCLK to HSI16. The NMI handler
+//! belongs to the application, which must forward it to [`on_hse_css_nmi`]:
+//!
+//! ```rust,ignore
+//! #[cortex_m_rt::exception]
+//! fn NonMaskableInt() {
+//!     rcc::on_hse_css_nmi();
+//! }
+//!
+//! rcc::enable_hse_css(None).unwrap();
+//! rcc::wait_for_hse_failure().await;
+//! // Update the clock frequencies known to the drivers.
+//! rcc::reinit(hsi_config, &mut p.RCC);
+//! ```
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use core::task::Poll;
+
+use embassy_sync::waitqueue::AtomicWaker;
+
+use super::RccError;
+use crate::interrupt;
+use crate::interrupt::typelevel::Interrupt;
+use crate::pac::rcc::vals::Stopwuck;
+use crate::pac::RCC;
+
+static LSE_CSS_WAKER: AtomicWaker = AtomicWaker::new();
+static HSE_CSS_WAKER: AtomicWaker = AtomicWaker::new();
+static HSE_FAILED: AtomicBool = AtomicBool::new(false);
+static HSE_CSS_CALLBACK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// RCC interrupt handler for the clock security system.
+pub struct CssInterruptHandler {
+    _private: (),
+}
+
+impl interrupt::typelevel::Handler<interrupt::typelevel::RCC> for CssInterruptHandler {
+    unsafe fn on_interrupt() {
+        if RCC.cifr().read().lsecssf() {
+            RCC.cier().modify(|w| w.set_lsecssie(false));
+            RCC.cicr().write(|w| w.set_lsecssc(true));
+            LSE_CSS_WAKER.wake();
+        }
+    }
+}
+
+/// Enable the LSE clock security system.
+///
+/// The LSE and the LSI must be running, otherwise this fails with [`RccError::SourceNotEnabled`].
+/// The RTC clock source must already be selected, as it can't be changed while the LSE CSS is on.
+///
+/// [`super::reinit`] keeps the LSE and RTC clock configuration while the LSE CSS is on. Once it
+/// detected a failure, a reinit with another RTC clock source resets the backup domain, which
+/// also turns the LSE CSS off.
+pub fn enable_lse_css(
+    _irq: impl interrupt::typelevel::Binding<interrupt::typelevel::RCC, CssInterruptHandler>,
+) -> Result<(), RccError> {
+    #[cfg(stm32l4)]
+    let lsi_ready = RCC.csr().read().lsirdy();
+    #[cfg(stm32wb)]
+    let lsi_ready = RCC.csr().read().lsi1rdy();
+    if !RCC.bdcr().read().lserdy() || !lsi_ready {
+        return Err(RccError::SourceNotEnabled);
+    }
+
+    RCC.bdcr().modify(|w| w.set_lsecsson(true));
+    RCC.cier().modify(|w| w.set_lsecssie(true));
+
+    interrupt::typelevel::RCC::unpend();
+    unsafe { interrupt::typelevel::RCC::enable() };
+    Ok(())
+}
+
+/// Whether the LSE clock security system detected a failure.
+///
+/// The failure flag is only cleared by a backup domain reset.
+pub fn lse_failed() -> bool {
+    RCC.bdcr().read().lsecssd()
+}
+
+/// Wait until the LSE clock security system detects a failure of the LSE.
+///
+/// Returns immediately if it already did.
+pub async fn wait_for_lse_failure() {
+    poll_fn(|cx| {
+        LSE_CSS_WAKER.register(cx.waker());
+        if lse_failed() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// Enable the HSE clock security system.
+///
+/// `callback` is called from the NMI when the HSE fails, before [`wait_for_hse_failure`] returns.
+///
+/// The backup clock used after a failure is selected by `STOPWUCK`, which also selects the clock
+/// used when waking up from Stop modes. It is set to HSI16 here, so the whole chip wakes up from
+/// Stop modes on HSI16 from then on instead of MSI.
+///
+/// Fails with [`RccError::SourceNotEnabled`] if the HSE doesn't run.
+pub fn enable_hse_css(callback: Option<fn()>) -> Result<(), RccError> {
+    if !RCC.cr().read().hserdy() {
+        return Err(RccError::SourceNotEnabled);
+    }
+
+    let callback = callback.map_or(core::ptr::null_mut(), |f| f as *mut ());
+    HSE_CSS_CALLBACK.store(callback, Ordering::Release);
+    HSE_FAILED.store(false, Ordering::Release);
+
+    let enable = || {
+        // Fall back to HSI16, not MSI.
+        RCC.cfgr().modify(|w| w.set_stopwuck(Stopwuck::HSI16));
+        RCC.cr().modify(|w| w.set_csson(true));
+    };
+    #[cfg(stm32l4)]
+    enable();
+    #[cfg(stm32wb)]
+    crate::hsem::shared::with_lock(crate::hsem::shared::RCC_SEMID, enable);
+    Ok(())
+}
+
+/// Handle an HSE failure. Call this from the `NonMaskableInt` exception handler.
+///
+/// Returns whether the NMI was raised by the HSE clock security system.
+pub fn on_hse_css_nmi() -> bool {
+    if !RCC.cifr().read().cssf() {
+        return false;
+    }
+    // The NMI is raised again as long as the flag is set.
+    RCC.cicr().write(|w| w.set_cssc(true));
+    HSE_FAILED.store(true, Ordering::Release);
+
+    let callback = HSE_CSS_CALLBACK.load(Ordering::Acquire);
+    if !callback.is_null() {
+        let callback: fn() = unsafe { core::mem::transmute(callback) };
+        callback();
+    }
+    HSE_CSS_WAKER.wake();
+    true
+}
+
+/// Wait until the HSE clock security system detects a failure of the HSE.
+///
+/// Returns immediately if it already did. The clock frequencies known to the drivers are out of
+/// date from then on, until the clocks are set up again with [`super::reinit`].
+pub async fn wait_for_hse_failure() {
+    poll_fn(|cx| {
+        HSE_CSS_WAKER.register(cx.waker());
+        if HSE_FAILED.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
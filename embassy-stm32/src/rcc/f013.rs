@@ -17,7 +17,8 @@ use crate::time::Hertz;
 /// HSI speed
 pub const HSI_FREQ: Hertz = Hertz(8_000_000);
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum HseMode {
     /// crystal/ceramic oscillator (HSEBYP=0)
     Oscillator,
@@ -25,7 +26,8 @@ pub enum HseMode {
     Bypass,
 }
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Hse {
     /// HSE frequency.
     pub freq: Hertz,
@@ -33,7 +35,8 @@ pub struct Hse {
     pub mode: HseMode,
 }
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PllSource {
     HSE,
     HSI,
@@ -43,7 +46,8 @@ pub enum PllSource {
     PLL2,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Pll {
     pub src: PllSource,
 
@@ -57,20 +61,23 @@ pub struct Pll {
 }
 
 #[cfg(stm32f107)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Pll2Or3 {
     pub mul: Pll2Mul,
 }
 
 #[cfg(all(stm32f3, not(rcc_f37)))]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum AdcClockSource {
     Pll(AdcPllPrescaler),
     Hclk(AdcHclkPrescaler),
 }
 
 #[cfg(all(stm32f3, not(rcc_f37)))]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum AdcHclkPrescaler {
     Div1,
     Div2,
@@ -78,7 +85,8 @@ pub enum AdcHclkPrescaler {
 }
 
 #[cfg(stm32f334)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum HrtimClockSource {
     BusClk,
     PllClk,
@@ -86,7 +94,8 @@ pub enum HrtimClockSource {
 
 /// Clocks configutation
 #[non_exhaustive]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Config {
     pub hsi: bool,
     pub hse: Option<Hse>,
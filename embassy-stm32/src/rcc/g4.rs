@@ -10,7 +10,8 @@ use crate::time::Hertz;
 pub const HSI_FREQ: Hertz = Hertz(16_000_000);
 
 /// HSE Mode
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum HseMode {
     /// crystal/ceramic oscillator (HSEBYP=0)
     Oscillator,
@@ -19,7 +20,8 @@ pub enum HseMode {
 }
 
 /// HSE Configuration
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Hse {
     /// HSE frequency.
     pub freq: Hertz,
@@ -32,7 +34,8 @@ pub struct Hse {
 /// Use this struct to configure the PLL source, input frequency, multiplication factor, and output
 /// dividers. Be sure to keep check the datasheet for your specific part for the appropriate
 /// frequency ranges for each of these settings.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Pll {
     /// PLL Source clock selection.
     pub source: PllSource,
@@ -55,7 +58,8 @@ pub struct Pll {
 
 /// Clocks configutation
 #[non_exhaustive]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Config {
     /// HSI Enable
     pub hsi: bool,
@@ -116,7 +120,8 @@ impl Default for Config {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct PllFreq {
     pub pll_p: Option<Hertz>,
     pub pll_q: Option<Hertz>,
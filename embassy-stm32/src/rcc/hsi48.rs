@@ -10,11 +10,12 @@ pub const HSI48_FREQ: Hertz = Hertz(48_000_000);
 
 /// Configuration for the HSI48 clock
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Hsi48Config {
     /// Enable CRS Sync from USB Start Of Frame (SOF) events.
     /// Required if HSI48 is going to be used as USB clock.
     ///
-    /// Other use cases of CRS are not supported yet.
+    /// For other synchronization sources, leave this disabled and use the `crs` driver.
     pub sync_from_usb: bool,
 }
 
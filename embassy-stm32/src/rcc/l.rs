@@ -1,4 +1,6 @@
-#[cfg(any(stm32l0, stm32l1))]
+#[cfg(stm32wb)]
+use crate::hsem::shared;
+#[cfg(any(stm32l0, stm32l1, stm32wb))]
 pub use crate::pac::pwr::vals::Vos as VoltageScale;
 use crate::pac::rcc::regs::Cfgr;
 #[cfg(any(stm32wb, stm32wl))]
@@ -11,7 +13,8 @@ use crate::time::Hertz;
 /// HSI speed
 pub const HSI_FREQ: Hertz = Hertz(16_000_000);
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum HseMode {
     /// crystal/ceramic oscillator (HSEBYP=0)
     Oscillator,
@@ -19,7 +22,8 @@ pub enum HseMode {
     Bypass,
 }
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Hse {
     /// HSE frequency.
     pub freq: Hertz,
@@ -28,10 +32,19 @@ pub struct Hse {
     /// HSE prescaler
     #[cfg(any(stm32wb, stm32wl))]
     pub prescaler: HsePrescaler,
+    /// HSE load capacitor tuning (`HSETUNE`, 0 to 63), to trim the crystal frequency for RF
+    /// accuracy. `None` keeps the current value.
+    #[cfg(stm32wb)]
+    pub tune: Option<u8>,
 }
 
+/// Key unlocking `RCC_HSECR` for one write.
+#[cfg(stm32wb)]
+const RCC_HSECR_KEY: u32 = 0xCAFE_CAFE;
+
 /// Clocks configuration
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Config {
     // base clock sources
     pub msi: Option<MSIRange>,
@@ -42,7 +55,7 @@ pub struct Config {
 
     // pll
     pub pll: Option<Pll>,
-    #[cfg(any(stm32l4, stm32l5, stm32wb))]
+    #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
     pub pllsai1: Option<Pll>,
     #[cfg(any(stm32l47x, stm32l48x, stm32l49x, stm32l4ax, rcc_l4plus, stm32l5))]
     pub pllsai2: Option<Pll>,
@@ -58,10 +71,23 @@ pub struct Config {
     #[cfg(any(stm32wl, stm32wb))]
     pub shared_ahb_pre: AHBPrescaler,
 
+    /// RF system wake-up clock, which must run before CPU2 starts the wireless stack.
+    #[cfg(stm32wb)]
+    pub rf_wakeup: RfWakeupClock,
+
+    /// SMPS step-down converter. `None` leaves it in its reset state, bypassed.
+    #[cfg(stm32wb)]
+    pub smps: Option<Smps>,
+
     // low speed LSI/LSE/RTC
     pub ls: super::LsConfig,
 
-    #[cfg(any(stm32l0, stm32l1))]
+    /// Enter Low-Power Run mode, with the main regulator off. SYSCLK must be at most 2 MHz.
+    #[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl))]
+    pub low_power_run: bool,
+
+    /// Core voltage range. On WB, range 2 limits HCLK4 to 16 MHz.
+    #[cfg(any(stm32l0, stm32l1, stm32wb))]
     pub voltage_scale: VoltageScale,
 
     /// Per-peripheral kernel clock selection muxes
@@ -83,15 +109,21 @@ impl Config {
             core2_ahb_pre: AHBPrescaler::DIV1,
             #[cfg(any(stm32wl, stm32wb))]
             shared_ahb_pre: AHBPrescaler::DIV1,
+            #[cfg(stm32wb)]
+            rf_wakeup: RfWakeupClock::Lse,
+            #[cfg(stm32wb)]
+            smps: None,
             pll: None,
-            #[cfg(any(stm32l4, stm32l5, stm32wb))]
+            #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
             pllsai1: None,
             #[cfg(any(stm32l47x, stm32l48x, stm32l49x, stm32l4ax, rcc_l4plus, stm32l5))]
             pllsai2: None,
             #[cfg(crs)]
             hsi48: Some(crate::rcc::Hsi48Config::new()),
             ls: crate::rcc::LsConfig::new(),
-            #[cfg(any(stm32l0, stm32l1))]
+            #[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl))]
+            low_power_run: false,
+            #[cfg(any(stm32l0, stm32l1, stm32wb))]
             voltage_scale: VoltageScale::RANGE1,
             mux: super::mux::ClockMux::default(),
         }
@@ -104,12 +136,149 @@ impl Default for Config {
     }
 }
 
+/// Clock configuration error, returned by [`Config::validate`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RccError {
+    /// A clock selected as source of SYSCLK, a PLL or the SMPS is not enabled.
+    SourceNotEnabled,
+    /// The selected clock source is not supported.
+    UnsupportedSource,
+    /// The enabled PLLs don't share the same source (and pre-divider on STM32L4), as the hardware
+    /// requires.
+    PllSourceMismatch,
+    /// The PLL input frequency, after the pre-divider (M), is out of range.
+    InvalidPllM,
+    /// The PLL VCO frequency is out of range.
+    VcoOutOfRange,
+    /// A PLL output is too high, or not available on this PLL.
+    InvalidPllOutput,
+    /// SYSCLK is above the maximum, or above 2 MHz in Low-Power Run mode.
+    SysclkTooHigh,
+    /// HCLK4 is too high for the voltage range.
+    HclkTooHigh,
+    /// HSETUNE or the SMPS output voltage is out of range.
+    InvalidParameter,
+}
+
+impl Config {
+    /// Check the configuration, without touching the hardware.
+    ///
+    /// [`crate::init`] panics if this fails, [`crate::try_init`] returns the error instead.
+    pub fn validate(&self) -> Result<(), RccError> {
+        let hse = self.hse.map(|hse| hse.freq);
+        let hsi = self.hsi.then_some(HSI_FREQ);
+        let msi = self.msi.map(msirange_to_hertz);
+
+        #[cfg(stm32wb)]
+        if self.hse.is_some_and(|hse| hse.tune.is_some_and(|tune| tune > 63)) {
+            return Err(RccError::InvalidParameter);
+        }
+
+        #[cfg(stm32wb)]
+        if let Some(smps) = self.smps {
+            if let Some(mv) = smps.output_voltage_mv {
+                if !(1200..=1950).contains(&mv) || mv % 50 != 0 {
+                    return Err(RccError::InvalidParameter);
+                }
+            }
+            let enabled = match smps.clock {
+                SmpsClock::HSI => hsi.is_some(),
+                SmpsClock::MSI => matches!(msi, Some(Hertz(16_000_000 | 24_000_000 | 32_000_000 | 48_000_000))),
+                SmpsClock::HSE => hse == Some(Hertz(32_000_000)),
+            };
+            if !enabled {
+                return Err(RccError::SourceNotEnabled);
+            }
+        }
+
+        let _plls = [
+            (PllInstance::Pll, &self.pll),
+            #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
+            (PllInstance::Pllsai1, &self.pllsai1),
+            #[cfg(any(stm32l47x, stm32l48x, stm32l49x, stm32l4ax, rcc_l4plus, stm32l5))]
+            (PllInstance::Pllsai2, &self.pllsai2),
+        ];
+
+        // L4 has shared PLLSRC, PLLM.
+        #[cfg(all(stm32l4, not(rcc_l4plus)))]
+        if super::util::get_equal(_plls.iter().filter_map(|(_, p)| p.map(|p| (p.source, p.prediv)))).is_err() {
+            return Err(RccError::PllSourceMismatch);
+        }
+        // L4+, WL has shared PLLSRC.
+        #[cfg(any(rcc_l4plus, stm32wl))]
+        if super::util::get_equal(_plls.iter().filter_map(|(_, p)| p.map(|p| p.source))).is_err() {
+            return Err(RccError::PllSourceMismatch);
+        }
+
+        let pll_input = PllInput {
+            hse,
+            hsi,
+            #[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl, stm32u0))]
+            msi,
+        };
+        let mut pll_r = None;
+        for (instance, pll) in _plls {
+            if let Some(pll) = pll {
+                let r = validate_pll(instance, pll, &pll_input)?;
+                if instance == PllInstance::Pll {
+                    pll_r = r;
+                }
+            }
+        }
+
+        let sys_clk = match self.sys {
+            Sysclk::HSE => hse,
+            Sysclk::HSI => hsi,
+            Sysclk::MSI => msi,
+            Sysclk::PLL1_R => pll_r,
+            #[cfg(stm32u0)]
+            _ => return Err(RccError::UnsupportedSource),
+        }
+        .ok_or(RccError::SourceNotEnabled)?;
+
+        #[cfg(rcc_l4plus)]
+        if sys_clk.0 > 120_000_000 {
+            return Err(RccError::SysclkTooHigh);
+        }
+        #[cfg(all(stm32l4, not(rcc_l4plus)))]
+        if sys_clk.0 > 80_000_000 {
+            return Err(RccError::SysclkTooHigh);
+        }
+
+        #[cfg(stm32wb)]
+        {
+            // In range 2, SYSCLK and all the HCLKs are limited to 16 MHz.
+            let max = match self.voltage_scale {
+                VoltageScale::RANGE1 => 64_000_000,
+                _ => 16_000_000,
+            };
+            if sys_clk.0 > max {
+                return Err(RccError::SysclkTooHigh);
+            }
+            for pre in [self.ahb_pre, self.core2_ahb_pre, self.shared_ahb_pre] {
+                if (sys_clk / pre).0 > max {
+                    return Err(RccError::HclkTooHigh);
+                }
+            }
+        }
+
+        #[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl))]
+        if self.low_power_run && sys_clk > Hertz(2_000_000) {
+            return Err(RccError::SysclkTooHigh);
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(stm32wb)]
 pub const WPAN_DEFAULT: Config = Config {
     hse: Some(Hse {
         freq: Hertz(32_000_000),
         mode: HseMode::Oscillator,
         prescaler: HsePrescaler::DIV1,
+        tune: None,
     }),
     sys: Sysclk::PLL1_R,
     #[cfg(crs)]
@@ -118,6 +287,8 @@ pub const WPAN_DEFAULT: Config = Config {
     hsi: false,
 
     ls: super::LsConfig::default_lse(),
+    low_power_run: false,
+    voltage_scale: VoltageScale::RANGE1,
 
     pll: Some(Pll {
         source: PllSource::HSE,
@@ -127,6 +298,7 @@ pub const WPAN_DEFAULT: Config = Config {
         divq: Some(PllQDiv::DIV4), // 32 / 2 * 12 / 4 = 48Mhz
         divr: Some(PllRDiv::DIV3), // 32 / 2 * 12 / 3 = 64Mhz
     }),
+    #[cfg(not(stm32wb1x))]
     pllsai1: None,
 
     ahb_pre: AHBPrescaler::DIV1,
@@ -134,10 +306,107 @@ pub const WPAN_DEFAULT: Config = Config {
     shared_ahb_pre: AHBPrescaler::DIV1,
     apb1_pre: APBPrescaler::DIV1,
     apb2_pre: APBPrescaler::DIV1,
+    rf_wakeup: RfWakeupClock::Lse,
+    smps: None,
 
     mux: super::mux::ClockMux::default(),
 };
 
+/// RF system wake-up clock source (`RFWKPSEL`).
+#[cfg(stm32wb)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RfWakeupClock {
+    /// No clock. The wireless stack can't run.
+    Disabled = 0b00,
+    /// LSE.
+    Lse = 0b01,
+    /// HSE divided by 1024.
+    HseDiv1024 = 0b11,
+}
+
+/// SMPS step-down converter mode.
+#[cfg(stm32wb)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SmpsMode {
+    /// The SMPS is bypassed, VDD supplies the LDOs directly.
+    Bypass,
+    /// The SMPS supplies the LDOs.
+    StepDown,
+}
+
+/// SMPS step-down converter clock source (`SMPSSEL`).
+#[cfg(stm32wb)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SmpsClock {
+    /// HSI16.
+    HSI = 0b00,
+    /// MSI, in the 16, 24, 32 or 48 MHz range.
+    MSI = 0b01,
+    /// HSE, 32 MHz.
+    HSE = 0b10,
+}
+
+/// SMPS step-down converter clock prescaler range (`SMPSDIV`).
+///
+/// The actual division factor depends on the source, so that the SMPS clock is 8 MHz in range 0
+/// and 4 MHz in range 1.
+#[cfg(stm32wb)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SmpsDivRange {
+    Range0 = 0b00,
+    Range1 = 0b01,
+}
+
+/// SMPS step-down converter configuration.
+#[cfg(stm32wb)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Smps {
+    pub mode: SmpsMode,
+    /// Clock source, which must be enabled in the config.
+    pub clock: SmpsClock,
+    pub div_range: SmpsDivRange,
+    /// Output voltage in millivolts, from 1200 to 1950 in 50 mV steps. `None` keeps the factory
+    /// trimmed 1500 mV.
+    pub output_voltage_mv: Option<u16>,
+}
+
+/// Factory calibration of `SMPSVOS` for 1500 mV, in bits 11:8.
+#[cfg(stm32wb)]
+const SMPS_VOLTAGE_CAL_ADDR: *const u32 = 0x1FFF_7558 as *const u32;
+
+#[cfg(stm32wb)]
+fn smps_init(smps: Smps) {
+    use crate::pac::rcc::vals::Smpssel;
+    use crate::pac::PWR;
+
+    RCC.smpscr().modify(|w| {
+        w.set_smpssel(Smpssel::from_bits(smps.clock as u8));
+        w.set_smpsdiv(smps.div_range as u8);
+    });
+
+    if let Some(mv) = smps.output_voltage_mv {
+        let cal = (unsafe { SMPS_VOLTAGE_CAL_ADDR.read_volatile() } >> 8) & 0xF;
+        let vos = (cal as i32 + (mv as i32 - 1500) / 50).clamp(0, 15) as u8;
+        PWR.cr5().modify(|w| w.set_smpsvos(vos));
+    }
+
+    match smps.mode {
+        SmpsMode::Bypass => {
+            PWR.cr5().modify(|w| w.set_smpsen(false));
+            while !PWR.sr2().read().smpsbf() {}
+        }
+        SmpsMode::StepDown => {
+            PWR.cr5().modify(|w| w.set_smpsen(true));
+            while !PWR.sr2().read().smpsf() {}
+        }
+    }
+}
+
 fn msi_enable(range: MSIRange) {
     #[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl, stm32u0))]
     RCC.cr().modify(|w| {
@@ -154,6 +423,22 @@ fn msi_enable(range: MSIRange) {
 }
 
 pub(crate) unsafe fn init(config: Config) {
+    unwrap!(config.validate());
+
+    // The wireless stack on CPU2 changes the clock configuration too, for example when it leaves
+    // Stop mode, so both cores take a hardware semaphore around it.
+    #[cfg(stm32wb)]
+    shared::blocking_lock(shared::RCC_SEMID);
+    #[cfg(stm32wb)]
+    let _unlock = embassy_hal_internal::drop::OnDrop::new(|| shared::unlock(shared::RCC_SEMID));
+
+    // Leave Low-Power Run mode before the clocks can be raised again.
+    #[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl))]
+    if crate::pac::PWR.cr1().read().lpr() {
+        crate::pac::PWR.cr1().modify(|w| w.set_lpr(false));
+        while crate::pac::PWR.sr2().read().reglpf() {}
+    }
+
     // Switch to MSI to prevent problems with PLL configuration.
     if !RCC.cr().read().msion() {
         // Turn on MSI and configure it to 4MHz.
@@ -180,6 +465,13 @@ pub(crate) unsafe fn init(config: Config) {
         crate::pac::PWR.cr().write(|w| w.set_vos(config.voltage_scale));
         while crate::pac::PWR.csr().read().vosf() {}
     }
+    // Raise the voltage range before the clocks. It is only lowered once the clocks are within
+    // the limits of the lower range, at the end.
+    #[cfg(stm32wb)]
+    if config.voltage_scale == VoltageScale::RANGE1 {
+        crate::pac::PWR.cr1().modify(|w| w.set_vos(config.voltage_scale));
+        while crate::pac::PWR.sr2().read().vosf() {}
+    }
 
     #[cfg(stm32l5)]
     crate::pac::PWR.cr1().modify(|w| {
@@ -188,8 +480,14 @@ pub(crate) unsafe fn init(config: Config) {
 
     let rtc = config.ls.init();
 
+    #[cfg(stm32wb)]
+    RCC.csr().modify(|w| w.set_rfwkpsel(config.rf_wakeup as u8));
+
     let lse = config.ls.lse.map(|l| l.frequency);
+    #[cfg(not(stm32wb))]
     let lsi = config.ls.lsi.then_some(LSI_FREQ);
+    #[cfg(stm32wb)]
+    let lsi = (config.ls.lsi || config.ls.lsi2).then_some(LSI_FREQ);
 
     let msi = config.msi.map(|range| {
         msi_enable(range);
@@ -210,6 +508,13 @@ pub(crate) unsafe fn init(config: Config) {
     });
 
     let hse = config.hse.map(|hse| {
+        #[cfg(stm32wb)]
+        if let Some(tune) = hse.tune {
+            // The key only unlocks the next write, the read of the modify doesn't consume it.
+            RCC.hsecr().write_value(crate::pac::rcc::regs::Hsecr(RCC_HSECR_KEY));
+            RCC.hsecr().modify(|w| w.set_hsetune(tune));
+        }
+
         RCC.cr().modify(|w| {
             #[cfg(stm32wl)]
             w.set_hsebyppwr(hse.mode == HseMode::Bypass);
@@ -222,39 +527,46 @@ pub(crate) unsafe fn init(config: Config) {
         hse.freq
     });
 
-    #[cfg(crs)]
+    #[cfg(stm32wb)]
+    if let Some(smps) = config.smps {
+        smps_init(smps);
+    }
+
+    #[cfg(all(crs, not(stm32wb)))]
     let hsi48 = config.hsi48.map(|config| super::init_hsi48(config));
+    #[cfg(all(crs, stm32wb))]
+    let hsi48 = shared::with_lock(shared::CLK48_SEMID, || {
+        config.hsi48.map(|config| super::init_hsi48(config))
+    });
     #[cfg(not(crs))]
     let hsi48: Option<Hertz> = None;
 
     let _plls = [
         &config.pll,
-        #[cfg(any(stm32l4, stm32l5, stm32wb))]
+        #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
         &config.pllsai1,
         #[cfg(any(stm32l47x, stm32l48x, stm32l49x, stm32l4ax, rcc_l4plus, stm32l5))]
         &config.pllsai2,
     ];
 
-    // L4 has shared PLLSRC, PLLM, check it's equal in all PLLs.
+    // L4 has shared PLLSRC, PLLM, checked to be equal in all PLLs by `validate`.
     #[cfg(all(stm32l4, not(rcc_l4plus)))]
-    match super::util::get_equal(_plls.into_iter().flatten().map(|p| (p.source, p.prediv))) {
-        Err(()) => panic!("Source must be equal across all enabled PLLs."),
-        Ok(None) => {}
-        Ok(Some((source, prediv))) => RCC.pllcfgr().write(|w| {
+    if let Ok(Some((source, prediv))) =
+        super::util::get_equal(_plls.into_iter().flatten().map(|p| (p.source, p.prediv)))
+    {
+        RCC.pllcfgr().write(|w| {
             w.set_pllm(prediv);
             w.set_pllsrc(source);
-        }),
-    };
+        });
+    }
 
-    // L4+, WL has shared PLLSRC, check it's equal in all PLLs.
+    // L4+, WL has shared PLLSRC, checked to be equal in all PLLs by `validate`.
     #[cfg(any(rcc_l4plus, stm32wl))]
-    match super::util::get_equal(_plls.into_iter().flatten().map(|p| p.source)) {
-        Err(()) => panic!("Source must be equal across all enabled PLLs."),
-        Ok(None) => {}
-        Ok(Some(source)) => RCC.pllcfgr().write(|w| {
+    if let Ok(Some(source)) = super::util::get_equal(_plls.into_iter().flatten().map(|p| p.source)) {
+        RCC.pllcfgr().write(|w| {
             w.set_pllsrc(source);
-        }),
-    };
+        });
+    }
 
     let pll_input = PllInput {
         hse,
@@ -263,7 +575,7 @@ pub(crate) unsafe fn init(config: Config) {
         msi,
     };
     let pll = init_pll(PllInstance::Pll, config.pll, &pll_input);
-    #[cfg(any(stm32l4, stm32l5, stm32wb))]
+    #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
     let pllsai1 = init_pll(PllInstance::Pllsai1, config.pllsai1, &pll_input);
     #[cfg(any(stm32l47x, stm32l48x, stm32l49x, stm32l4ax, rcc_l4plus, stm32l5))]
     let pllsai2 = init_pll(PllInstance::Pllsai2, config.pllsai2, &pll_input);
@@ -274,16 +586,9 @@ pub(crate) unsafe fn init(config: Config) {
         Sysclk::MSI => msi.unwrap(),
         Sysclk::PLL1_R => pll.r.unwrap(),
         #[cfg(stm32u0)]
-        Sysclk::LSI | Sysclk::LSE => todo!(),
-        #[cfg(stm32u0)]
-        Sysclk::_RESERVED_6 | Sysclk::_RESERVED_7 => unreachable!(),
+        _ => unreachable!(),
     };
 
-    #[cfg(rcc_l4plus)]
-    assert!(sys_clk.0 <= 120_000_000);
-    #[cfg(all(stm32l4, not(rcc_l4plus)))]
-    assert!(sys_clk.0 <= 80_000_000);
-
     let hclk1 = sys_clk / config.ahb_pre;
     let (pclk1, pclk1_tim) = super::util::calc_pclk(hclk1, config.apb1_pre);
     #[cfg(not(stm32u0))]
@@ -331,13 +636,15 @@ pub(crate) unsafe fn init(config: Config) {
         _ => 2,
     };
     #[cfg(stm32wb)]
-    let latency = match hclk3.0 {
-        // VOS RANGE1, others TODO.
-        ..=18_000_000 => 0,
-        ..=36_000_000 => 1,
-        ..=54_000_000 => 2,
-        ..=64_000_000 => 3,
-        _ => 4,
+    let latency = match (config.voltage_scale, hclk3.0) {
+        (VoltageScale::RANGE1, ..=18_000_000) => 0,
+        (VoltageScale::RANGE1, ..=36_000_000) => 1,
+        (VoltageScale::RANGE1, ..=54_000_000) => 2,
+        (VoltageScale::RANGE1, ..=64_000_000) => 3,
+        (VoltageScale::RANGE2, ..=6_000_000) => 0,
+        (VoltageScale::RANGE2, ..=12_000_000) => 1,
+        (VoltageScale::RANGE2, ..=16_000_000) => 2,
+        _ => unreachable!(),
     };
     #[cfg(stm32u0)]
     let latency = match hclk1.0 {
@@ -378,7 +685,22 @@ pub(crate) unsafe fn init(config: Config) {
         while !RCC.extcfgr().read().c2hpref() {}
     }
 
+    #[cfg(stm32wb)]
+    if config.voltage_scale != VoltageScale::RANGE1 {
+        crate::pac::PWR.cr1().modify(|w| w.set_vos(config.voltage_scale));
+        while crate::pac::PWR.sr2().read().vosf() {}
+    }
+
+    #[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl))]
+    if config.low_power_run {
+        crate::pac::PWR.cr1().modify(|w| w.set_lpr(true));
+        while !crate::pac::PWR.sr2().read().reglpf() {}
+    }
+
+    #[cfg(not(stm32wb))]
     config.mux.init();
+    #[cfg(stm32wb)]
+    shared::with_lock(shared::CLK48_SEMID, || config.mux.init());
 
     set_clocks!(
         sys: Some(sys_clk),
@@ -409,11 +731,11 @@ pub(crate) unsafe fn init(config: Config) {
         pll1_q: pll.q,
         pll1_r: pll.r,
 
-        #[cfg(any(stm32l4, stm32l5, stm32wb))]
+        #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
         pllsai1_p: pllsai1.p,
-        #[cfg(any(stm32l4, stm32l5, stm32wb))]
+        #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
         pllsai1_q: pllsai1.q,
-        #[cfg(any(stm32l4, stm32l5, stm32wb))]
+        #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
         pllsai1_r: pllsai1.r,
 
         #[cfg(not(any(stm32l47x, stm32l48x, stm32l49x, stm32l4ax, rcc_l4plus, stm32l5)))]
@@ -470,7 +792,7 @@ fn msirange_to_hertz(range: MSIRange) -> Hertz {
 #[derive(PartialEq, Eq, Clone, Copy)]
 enum PllInstance {
     Pll,
-    #[cfg(any(stm32l4, stm32l5, stm32wb))]
+    #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
     Pllsai1,
     #[cfg(any(stm32l47x, stm32l48x, stm32l49x, stm32l4ax, rcc_l4plus, stm32l5))]
     Pllsai2,
@@ -482,7 +804,7 @@ fn pll_enable(instance: PllInstance, enabled: bool) {
             RCC.cr().modify(|w| w.set_pllon(enabled));
             while RCC.cr().read().pllrdy() != enabled {}
         }
-        #[cfg(any(stm32l4, stm32l5, stm32wb))]
+        #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
         PllInstance::Pllsai1 => {
             RCC.cr().modify(|w| w.set_pllsai1on(enabled));
             while RCC.cr().read().pllsai1rdy() != enabled {}
@@ -499,12 +821,13 @@ pub use pll::*;
 
 #[cfg(any(stm32l0, stm32l1))]
 mod pll {
-    use super::{pll_enable, PllInstance};
+    use super::{pll_enable, PllInstance, RccError};
     pub use crate::pac::rcc::vals::{Plldiv as PllDiv, Pllmul as PllMul, Pllsrc as PllSource};
     use crate::pac::RCC;
     use crate::time::Hertz;
 
-    #[derive(Clone, Copy)]
+    #[derive(Clone, Copy, Debug)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub struct Pll {
         /// PLL source
         pub source: PllSource,
@@ -528,6 +851,21 @@ mod pll {
         pub vco: Option<Hertz>,
     }
 
+    /// Check the PLL configuration, returning the output frequency.
+    pub(super) fn validate_pll(_instance: PllInstance, pll: &Pll, input: &PllInput) -> Result<Option<Hertz>, RccError> {
+        let pll_src = match pll.source {
+            PllSource::HSE => input.hse,
+            PllSource::HSI => input.hsi,
+        }
+        .ok_or(RccError::SourceNotEnabled)?;
+
+        let r = pll_src * pll.mul / pll.div;
+        if r > Hertz(32_000_000) {
+            return Err(RccError::InvalidPllOutput);
+        }
+        Ok(Some(r))
+    }
+
     pub(super) fn init_pll(instance: PllInstance, config: Option<Pll>, input: &PllInput) -> PllOutput {
         // Disable PLL
         pll_enable(instance, false);
@@ -543,8 +881,6 @@ mod pll {
 
         let r = vco_freq / pll.div;
 
-        assert!(r <= Hertz(32_000_000));
-
         RCC.cfgr().write(move |w| {
             w.set_pllmul(pll.mul);
             w.set_plldiv(pll.div);
@@ -563,14 +899,15 @@ mod pll {
 
 #[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl, stm32u0))]
 mod pll {
-    use super::{pll_enable, PllInstance};
+    use super::{pll_enable, PllInstance, RccError};
     pub use crate::pac::rcc::vals::{
         Pllm as PllPreDiv, Plln as PllMul, Pllp as PllPDiv, Pllq as PllQDiv, Pllr as PllRDiv, Pllsrc as PllSource,
     };
     use crate::pac::RCC;
     use crate::time::Hertz;
 
-    #[derive(Clone, Copy)]
+    #[derive(Clone, Copy, Debug)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub struct Pll {
         /// PLL source
         pub source: PllSource,
@@ -589,6 +926,44 @@ mod pll {
         pub divr: Option<PllRDiv>,
     }
 
+    #[cfg(stm32wb)]
+    impl Pll {
+        /// Find the PLL settings that produce `sysclk` on the R output, from `source` running at
+        /// `input`. The P and Q outputs are left disabled.
+        ///
+        /// Returns `None` if `sysclk` can't be reached exactly within the PLL input and VCO limits.
+        pub fn from_target(source: PllSource, input: Hertz, sysclk: Hertz) -> Option<Self> {
+            if sysclk.0 > 64_000_000 {
+                return None;
+            }
+            // Prefer the highest PLL input frequency, which has the lowest jitter.
+            for m in 1..=8u32 {
+                let pll_input = input.0 / m;
+                if input.0 % m != 0 || !(2_660_000..=16_000_000).contains(&pll_input) {
+                    continue;
+                }
+                for r in 2..=8u32 {
+                    let vco = sysclk.0 as u64 * r as u64;
+                    if !(96_000_000..=344_000_000).contains(&vco) || vco % pll_input as u64 != 0 {
+                        continue;
+                    }
+                    let n = vco / pll_input as u64;
+                    if (6..=127).contains(&n) {
+                        return Some(Self {
+                            source,
+                            prediv: PllPreDiv::from_bits(m as u8 - 1),
+                            mul: PllMul::from_bits(n as u8),
+                            divp: None,
+                            divq: None,
+                            divr: Some(PllRDiv::from_bits(r as u8 - 1)),
+                        });
+                    }
+                }
+            }
+            None
+        }
+    }
+
     pub(super) struct PllInput {
         pub hsi: Option<Hertz>,
         pub hse: Option<Hertz>,
@@ -603,6 +978,37 @@ mod pll {
         pub r: Option<Hertz>,
     }
 
+    /// Check the PLL configuration, returning the R output frequency.
+    pub(super) fn validate_pll(_instance: PllInstance, pll: &Pll, input: &PllInput) -> Result<Option<Hertz>, RccError> {
+        let pll_src = match pll.source {
+            PllSource::DISABLE => return Err(RccError::UnsupportedSource),
+            PllSource::HSE => input.hse,
+            PllSource::HSI => input.hsi,
+            PllSource::MSI => input.msi,
+        }
+        .ok_or(RccError::SourceNotEnabled)?;
+
+        let vco_freq = pll_src / pll.prediv * pll.mul;
+
+        #[cfg(stm32wb)]
+        {
+            if !(2_660_000..=16_000_000).contains(&(pll_src / pll.prediv).0) {
+                return Err(RccError::InvalidPllM);
+            }
+            if !(96_000_000..=344_000_000).contains(&vco_freq.0) {
+                return Err(RccError::VcoOutOfRange);
+            }
+        }
+
+        // PLLSAI2 has no Q and R outputs on L5.
+        #[cfg(stm32l5)]
+        if _instance == PllInstance::Pllsai2 && (pll.divq.is_some() || pll.divr.is_some()) {
+            return Err(RccError::InvalidPllOutput);
+        }
+
+        Ok(pll.divr.map(|div| vco_freq / div))
+    }
+
     pub(super) fn init_pll(instance: PllInstance, config: Option<Pll>, input: &PllInput) -> PllOutput {
         // Disable PLL
         pll_enable(instance, false);
@@ -610,7 +1016,7 @@ mod pll {
         let Some(pll) = config else { return PllOutput::default() };
 
         let pll_src = match pll.source {
-            PllSource::DISABLE => panic!("must not select PLL source as DISABLE"),
+            PllSource::DISABLE => unreachable!(),
             PllSource::HSE => unwrap!(input.hse),
             PllSource::HSI => unwrap!(input.hsi),
             PllSource::MSI => unwrap!(input.msi),
@@ -622,12 +1028,6 @@ mod pll {
         let q = pll.divq.map(|div| vco_freq / div);
         let r = pll.divr.map(|div| vco_freq / div);
 
-        #[cfg(stm32l5)]
-        if instance == PllInstance::Pllsai2 {
-            assert!(q.is_none(), "PLLSAI2_Q is not available on L5");
-            assert!(r.is_none(), "PLLSAI2_R is not available on L5");
-        }
-
         macro_rules! write_fields {
             ($w:ident) => {
                 $w.set_plln(pll.mul);
@@ -652,7 +1052,7 @@ mod pll {
                 w.set_pllsrc(pll.source);
                 write_fields!(w);
             }),
-            #[cfg(any(stm32l4, stm32l5, stm32wb))]
+            #[cfg(any(stm32l4, stm32l5, all(stm32wb, not(stm32wb1x))))]
             PllInstance::Pllsai1 => RCC.pllsai1cfgr().write(|w| {
                 #[cfg(any(rcc_l4plus, stm32l5))]
                 w.set_pllm(pll.prediv);
@@ -676,3 +1076,35 @@ mod pll {
         PllOutput { p, q, r }
     }
 }
+
+#[cfg(all(test, stm32wb))]
+mod tests {
+    use super::*;
+
+    fn assert_pll(pll: Pll, prediv: u8, mul: u8, divr: u8) {
+        assert_eq!(prediv, pll.prediv.to_bits() + 1);
+        assert_eq!(mul, pll.mul.to_bits());
+        assert_eq!(divr, pll.divr.unwrap().to_bits() + 1);
+        assert!(pll.divp.is_none() && pll.divq.is_none());
+    }
+
+    #[test]
+    fn can_find_pll_for_target() {
+        let pll = Pll::from_target(PllSource::HSE, Hertz(32_000_000), Hertz(64_000_000)).unwrap();
+        assert!(pll.source == PllSource::HSE);
+        assert_pll(pll, 2, 8, 2);
+
+        let pll = Pll::from_target(PllSource::MSI, Hertz(4_000_000), Hertz(48_000_000)).unwrap();
+        assert_pll(pll, 1, 24, 2);
+
+        // The VCO must run at 96 MHz or more.
+        let pll = Pll::from_target(PllSource::HSI, Hertz(16_000_000), Hertz(24_000_000)).unwrap();
+        assert_pll(pll, 1, 6, 4);
+    }
+
+    #[test]
+    fn rejects_unreachable_pll_target() {
+        assert!(Pll::from_target(PllSource::HSE, Hertz(32_000_000), Hertz(80_000_000)).is_none());
+        assert!(Pll::from_target(PllSource::HSE, Hertz(32_000_000), Hertz(63_999_999)).is_none());
+    }
+}
@@ -0,0 +1,74 @@
+//! Low-speed clock output (LSCO)
+//!
+//! Outputs the LSI or LSE on PA2, for example to clock an external RTC or to measure the LSE
+//! accuracy. LSCO is part of the backup domain, so it keeps running in Stop and Standby modes,
+//! independently of the GPIO configuration.
+//!
+//! ```rust,ignore
+//! let lsco = rcc::Lsco::new(p.PA2, rcc::LscoSource::LSE).unwrap();
+//! // Keep LSCO running after `lsco` goes out of scope, for example across Standby.
+//! core::mem::forget(lsco);
+//! ```
+use super::bd::{bdcr, unlock};
+use super::RccError;
+use crate::gpio::SealedPin;
+use crate::pac::rcc::vals::Lscosel;
+use crate::{peripherals, Peri};
+
+/// LSCO clock source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LscoSource {
+    /// LSI. On WB, LSI2 if it runs, LSI1 otherwise.
+    LSI,
+    /// LSE.
+    LSE,
+}
+
+/// Low-speed clock output driver.
+///
+/// LSCO is turned off when the driver is dropped.
+pub struct Lsco<'d> {
+    _pin: Peri<'d, peripherals::PA2>,
+}
+
+impl<'d> Lsco<'d> {
+    /// Output `source` on PA2. Fails with [`RccError::SourceNotEnabled`] if it doesn't run.
+    pub fn new(pin: Peri<'d, peripherals::PA2>, source: LscoSource) -> Result<Self, RccError> {
+        let csr = crate::pac::RCC.csr().read();
+        let running = match source {
+            #[cfg(not(stm32wb))]
+            LscoSource::LSI => csr.lsirdy(),
+            #[cfg(stm32wb)]
+            LscoSource::LSI => csr.lsi1rdy() || csr.lsi2rdy(),
+            LscoSource::LSE => bdcr().read().lserdy(),
+        };
+        if !running {
+            return Err(RccError::SourceNotEnabled);
+        }
+
+        critical_section::with(|_| {
+            pin.set_as_analog();
+
+            unlock();
+            bdcr().modify(|w| {
+                w.set_lscosel(match source {
+                    LscoSource::LSI => Lscosel::LSI,
+                    LscoSource::LSE => Lscosel::LSE,
+                });
+                w.set_lscoen(true);
+            });
+        });
+
+        Ok(Self { _pin: pin })
+    }
+}
+
+impl<'d> Drop for Lsco<'d> {
+    fn drop(&mut self) {
+        critical_section::with(|_| {
+            unlock();
+            bdcr().modify(|w| w.set_lscoen(false));
+        });
+    }
+}
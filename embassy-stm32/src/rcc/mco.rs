@@ -35,7 +35,8 @@ use crate::pac::RCC;
 use crate::{peripherals, Peri};
 
 #[cfg(any(stm32f1, rcc_f0v1, rcc_f3v1, rcc_f37))]
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum McoPrescaler {
     DIV1,
 }
@@ -64,11 +65,17 @@ macro_rules! impl_peri {
                 #[cfg(any(stm32u5, stm32wba))]
                 let r = RCC.cfgr1();
 
-                r.modify(|w| {
-                    w.$set_source(source);
-                    #[cfg(not(any(stm32f1, rcc_f0v1, rcc_f3v1, rcc_f37)))]
-                    w.$set_prescaler(_prescaler);
-                });
+                let apply = || {
+                    r.modify(|w| {
+                        w.$set_source(source);
+                        #[cfg(not(any(stm32f1, rcc_f0v1, rcc_f3v1, rcc_f37)))]
+                        w.$set_prescaler(_prescaler);
+                    })
+                };
+                #[cfg(not(stm32wb))]
+                apply();
+                #[cfg(stm32wb)]
+                crate::hsem::shared::with_lock(crate::hsem::shared::RCC_SEMID, apply);
             }
         }
     };
@@ -8,12 +8,25 @@ use core::mem::MaybeUninit;
 mod bd;
 pub use bd::*;
 
+mod common;
+pub use common::*;
+
 #[cfg(any(mco, mco1, mco2))]
 mod mco;
 use critical_section::CriticalSection;
 #[cfg(any(mco, mco1, mco2))]
 pub use mco::*;
 
+#[cfg(any(stm32l4, stm32wb))]
+mod css;
+#[cfg(any(stm32l4, stm32wb))]
+pub use css::*;
+
+#[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl))]
+mod lsco;
+#[cfg(any(stm32l4, stm32l5, stm32wb, stm32wl))]
+pub use lsco::*;
+
 #[cfg(crs)]
 mod hsi48;
 #[cfg(crs)]
@@ -106,6 +119,9 @@ pub fn clocks<'a>(_rcc: &'a crate::Peri<'a, crate::peripherals::RCC>) -> &'a Clo
 
 pub(crate) trait SealedRccPeripheral {
     fn frequency() -> Hertz;
+    /// Kernel clock frequency, or `None` if the clock is not running.
+    #[allow(dead_code)]
+    fn try_frequency() -> Option<Hertz>;
     #[allow(dead_code)]
     fn bus_frequency() -> Hertz;
     const RCC_INFO: RccInfo;
@@ -175,7 +191,18 @@ impl RccInfo {
     }
 
     // TODO: should this be `unsafe`?
-    pub(crate) fn enable_and_reset_with_cs(&self, _cs: CriticalSection) {
+    pub(crate) fn enable_and_reset_with_cs(&self, cs: CriticalSection) {
+        self.enable_inner(cs, true)
+    }
+
+    /// Enable the clock without resetting the peripheral, so its registers keep the values they
+    /// had when it was disabled.
+    // TODO: should this be `unsafe`?
+    pub(crate) fn enable_with_cs(&self, cs: CriticalSection) {
+        self.enable_inner(cs, false)
+    }
+
+    fn enable_inner(&self, _cs: CriticalSection, reset: bool) {
         if self.refcount_idx_or_0xff != 0xff {
             let refcount_idx = self.refcount_idx_or_0xff as usize;
 
@@ -206,7 +233,7 @@ impl RccInfo {
         }
 
         // set the xxxRST bit
-        let reset_ptr = self.reset_ptr();
+        let reset_ptr = if reset { self.reset_ptr() } else { None };
         if let Some(reset_ptr) = reset_ptr {
             unsafe {
                 let val = reset_ptr.read_volatile();
@@ -284,6 +311,11 @@ impl RccInfo {
         critical_section::with(|cs| self.enable_and_reset_with_cs(cs))
     }
 
+    // TODO: should this be `unsafe`?
+    pub(crate) fn enable(&self) {
+        critical_section::with(|cs| self.enable_with_cs(cs))
+    }
+
     // TODO: should this be `unsafe`?
     pub(crate) fn disable(&self) {
         critical_section::with(|cs| self.disable_with_cs(cs))
@@ -371,6 +403,16 @@ pub fn enable_and_reset<T: RccPeripheral>() {
     T::RCC_INFO.enable_and_reset();
 }
 
+/// Enables peripheral `T` without resetting it.
+///
+/// # Safety
+///
+/// Peripheral must not be in use.
+// TODO: should this be `unsafe`?
+pub fn enable<T: RccPeripheral>() {
+    T::RCC_INFO.enable();
+}
+
 /// Disables peripheral `T`.
 ///
 /// # Safety
@@ -384,14 +426,23 @@ pub fn disable<T: RccPeripheral>() {
 /// Re-initialize the `embassy-stm32` clock configuration with the provided configuration.
 ///
 /// This is useful when you need to alter the CPU clock after configuring peripherals.
-/// For instance, configure an external clock via spi or i2c.
+/// For instance, configure an external clock via spi or i2c, or scale the system clock down
+/// while idle and back up under load. The flash latency and the frequencies returned by
+/// [`get_freqs`] are updated, and the time driver keeps counting at the same tick rate.
 ///
-/// Please not this only re-configures the rcc and the time driver (not GPIO, EXTI, etc).
+/// Please note this only re-configures the rcc and the time driver (not GPIO, EXTI, etc).
+/// Drivers that derive a divider from their kernel clock, like a UART baudrate, must be
+/// reconfigured by the application if their kernel clock changed.
 ///
 /// This should only be called after `init`.
 #[cfg(not(feature = "_dual-core"))]
 pub fn reinit<'a>(config: Config, _rcc: &'a mut crate::Peri<'a, crate::peripherals::RCC>) {
-    critical_section::with(|cs| init_rcc(cs, config))
+    critical_section::with(|_cs| unsafe {
+        init(config);
+
+        #[cfg(feature = "_time-driver")]
+        crate::time_driver::reconfigure(_cs);
+    })
 }
 
 pub(crate) fn init_rcc(_cs: CriticalSection, config: Config) {
@@ -25,6 +25,18 @@ pub enum Error {
     ClockError,
 }
 
+/// RNG error statistics.
+///
+/// Each counter saturates at `u32::MAX`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ErrorStats {
+    /// Number of seed errors. The RNG is reconditioned after each of them.
+    pub seed_errors: u32,
+    /// Number of clock errors.
+    pub clock_errors: u32,
+}
+
 /// RNG interrupt handler.
 pub struct InterruptHandler<T: Instance> {
     _phantom: PhantomData<T>,
@@ -43,6 +55,7 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
 /// RNG driver.
 pub struct Rng<'d, T: Instance> {
     _inner: Peri<'d, T>,
+    stats: ErrorStats,
 }
 
 impl<'d, T: Instance> Rng<'d, T> {
@@ -52,7 +65,20 @@ impl<'d, T: Instance> Rng<'d, T> {
         _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
     ) -> Self {
         rcc::enable_and_reset::<T>();
-        let mut random = Self { _inner: inner };
+
+        // The clock error detection flags an RNG clock slower than HCLK / 16, see the reference manual.
+        let freq = T::frequency();
+        if freq.0 < T::bus_frequency().0 / 16 {
+            warn!(
+                "RNG clock is {} Hz, which is too slow for the AHB clock. Use the 48 MHz clock for RNG.",
+                freq.0
+            );
+        }
+
+        let mut random = Self {
+            _inner: inner,
+            stats: ErrorStats::default(),
+        };
         random.reset();
 
         T::Interrupt::unpend();
@@ -115,8 +141,22 @@ impl<'d, T: Instance> Rng<'d, T> {
         while T::regs().cr().read().condrst() {}
     }
 
+    /// Get the error statistics since the driver was created.
+    pub fn error_stats(&self) -> ErrorStats {
+        self.stats
+    }
+
+    /// Clear the error statistics.
+    pub fn clear_error_stats(&mut self) {
+        self.stats = ErrorStats::default();
+    }
+
     /// Try to recover from a seed error.
+    ///
+    /// This clears the error and reconditions the RNG, as described in the reference manual.
+    /// Random data generated before the error is discarded.
     pub fn recover_seed_error(&mut self) {
+        self.stats.seed_errors = self.stats.seed_errors.saturating_add(1);
         self.reset();
         // reset should also clear the SEIS flag
         if T::regs().sr().read().seis() {
@@ -164,6 +204,7 @@ impl<'d, T: Instance> Rng<'d, T> {
             } else if bits.ceis() {
                 // clock error detected, DR could still be used but keep it safe,
                 // clear the error and abort
+                self.stats.clock_errors = self.stats.clock_errors.saturating_add(1);
                 T::regs().sr().modify(|sr| sr.set_ceis(false));
                 return Err(Error::ClockError);
             } else if bits.drdy() {
@@ -172,6 +213,7 @@ impl<'d, T: Instance> Rng<'d, T> {
                 let random_word = T::regs().dr().read();
                 // reference manual: always check if DR is zero
                 if random_word == 0 {
+                    self.recover_seed_error();
                     return Err(Error::SeedError);
                 }
                 // write bytes to chunk
@@ -188,10 +230,17 @@ impl<'d, T: Instance> Rng<'d, T> {
     pub fn next_u32(&mut self) -> u32 {
         loop {
             let sr = T::regs().sr().read();
-            if sr.seis() | sr.ceis() {
+            if sr.seis() {
+                self.recover_seed_error();
+            } else if sr.ceis() {
+                self.stats.clock_errors = self.stats.clock_errors.saturating_add(1);
                 self.reset();
             } else if sr.drdy() {
-                return T::regs().dr().read();
+                let random_word = T::regs().dr().read();
+                if random_word != 0 {
+                    return random_word;
+                }
+                self.recover_seed_error();
             }
         }
     }
@@ -232,11 +232,11 @@ impl Rtc {
             use crate::pac::EXTI;
             EXTI.rtsr(0).modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
 
-            #[cfg(not(stm32wb))]
+            #[cfg(not(exti_w))]
             {
                 EXTI.imr(0).modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
             }
-            #[cfg(stm32wb)]
+            #[cfg(exti_w)]
             {
                 EXTI.cpu(0).imr(0).modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
             }
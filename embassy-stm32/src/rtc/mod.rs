@@ -30,6 +30,9 @@ mod _version;
 #[allow(unused_imports)]
 pub use _version::*;
 
+#[cfg(all(feature = "time", any(stm32l4, stm32wb)))]
+pub mod tempcomp;
+
 use crate::peripherals::RTC;
 use crate::Peri;
 
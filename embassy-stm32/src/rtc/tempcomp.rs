@@ -0,0 +1,135 @@
+//! Temperature compensation of the RTC
+//!
+//! 32.768 kHz tuning-fork crystals slow down on both sides of their turnover temperature,
+//! following a parabola: about -0.034 ppm/°C², which is already -20 ppm (almost 2 s/day) at
+//! 0 °C or 50 °C. [`TemperatureCompensation`] samples the die temperature with the ADC and
+//! programs the RTC smooth calibration to cancel the expected drift.
+//!
+//! ```rust,ignore
+//! #[embassy_executor::task]
+//! async fn rtc_compensation(mut rtc: Rtc, mut adc: Adc<'static, ADC1>) {
+//!     let mut comp = TemperatureCompensation::new(CrystalTempco::default());
+//!     comp.run(&mut rtc, &mut adc, Duration::from_secs(60)).await
+//! }
+//! ```
+use embassy_time::{Duration, Timer};
+
+use super::{Rtc, RtcCalibrationCyclePeriod};
+use crate::adc::{self, Adc};
+
+/// Frequency-temperature curve of the LSE crystal.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CrystalTempco {
+    /// Turnover temperature, in °C, where the crystal frequency is the highest.
+    pub turnover_celsius: f32,
+    /// Parabolic coefficient, in ppm/°C². Negative for tuning-fork crystals.
+    pub coefficient: f32,
+    /// Frequency error at the turnover temperature, in ppm, for example from a factory measurement.
+    pub offset_ppm: f32,
+}
+
+impl Default for CrystalTempco {
+    fn default() -> Self {
+        Self {
+            turnover_celsius: 25.0,
+            coefficient: -0.034,
+            offset_ppm: 0.0,
+        }
+    }
+}
+
+impl CrystalTempco {
+    /// Expected frequency error of the crystal at `celsius`, in ppm.
+    pub fn drift_ppm(&self, celsius: f32) -> f32 {
+        let delta = celsius - self.turnover_celsius;
+        self.offset_ppm + self.coefficient * delta * delta
+    }
+}
+
+/// RTC temperature compensation.
+pub struct TemperatureCompensation {
+    tempco: CrystalTempco,
+    correction_ppm: Option<f32>,
+}
+
+impl TemperatureCompensation {
+    /// Calibration resolution of the RTC, in ppm. Smaller changes are not applied.
+    const RESOLUTION_PPM: f32 = 0.9537;
+
+    /// Create a new temperature compensation for a crystal with the curve `tempco`.
+    pub const fn new(tempco: CrystalTempco) -> Self {
+        Self {
+            tempco,
+            correction_ppm: None,
+        }
+    }
+
+    /// Correction currently applied to the RTC, in ppm.
+    pub fn correction_ppm(&self) -> Option<f32> {
+        self.correction_ppm
+    }
+
+    /// Measure the die temperature and update the RTC calibration.
+    ///
+    /// The ADC must use the 12-bit resolution. Its sampling time is set to a value suitable for the
+    /// temperature sensor. Returns the measured temperature, in °C.
+    pub fn update<T: adc::Instance>(&mut self, rtc: &mut Rtc, adc: &mut Adc<'_, T>) -> f32 {
+        let (_, celsius) = adc.blocking_read_vdda_and_temperature();
+
+        // A slow crystal needs positive pulses.
+        let correction = -self.tempco.drift_ppm(celsius);
+        let changed = match self.correction_ppm {
+            Some(current) => {
+                let delta = correction - current;
+                delta >= Self::RESOLUTION_PPM / 2.0 || delta <= -Self::RESOLUTION_PPM / 2.0
+            }
+            None => true,
+        };
+        if changed {
+            trace!("rtc: temperature {} °C, correction {} ppm", celsius, correction);
+            rtc.calibrate(correction, RtcCalibrationCyclePeriod::Seconds32);
+            self.correction_ppm = Some(correction);
+        }
+
+        celsius
+    }
+
+    /// Update the RTC calibration every `period`.
+    pub async fn run<T: adc::Instance>(&mut self, rtc: &mut Rtc, adc: &mut Adc<'_, T>, period: Duration) -> ! {
+        loop {
+            self.update(rtc, adc);
+            Timer::after(period).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expected: f32, actual: f32) {
+        let delta = expected - actual;
+        assert!(delta < 1e-3 && delta > -1e-3, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn can_compute_drift() {
+        let tempco = CrystalTempco::default();
+        assert_close(0.0, tempco.drift_ppm(25.0));
+        assert_close(-3.4, tempco.drift_ppm(15.0));
+        assert_close(-3.4, tempco.drift_ppm(35.0));
+        assert_close(-21.25, tempco.drift_ppm(0.0));
+    }
+
+    #[test]
+    fn can_compute_drift_with_offset() {
+        let tempco = CrystalTempco {
+            turnover_celsius: 20.0,
+            coefficient: -0.04,
+            offset_ppm: 5.0,
+        };
+        assert_close(5.0, tempco.drift_ppm(20.0));
+        assert_close(1.0, tempco.drift_ppm(30.0));
+    }
+}
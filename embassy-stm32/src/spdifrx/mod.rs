@@ -97,6 +97,7 @@ pub struct Config {
 
 /// S/PDIF errors.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// DMA overrun error.
     RingbufferError(RingbufferError),
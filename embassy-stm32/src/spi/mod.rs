@@ -46,7 +46,8 @@ impl core::fmt::Display for Error {
 impl core::error::Error for Error {}
 
 /// SPI bit order
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum BitOrder {
     /// Least significant bit first.
     LsbFirst,
@@ -56,7 +57,7 @@ pub enum BitOrder {
 
 /// SPI configuration.
 #[non_exhaustive]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct Config {
     /// SPI mode.
     pub mode: Mode,
@@ -86,6 +87,30 @@ impl Default for Config {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Config {
+    fn format(&self, f: defmt::Formatter) {
+        let polarity = match self.mode.polarity {
+            Polarity::IdleLow => "IdleLow",
+            Polarity::IdleHigh => "IdleHigh",
+        };
+        let phase = match self.mode.phase {
+            Phase::CaptureOnFirstTransition => "CaptureOnFirstTransition",
+            Phase::CaptureOnSecondTransition => "CaptureOnSecondTransition",
+        };
+        defmt::write!(
+            f,
+            "Config {{ mode: {{ polarity: {=str}, phase: {=str} }}, bit_order: {}, frequency: {}, miso_pull: {}, rise_fall_speed: {} }}",
+            polarity,
+            phase,
+            self.bit_order,
+            self.frequency,
+            self.miso_pull,
+            self.rise_fall_speed
+        )
+    }
+}
+
 impl Config {
     fn raw_phase(&self) -> vals::Cpha {
         match self.mode.phase {
@@ -137,6 +162,7 @@ pub struct Spi<'d, M: PeriMode> {
     _phantom: PhantomData<M>,
     current_word_size: word_impl::Config,
     rise_fall_speed: Speed,
+    suspended: bool,
 }
 
 impl<'d, M: PeriMode> Spi<'d, M> {
@@ -160,6 +186,7 @@ impl<'d, M: PeriMode> Spi<'d, M> {
             current_word_size: <u8 as SealedWord>::CONFIG,
             _phantom: PhantomData,
             rise_fall_speed: config.rise_fall_speed,
+            suspended: false,
         };
         this.enable_and_init(config);
         this
@@ -351,6 +378,26 @@ impl<'d, M: PeriMode> Spi<'d, M> {
         }
     }
 
+    /// Gate the peripheral clock while the bus is idle.
+    ///
+    /// The configuration is kept, and the SPI no longer keeps the chip out of the low-power stop
+    /// modes. It must be [resumed](Self::resume) before the next transfer. Does nothing if the
+    /// SPI is already suspended.
+    pub fn suspend(&mut self) {
+        if !self.suspended {
+            self.suspended = true;
+            self.info.rcc.disable();
+        }
+    }
+
+    /// Ungate the peripheral clock after [`suspend`](Self::suspend).
+    pub fn resume(&mut self) {
+        if self.suspended {
+            self.suspended = false;
+            self.info.rcc.enable();
+        }
+    }
+
     pub(crate) fn set_word_size(&mut self, word_size: word_impl::Config) {
         if self.current_word_size == word_size {
             return;
@@ -894,7 +941,9 @@ impl<'d, M: PeriMode> Drop for Spi<'d, M> {
         self.mosi.as_ref().map(|x| x.set_as_disconnected());
         self.miso.as_ref().map(|x| x.set_as_disconnected());
 
-        self.info.rcc.disable();
+        if !self.suspended {
+            self.info.rcc.disable();
+        }
     }
 }
 
@@ -0,0 +1,508 @@
+//! LoRa modem
+//!
+//! [`LoRa`] configures the radio for LoRa packets and provides async transmit, receive and
+//! channel activity detection, which is what a LoRaWAN stack needs from the PHY: channel hopping
+//! is done with [`LoRa::set_frequency`] and [`LoRa::set_modulation`], and receive windows are
+//! opened with [`LoRa::receive`].
+//!
+//! There is no implementation of the `lorawan-device` radio traits in this crate. [`LoRa`] covers
+//! the same transmit, receive and CAD operations, an application using such a stack implements
+//! its radio trait on top of it.
+//!
+//! The antenna switch of the board, if any, is given as an [`RfSwitch`] and set automatically
+//! around each operation.
+use super::{Error, Irq, RfMode, RfSwitch, SubGhz};
+
+const SET_SLEEP: u8 = 0x84;
+const SET_STANDBY: u8 = 0x80;
+const SET_TX: u8 = 0x83;
+const SET_RX: u8 = 0x82;
+const SET_CAD: u8 = 0xC5;
+const SET_REGULATOR_MODE: u8 = 0x96;
+const CALIBRATE: u8 = 0x89;
+const CALIBRATE_IMAGE: u8 = 0x98;
+const SET_PA_CONFIG: u8 = 0x95;
+const SET_DIO_IRQ_PARAMS: u8 = 0x08;
+const SET_TCXO_MODE: u8 = 0x97;
+const SET_RF_FREQUENCY: u8 = 0x86;
+const SET_PACKET_TYPE: u8 = 0x8A;
+const SET_TX_PARAMS: u8 = 0x8E;
+const SET_MODULATION_PARAMS: u8 = 0x8B;
+const SET_PACKET_PARAMS: u8 = 0x8C;
+const SET_CAD_PARAMS: u8 = 0x88;
+const SET_BUFFER_BASE_ADDRESS: u8 = 0x8F;
+const GET_RX_BUFFER_STATUS: u8 = 0x13;
+const GET_PACKET_STATUS: u8 = 0x14;
+
+const PACKET_TYPE_LORA: u8 = 0x01;
+const REG_LORA_SYNC_WORD: u16 = 0x0740;
+/// IQ polarity setup, see the SX126x errata "Optimizing the inverted IQ operation".
+const REG_IQ_POLARITY: u16 = 0x0736;
+/// TX modulation, see the SX126x errata "Modulation quality with 500 kHz LoRa bandwidth".
+const REG_TX_MODULATION: u16 = 0x0889;
+
+/// Radio crystal frequency, in Hz.
+const XTAL_FREQ: u64 = 32_000_000;
+
+/// LoRa spreading factor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+pub enum SpreadingFactor {
+    _5 = 5,
+    _6 = 6,
+    _7 = 7,
+    _8 = 8,
+    _9 = 9,
+    _10 = 10,
+    _11 = 11,
+    _12 = 12,
+}
+
+/// LoRa bandwidth.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Bandwidth {
+    /// 7.81 kHz.
+    _7kHz,
+    /// 10.42 kHz.
+    _10kHz,
+    /// 15.63 kHz.
+    _15kHz,
+    /// 20.83 kHz.
+    _20kHz,
+    /// 31.25 kHz.
+    _31kHz,
+    /// 41.67 kHz.
+    _41kHz,
+    /// 62.5 kHz.
+    _62kHz,
+    /// 125 kHz.
+    _125kHz,
+    /// 250 kHz.
+    _250kHz,
+    /// 500 kHz.
+    _500kHz,
+}
+
+impl Bandwidth {
+    fn bits(&self) -> u8 {
+        match self {
+            Self::_7kHz => 0x00,
+            Self::_10kHz => 0x08,
+            Self::_15kHz => 0x01,
+            Self::_20kHz => 0x09,
+            Self::_31kHz => 0x02,
+            Self::_41kHz => 0x0A,
+            Self::_62kHz => 0x03,
+            Self::_125kHz => 0x04,
+            Self::_250kHz => 0x05,
+            Self::_500kHz => 0x06,
+        }
+    }
+
+    fn hz(&self) -> u32 {
+        match self {
+            Self::_7kHz => 7_810,
+            Self::_10kHz => 10_420,
+            Self::_15kHz => 15_630,
+            Self::_20kHz => 20_830,
+            Self::_31kHz => 31_250,
+            Self::_41kHz => 41_670,
+            Self::_62kHz => 62_500,
+            Self::_125kHz => 125_000,
+            Self::_250kHz => 250_000,
+            Self::_500kHz => 500_000,
+        }
+    }
+}
+
+/// LoRa coding rate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CodingRate {
+    /// 4/5.
+    _4_5 = 1,
+    /// 4/6.
+    _4_6 = 2,
+    /// 4/7.
+    _4_7 = 3,
+    /// 4/8.
+    _4_8 = 4,
+}
+
+/// Power amplifier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerAmplifier {
+    /// Low-power PA, from -17 to +15 dBm.
+    LowPower,
+    /// High-power PA, from -9 to +22 dBm.
+    HighPower,
+}
+
+/// TCXO supply voltage, on the PB0-VDD_TCXO pin.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+pub enum TcxoVoltage {
+    _1V6 = 0,
+    _1V7 = 1,
+    _1V8 = 2,
+    _2V2 = 3,
+    _2V4 = 4,
+    _2V7 = 5,
+    _3V0 = 6,
+    _3V3 = 7,
+}
+
+/// LoRa modem configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct Config {
+    /// RF frequency, in Hz.
+    pub frequency: u32,
+    /// Spreading factor.
+    pub spreading_factor: SpreadingFactor,
+    /// Bandwidth.
+    pub bandwidth: Bandwidth,
+    /// Coding rate.
+    pub coding_rate: CodingRate,
+    /// Preamble length, in symbols.
+    pub preamble_len: u16,
+    /// Append a CRC to transmitted packets, and check it on received packets.
+    pub crc: bool,
+    /// Invert the IQ signals when transmitting.
+    pub invert_iq_tx: bool,
+    /// Invert the IQ signals when receiving. LoRaWAN downlinks are sent with inverted IQ.
+    pub invert_iq_rx: bool,
+    /// Use the LoRaWAN public network sync word instead of the private one.
+    pub public_network: bool,
+    /// Power amplifier.
+    pub power_amplifier: PowerAmplifier,
+    /// Output power, in dBm.
+    pub tx_power: i8,
+    /// TCXO supply voltage, if the radio is clocked from a TCXO instead of a crystal.
+    pub tcxo: Option<TcxoVoltage>,
+    /// Power the radio from the SMPS instead of the LDO.
+    pub use_smps: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            frequency: 868_100_000,
+            spreading_factor: SpreadingFactor::_7,
+            bandwidth: Bandwidth::_125kHz,
+            coding_rate: CodingRate::_4_5,
+            preamble_len: 8,
+            crc: true,
+            invert_iq_tx: false,
+            invert_iq_rx: false,
+            public_network: true,
+            power_amplifier: PowerAmplifier::LowPower,
+            tx_power: 14,
+            tcxo: None,
+            use_smps: false,
+        }
+    }
+}
+
+/// Received packet information.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RxPacket {
+    /// Length of the packet. Only the part that fits in the buffer is copied.
+    pub len: usize,
+    /// Average RSSI over the packet, in dBm.
+    pub rssi: i16,
+    /// SNR, in dB.
+    pub snr: i8,
+}
+
+/// LoRa modem driver.
+pub struct LoRa<'d, S: RfSwitch = ()> {
+    radio: SubGhz<'d>,
+    switch: S,
+    config: Config,
+}
+
+impl<'d, S: RfSwitch> LoRa<'d, S> {
+    /// Configure the radio as a LoRa modem, with the antenna switch `switch`.
+    ///
+    /// Use `()` as `switch` on boards without an antenna switch to control.
+    pub async fn new(radio: SubGhz<'d>, switch: S, config: Config) -> Result<Self, Error> {
+        let mut this = Self { radio, switch, config };
+        this.init().await?;
+        Ok(this)
+    }
+
+    /// Release the radio driver and the antenna switch.
+    pub fn release(self) -> (SubGhz<'d>, S) {
+        (self.radio, self.switch)
+    }
+
+    /// Current configuration.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    async fn init(&mut self) -> Result<(), Error> {
+        self.set_standby().await?;
+
+        if let Some(voltage) = self.config.tcxo {
+            // 5 ms startup time, in steps of 15.625 µs.
+            let [_, d0, d1, d2] = 320u32.to_be_bytes();
+            self.radio
+                .write_command(SET_TCXO_MODE, &[voltage as u8, d0, d1, d2])
+                .await?;
+        }
+        self.radio
+            .write_command(SET_REGULATOR_MODE, &[self.config.use_smps as u8])
+            .await?;
+        // Calibrate all blocks, now that the clock source is known.
+        self.radio.write_command(CALIBRATE, &[0x7F]).await?;
+
+        self.radio.write_command(SET_BUFFER_BASE_ADDRESS, &[0, 0]).await?;
+        self.radio.write_command(SET_PACKET_TYPE, &[PACKET_TYPE_LORA]).await?;
+
+        let sync_word: u16 = if self.config.public_network { 0x3444 } else { 0x1424 };
+        self.radio
+            .write_registers(REG_LORA_SYNC_WORD, &sync_word.to_be_bytes())
+            .await?;
+
+        self.set_frequency(self.config.frequency).await?;
+        self.set_tx_power(self.config.power_amplifier, self.config.tx_power)
+            .await?;
+        self.set_modulation(
+            self.config.spreading_factor,
+            self.config.bandwidth,
+            self.config.coding_rate,
+        )
+        .await
+    }
+
+    /// Set the RF frequency, in Hz.
+    pub async fn set_frequency(&mut self, frequency: u32) -> Result<(), Error> {
+        self.config.frequency = frequency;
+
+        // The image calibration covers the whole band around the frequency.
+        let image = match frequency {
+            ..=440_000_000 => [0x6B, 0x6F],
+            ..=510_000_000 => [0x75, 0x81],
+            ..=787_000_000 => [0xC1, 0xC5],
+            ..=870_000_000 => [0xD7, 0xDB],
+            _ => [0xE1, 0xE9],
+        };
+        self.radio.write_command(CALIBRATE_IMAGE, &image).await?;
+
+        let steps = ((frequency as u64) << 25) / XTAL_FREQ;
+        self.radio
+            .write_command(SET_RF_FREQUENCY, &(steps as u32).to_be_bytes())
+            .await
+    }
+
+    /// Set the spreading factor, bandwidth and coding rate.
+    pub async fn set_modulation(
+        &mut self,
+        spreading_factor: SpreadingFactor,
+        bandwidth: Bandwidth,
+        coding_rate: CodingRate,
+    ) -> Result<(), Error> {
+        self.config.spreading_factor = spreading_factor;
+        self.config.bandwidth = bandwidth;
+        self.config.coding_rate = coding_rate;
+
+        // The low data rate optimization is required for symbols longer than 16.38 ms.
+        let symbol_us = (1_000_000u64 << spreading_factor as u8) / bandwidth.hz() as u64;
+        let ldro = symbol_us >= 16_380;
+
+        self.radio
+            .write_command(
+                SET_MODULATION_PARAMS,
+                &[spreading_factor as u8, bandwidth.bits(), coding_rate as u8, ldro as u8],
+            )
+            .await
+    }
+
+    /// Set the power amplifier and output power, in dBm.
+    pub async fn set_tx_power(&mut self, power_amplifier: PowerAmplifier, power: i8) -> Result<(), Error> {
+        self.config.power_amplifier = power_amplifier;
+        self.config.tx_power = power;
+
+        // PA duty cycle, HP max and PA selection, for the maximum output power of each PA.
+        let (pa_config, power) = match power_amplifier {
+            PowerAmplifier::LowPower => ([0x06, 0x00, 0x01, 0x01], power.clamp(-17, 15)),
+            PowerAmplifier::HighPower => ([0x04, 0x07, 0x00, 0x01], power.clamp(-9, 22)),
+        };
+        self.radio.write_command(SET_PA_CONFIG, &pa_config).await?;
+        // 200 µs ramp time.
+        self.radio.write_command(SET_TX_PARAMS, &[power as u8, 0x04]).await
+    }
+
+    /// Transmit a packet of up to 255 bytes, and wait until it has been sent.
+    ///
+    /// Fails with [`Error::PacketTooLong`] if `data` is longer.
+    pub async fn transmit(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > 255 {
+            return Err(Error::PacketTooLong);
+        }
+
+        self.set_standby().await?;
+        self.radio.write_buffer(0, data).await?;
+        self.set_packet_params(data.len() as u8, self.config.invert_iq_tx)
+            .await?;
+
+        // Errata: bit 2 must be cleared with the 500 kHz bandwidth and set otherwise, before
+        // each transmission.
+        let bw_500khz = self.config.bandwidth == Bandwidth::_500kHz;
+        self.modify_register(REG_TX_MODULATION, |v| if bw_500khz { v & !0x04 } else { v | 0x04 })
+            .await?;
+
+        let irq = self
+            .run(
+                &[SET_TX, 0, 0, 0],
+                Irq::TX_DONE | Irq::TIMEOUT,
+                RfMode::Tx(self.config.power_amplifier),
+            )
+            .await?;
+        if irq.contains(Irq::TIMEOUT) {
+            return Err(Error::Timeout);
+        }
+        Ok(())
+    }
+
+    /// Receive a packet into `buf`.
+    ///
+    /// Stops listening after `timeout_ms` milliseconds without a preamble, or never with `0`.
+    pub async fn receive(&mut self, buf: &mut [u8], timeout_ms: u32) -> Result<RxPacket, Error> {
+        self.set_standby().await?;
+        self.set_packet_params(255, self.config.invert_iq_rx).await?;
+
+        // Timeout in steps of 15.625 µs, at most 0xFFFFFE since 0xFFFFFF means continuous.
+        let [_, t0, t1, t2] = timeout_ms.saturating_mul(64).min(0xFF_FFFE).to_be_bytes();
+        let irq = self
+            .run(
+                &[SET_RX, t0, t1, t2],
+                Irq::RX_DONE | Irq::TIMEOUT | Irq::CRC_ERROR | Irq::HEADER_ERROR,
+                RfMode::Rx,
+            )
+            .await?;
+        if irq.contains(Irq::TIMEOUT) {
+            return Err(Error::Timeout);
+        }
+        if irq.intersects(Irq::CRC_ERROR | Irq::HEADER_ERROR) {
+            return Err(Error::Crc);
+        }
+
+        let mut status = [0; 2];
+        self.radio.read_command(GET_RX_BUFFER_STATUS, &mut status).await?;
+        let [len, offset] = status;
+        let len = len as usize;
+        let copied = len.min(buf.len());
+        self.radio.read_buffer(offset, &mut buf[..copied]).await?;
+
+        let mut packet = [0; 3];
+        self.radio.read_command(GET_PACKET_STATUS, &mut packet).await?;
+
+        Ok(RxPacket {
+            len,
+            rssi: -(packet[0] as i16) / 2,
+            snr: (packet[1] as i8) / 4,
+        })
+    }
+
+    /// Run a channel activity detection, and return whether LoRa activity was detected.
+    pub async fn channel_activity_detection(&mut self) -> Result<bool, Error> {
+        self.set_standby().await?;
+
+        // Detection peak recommended for each spreading factor, on 4 symbols.
+        let det_peak = 13 + self.config.spreading_factor as u8;
+        self.radio
+            .write_command(SET_CAD_PARAMS, &[0x02, det_peak, 10, 0x00, 0, 0, 0])
+            .await?;
+
+        let irq = self
+            .run(&[SET_CAD], Irq::CAD_DONE | Irq::CAD_DETECTED, RfMode::Rx)
+            .await?;
+        Ok(irq.contains(Irq::CAD_DETECTED))
+    }
+
+    /// Put the radio in sleep mode, keeping its configuration.
+    ///
+    /// The radio wakes up with the next command.
+    pub async fn sleep(&mut self) -> Result<(), Error> {
+        self.switch.set(RfMode::Off);
+        // Warm start, the configuration is retained.
+        self.radio.write_command(SET_SLEEP, &[0x04]).await
+    }
+
+    /// Put the radio in standby mode, on the RC oscillator.
+    pub async fn set_standby(&mut self) -> Result<(), Error> {
+        self.switch.set(RfMode::Off);
+        self.radio.write_command(SET_STANDBY, &[0x00]).await
+    }
+
+    async fn set_packet_params(&mut self, payload_len: u8, invert_iq: bool) -> Result<(), Error> {
+        let [p0, p1] = self.config.preamble_len.to_be_bytes();
+        // Explicit header.
+        self.radio
+            .write_command(
+                SET_PACKET_PARAMS,
+                &[p0, p1, 0x00, payload_len, self.config.crc as u8, invert_iq as u8],
+            )
+            .await?;
+
+        // Errata: bit 2 must be cleared with inverted IQ and set with standard IQ, or the
+        // receiver loses packets.
+        self.modify_register(REG_IQ_POLARITY, |v| if invert_iq { v & !0x04 } else { v | 0x04 })
+            .await
+    }
+
+    async fn modify_register(&mut self, address: u16, f: impl FnOnce(u8) -> u8) -> Result<(), Error> {
+        let mut value = [0];
+        self.radio.read_registers(address, &mut value).await?;
+        self.radio.write_registers(address, &[f(value[0])]).await
+    }
+
+    /// Route `irqs` to the radio IRQ line, start the operation with `command` (opcode and
+    /// parameters) and wait for one of them. The antenna switch is set to `mode` meanwhile.
+    async fn run(&mut self, command: &[u8], irqs: Irq, mode: RfMode) -> Result<Irq, Error> {
+        let [m0, m1] = irqs.0.to_be_bytes();
+        self.radio
+            .write_command(SET_DIO_IRQ_PARAMS, &[m0, m1, m0, m1, 0, 0, 0, 0])
+            .await?;
+        self.radio.clear_irq(Irq::ALL).await?;
+
+        self.switch.set(mode);
+        let mut guard = AbortOnDrop(&mut *self);
+        let result = async {
+            guard.0.radio.write_command(command[0], &command[1..]).await?;
+            loop {
+                let irq = guard.0.radio.wait_irq().await?;
+                if irq.intersects(irqs) {
+                    return Ok(irq);
+                }
+            }
+        }
+        .await;
+        // The radio is back in standby after the IRQs this driver waits for.
+        core::mem::forget(guard);
+        self.switch.set(RfMode::Off);
+        result
+    }
+}
+
+/// Puts the radio in standby and turns the antenna switch off when an operation is dropped
+/// before it completes.
+struct AbortOnDrop<'a, 'd, S: RfSwitch>(&'a mut LoRa<'d, S>);
+
+impl<'a, 'd, S: RfSwitch> Drop for AbortOnDrop<'a, 'd, S> {
+    fn drop(&mut self) {
+        if let Err(e) = self.0.radio.blocking_write_command(SET_STANDBY, &[0x00]) {
+            warn!("SUBGHZ: failed to abort the radio operation: {:?}", e);
+        }
+        self.0.switch.set(RfMode::Off);
+    }
+}
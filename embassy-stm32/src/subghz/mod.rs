@@ -0,0 +1,396 @@
+//! Sub-GHz radio (SUBGHZ)
+//!
+//! The STM32WL integrates an SX126x-compatible radio, connected to the MCU through the internal
+//! SUBGHZSPI bus. The radio NSS, reset and busy lines are controlled through the PWR and RCC
+//! registers instead of GPIOs, and the radio IRQ line is the `SUBGHZ_RADIO` interrupt.
+//!
+//! [`SubGhz`] provides the raw command interface: opcodes and parameters are described in the
+//! radio section of the reference manual (RM0453). [`lora::LoRa`] builds a LoRa modem on top of it.
+//!
+//! # Low-power
+//!
+//! The radio IRQ is a wakeup source of the MCU Stop modes, so waiting for a radio event doesn't
+//! keep the MCU awake with the `low-power` executor. Between operations, put the radio in sleep
+//! mode (for example with [`lora::LoRa::sleep`]): it keeps its configuration and draws about
+//! 1 µA, and wakes up with the next command.
+//!
+//! While the radio is busy, e.g. calibrating or waking up, the driver polls its BUSY line on the
+//! `embassy-time` timer with the `time` feature, and spins otherwise.
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::interrupt::typelevel::{Interrupt, SUBGHZ_RADIO};
+use crate::mode::Async;
+use crate::pac::{PWR, RCC};
+use crate::peripherals::SUBGHZSPI;
+use crate::spi::{self, RxDma, Spi, TxDma};
+use crate::{interrupt, Peri};
+
+pub mod lora;
+mod rf_switch;
+pub use rf_switch::*;
+
+static IRQ_WAKER: AtomicWaker = AtomicWaker::new();
+static IRQ_PENDING: AtomicBool = AtomicBool::new(false);
+
+const RADIO_IRQ_EXTI_LINE: usize = 44;
+
+/// Radio opcodes.
+pub mod opcode {
+    /// Clear IRQ status.
+    pub const CLR_IRQ_STATUS: u8 = 0x02;
+    /// Write registers.
+    pub const WRITE_REGISTER: u8 = 0x0D;
+    /// Write to the data buffer.
+    pub const WRITE_BUFFER: u8 = 0x0E;
+    /// Get IRQ status.
+    pub const GET_IRQ_STATUS: u8 = 0x12;
+    /// Read registers.
+    pub const READ_REGISTER: u8 = 0x1D;
+    /// Read from the data buffer.
+    pub const READ_BUFFER: u8 = 0x1E;
+    /// Get status.
+    pub const GET_STATUS: u8 = 0xC0;
+}
+
+/// SUBGHZ radio interrupt handler.
+pub struct InterruptHandler {
+    _private: (),
+}
+
+impl interrupt::typelevel::Handler<SUBGHZ_RADIO> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        // The radio IRQ line stays asserted until the IRQ status is cleared over SPI, mask the
+        // interrupt until the driver does so.
+        SUBGHZ_RADIO::disable();
+        IRQ_PENDING.store(true, Ordering::Release);
+        IRQ_WAKER.wake();
+    }
+}
+
+/// SUBGHZ error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// SUBGHZSPI error.
+    Spi(spi::Error),
+    /// The radio reported a command error, see [`Status::command_status`].
+    Command(Status),
+    /// The radio operation timed out.
+    Timeout,
+    /// A packet was received with a wrong CRC or header.
+    Crc,
+    /// The packet is longer than 255 bytes.
+    PacketTooLong,
+}
+
+impl From<spi::Error> for Error {
+    fn from(e: spi::Error) -> Self {
+        Self::Spi(e)
+    }
+}
+
+/// Radio operating mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChipMode {
+    /// Standby with the RC 13 MHz oscillator.
+    StandbyRc,
+    /// Standby with the HSE32 oscillator.
+    StandbyHse,
+    /// Frequency synthesis.
+    Fs,
+    /// Receiving.
+    Rx,
+    /// Transmitting.
+    Tx,
+    /// Reserved value.
+    Unknown(u8),
+}
+
+/// Status of the last command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CommandStatus {
+    /// Data is available to the host.
+    DataAvailable,
+    /// The command timed out.
+    Timeout,
+    /// The command could not be processed.
+    ProcessingError,
+    /// The command failed to execute.
+    ExecutionFailure,
+    /// The command was transmitted.
+    TxDone,
+    /// Reserved value.
+    Unknown(u8),
+}
+
+/// Radio status byte, returned by every read command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Status(pub u8);
+
+impl Status {
+    /// Operating mode.
+    pub fn chip_mode(&self) -> ChipMode {
+        match (self.0 >> 4) & 0x7 {
+            0x2 => ChipMode::StandbyRc,
+            0x3 => ChipMode::StandbyHse,
+            0x4 => ChipMode::Fs,
+            0x5 => ChipMode::Rx,
+            0x6 => ChipMode::Tx,
+            other => ChipMode::Unknown(other),
+        }
+    }
+
+    /// Status of the last command.
+    pub fn command_status(&self) -> CommandStatus {
+        match (self.0 >> 1) & 0x7 {
+            0x2 => CommandStatus::DataAvailable,
+            0x3 => CommandStatus::Timeout,
+            0x4 => CommandStatus::ProcessingError,
+            0x5 => CommandStatus::ExecutionFailure,
+            0x6 => CommandStatus::TxDone,
+            other => CommandStatus::Unknown(other),
+        }
+    }
+
+    fn check(self) -> Result<Self, Error> {
+        match self.command_status() {
+            CommandStatus::Timeout | CommandStatus::ProcessingError | CommandStatus::ExecutionFailure => {
+                Err(Error::Command(self))
+            }
+            _ => Ok(self),
+        }
+    }
+}
+
+/// Radio IRQ flags, as a bit set.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Irq(pub u16);
+
+impl Irq {
+    /// Packet transmission finished.
+    pub const TX_DONE: Self = Self(1 << 0);
+    /// Packet received.
+    pub const RX_DONE: Self = Self(1 << 1);
+    /// Preamble detected.
+    pub const PREAMBLE_DETECTED: Self = Self(1 << 2);
+    /// Synchronization word valid.
+    pub const SYNC_DETECTED: Self = Self(1 << 3);
+    /// LoRa header valid.
+    pub const HEADER_VALID: Self = Self(1 << 4);
+    /// LoRa header CRC error.
+    pub const HEADER_ERROR: Self = Self(1 << 5);
+    /// Packet CRC error.
+    pub const CRC_ERROR: Self = Self(1 << 6);
+    /// Channel activity detection finished.
+    pub const CAD_DONE: Self = Self(1 << 7);
+    /// Channel activity detected.
+    pub const CAD_DETECTED: Self = Self(1 << 8);
+    /// RX or TX timeout.
+    pub const TIMEOUT: Self = Self(1 << 9);
+    /// All IRQ flags.
+    pub const ALL: Self = Self(0x03FF);
+
+    /// Whether all flags of `other` are set.
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any flag of `other` is set.
+    pub fn intersects(&self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl core::ops::BitOr for Irq {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Sub-GHz radio driver.
+pub struct SubGhz<'d> {
+    spi: Spi<'d, Async>,
+}
+
+impl<'d> SubGhz<'d> {
+    /// Create a new sub-GHz radio driver, and reset the radio.
+    pub async fn new(
+        peri: Peri<'d, SUBGHZSPI>,
+        tx_dma: Peri<'d, impl TxDma<SUBGHZSPI>>,
+        rx_dma: Peri<'d, impl RxDma<SUBGHZSPI>>,
+        _irq: impl interrupt::typelevel::Binding<SUBGHZ_RADIO, InterruptHandler> + 'd,
+    ) -> Self {
+        let spi = Spi::new_subghz(peri, tx_dma, rx_dma);
+        set_nss(true);
+
+        IRQ_PENDING.store(false, Ordering::Relaxed);
+        SUBGHZ_RADIO::unpend();
+        // Let the radio IRQ wake the MCU up from Stop modes, on EXTI line 44.
+        crate::exti::cpu_regs()
+            .imr(1)
+            .modify(|w| w.set_line(RADIO_IRQ_EXTI_LINE - 32, true));
+
+        let mut this = Self { spi };
+        this.reset().await;
+        this
+    }
+
+    /// Reset the radio, and wait until it is ready.
+    pub async fn reset(&mut self) {
+        RCC.csr().modify(|w| w.set_rfrst(true));
+        RCC.csr().modify(|w| w.set_rfrst(false));
+        wait_while(|| RCC.csr().read().rfrstf()).await;
+        wait_not_busy().await;
+    }
+
+    /// Wake the radio up from sleep mode, and wait until it is ready.
+    ///
+    /// Any command also wakes the radio up, this only avoids waiting in the next command.
+    pub async fn wakeup(&mut self) {
+        set_nss(false);
+        // NSS must stay low for at least 20 µs, the radio is busy until then.
+        crate::delay::blocking_delay_us(20);
+        set_nss(true);
+        wait_not_busy().await;
+    }
+
+    /// Write a command with its parameters.
+    pub async fn write_command(&mut self, opcode: u8, params: &[u8]) -> Result<(), Error> {
+        self.write(&[opcode], params).await
+    }
+
+    /// Write a command, then read the status and `response`.
+    pub async fn read_command(&mut self, opcode: u8, response: &mut [u8]) -> Result<Status, Error> {
+        self.read(&[opcode], response).await
+    }
+
+    /// Get the radio status.
+    pub async fn status(&mut self) -> Result<Status, Error> {
+        self.read_command(opcode::GET_STATUS, &mut []).await
+    }
+
+    /// Write radio registers, starting at `address`.
+    pub async fn write_registers(&mut self, address: u16, data: &[u8]) -> Result<(), Error> {
+        let [hi, lo] = address.to_be_bytes();
+        self.write(&[opcode::WRITE_REGISTER, hi, lo], data).await
+    }
+
+    /// Read radio registers, starting at `address`.
+    pub async fn read_registers(&mut self, address: u16, data: &mut [u8]) -> Result<(), Error> {
+        let [hi, lo] = address.to_be_bytes();
+        self.read(&[opcode::READ_REGISTER, hi, lo], data).await?;
+        Ok(())
+    }
+
+    /// Write to the radio data buffer, starting at `offset`.
+    pub async fn write_buffer(&mut self, offset: u8, data: &[u8]) -> Result<(), Error> {
+        self.write(&[opcode::WRITE_BUFFER, offset], data).await
+    }
+
+    /// Read from the radio data buffer, starting at `offset`.
+    pub async fn read_buffer(&mut self, offset: u8, data: &mut [u8]) -> Result<(), Error> {
+        self.read(&[opcode::READ_BUFFER, offset], data).await?;
+        Ok(())
+    }
+
+    /// Read the radio IRQ flags.
+    pub async fn irq_status(&mut self) -> Result<Irq, Error> {
+        let mut buf = [0; 2];
+        self.read_command(opcode::GET_IRQ_STATUS, &mut buf).await?;
+        Ok(Irq(u16::from_be_bytes(buf)))
+    }
+
+    /// Clear radio IRQ flags.
+    pub async fn clear_irq(&mut self, irq: Irq) -> Result<(), Error> {
+        self.write_command(opcode::CLR_IRQ_STATUS, &irq.0.to_be_bytes()).await
+    }
+
+    /// Wait for the radio IRQ line, then read and clear the IRQ flags.
+    ///
+    /// Which flags drive the IRQ line is configured with the `SetDioIrqParams` command.
+    pub async fn wait_irq(&mut self) -> Result<Irq, Error> {
+        let on_drop = OnDrop::new(|| SUBGHZ_RADIO::disable());
+        poll_fn(|cx| {
+            IRQ_WAKER.register(cx.waker());
+            if IRQ_PENDING.swap(false, Ordering::Acquire) {
+                Poll::Ready(())
+            } else {
+                unsafe { SUBGHZ_RADIO::enable() };
+                Poll::Pending
+            }
+        })
+        .await;
+        on_drop.defuse();
+
+        let irq = self.irq_status().await?;
+        self.clear_irq(irq).await?;
+        Ok(irq)
+    }
+
+    async fn write(&mut self, header: &[u8], data: &[u8]) -> Result<(), Error> {
+        wait_not_busy().await;
+
+        set_nss(false);
+        let _nss = OnDrop::new(|| set_nss(true));
+        self.spi.write(header).await?;
+        self.spi.write(data).await?;
+        Ok(())
+    }
+
+    async fn read(&mut self, header: &[u8], data: &mut [u8]) -> Result<Status, Error> {
+        wait_not_busy().await;
+
+        let mut status = [0; 1];
+        {
+            set_nss(false);
+            let _nss = OnDrop::new(|| set_nss(true));
+            self.spi.write(header).await?;
+            self.spi.read(&mut status).await?;
+            self.spi.read(data).await?;
+        }
+        Status(status[0]).check()
+    }
+
+    /// Write a command with its parameters without DMA, for use in drop handlers.
+    fn blocking_write_command(&mut self, opcode: u8, params: &[u8]) -> Result<(), Error> {
+        while PWR.sr2().read().rfbusys() {}
+
+        set_nss(false);
+        let _nss = OnDrop::new(|| set_nss(true));
+        self.spi.blocking_write(&[opcode])?;
+        self.spi.blocking_write(params)?;
+        Ok(())
+    }
+}
+
+/// Drive the radio NSS line.
+fn set_nss(high: bool) {
+    PWR.subghzspicr().modify(|w| w.set_nss(high));
+}
+
+/// Wait until the radio is ready to accept a command.
+async fn wait_not_busy() {
+    wait_while(|| PWR.sr2().read().rfbusys()).await
+}
+
+/// Wait while `busy` returns `true`.
+///
+/// With the `time` feature, the task sleeps between polls so that the executor can enter Stop
+/// modes, e.g. during the milliseconds of a calibration. Otherwise this spins.
+async fn wait_while(busy: impl Fn() -> bool) {
+    while busy() {
+        #[cfg(feature = "time")]
+        embassy_time::Timer::after_micros(100).await;
+    }
+}
@@ -0,0 +1,91 @@
+use super::lora::PowerAmplifier;
+use crate::gpio::{Level, Output};
+
+/// RF path to select on the antenna switch.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RfMode {
+    /// Radio idle, antenna disconnected.
+    Off,
+    /// Receive path.
+    Rx,
+    /// Transmit path of the given power amplifier.
+    Tx(PowerAmplifier),
+}
+
+/// Antenna switch, set by the radio drivers around each operation.
+pub trait RfSwitch {
+    /// Select the RF path for `mode`.
+    fn set(&mut self, mode: RfMode);
+}
+
+/// No antenna switch, or one driven by the radio itself.
+impl RfSwitch for () {
+    fn set(&mut self, _mode: RfMode) {}
+}
+
+/// Levels of the antenna switch control pins for each [`RfMode`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RfSwitchLevels<const N: usize> {
+    /// Levels for [`RfMode::Off`].
+    pub off: [bool; N],
+    /// Levels for [`RfMode::Rx`].
+    pub rx: [bool; N],
+    /// Levels for [`RfMode::Tx`] with the low-power PA.
+    pub tx_low_power: [bool; N],
+    /// Levels for [`RfMode::Tx`] with the high-power PA.
+    pub tx_high_power: [bool; N],
+}
+
+impl RfSwitchLevels<3> {
+    /// NUCLEO-WL55JC, with FE_CTRL1 on PC4, FE_CTRL2 on PC5 and FE_CTRL3 on PC3.
+    pub const NUCLEO_WL55: Self = Self {
+        off: [false, false, false],
+        rx: [true, false, true],
+        tx_low_power: [true, true, true],
+        tx_high_power: [false, true, true],
+    };
+}
+
+impl RfSwitchLevels<2> {
+    /// Seeed LoRa-E5 module, with RF_CTRL1 on PA4 and RF_CTRL2 on PA5. Only the high-power PA is
+    /// connected.
+    pub const LORA_E5: Self = Self {
+        off: [false, false],
+        rx: [true, false],
+        tx_low_power: [false, true],
+        tx_high_power: [false, true],
+    };
+}
+
+/// Antenna switch controlled by GPIOs.
+pub struct GpioRfSwitch<'d, const N: usize> {
+    pins: [Output<'d>; N],
+    levels: RfSwitchLevels<N>,
+}
+
+impl<'d, const N: usize> GpioRfSwitch<'d, N> {
+    /// Create a new antenna switch from its control `pins`, in the order of `levels`.
+    ///
+    /// The switch starts in [`RfMode::Off`].
+    pub fn new(pins: [Output<'d>; N], levels: RfSwitchLevels<N>) -> Self {
+        let mut this = Self { pins, levels };
+        this.set(RfMode::Off);
+        this
+    }
+}
+
+impl<'d, const N: usize> RfSwitch for GpioRfSwitch<'d, N> {
+    fn set(&mut self, mode: RfMode) {
+        let levels = match mode {
+            RfMode::Off => self.levels.off,
+            RfMode::Rx => self.levels.rx,
+            RfMode::Tx(PowerAmplifier::LowPower) => self.levels.tx_low_power,
+            RfMode::Tx(PowerAmplifier::HighPower) => self.levels.tx_high_power,
+        };
+        for (pin, level) in self.pins.iter_mut().zip(levels) {
+            pin.set_level(Level::from(level));
+        }
+    }
+}
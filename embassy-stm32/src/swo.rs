@@ -0,0 +1,138 @@
+//! Serial wire output (SWO) trace
+//!
+//! Sets up the ITM and the TPIU to send trace output on the TRACESWO pin, in asynchronous NRZ
+//! mode. The TPIU prescaler is derived from the current HCLK frequency, so it must be set up again
+//! after changing the clocks.
+//!
+//! ```rust,ignore
+//! let mut cp = cortex_m::Peripherals::take().unwrap();
+//! let mut swo = Swo::new(p.PB3, cp.ITM, &mut cp.TPIU, &mut cp.DCB, Hertz(2_000_000)).unwrap();
+//! core::fmt::write(&mut swo, format_args!("hello {}\n", 42)).unwrap();
+//! ```
+use core::fmt;
+
+use cortex_m::peripheral::{DCB, ITM, TPIU};
+
+use crate::gpio::{AfType, AnyPin, OutputType, SealedPin as _, Speed};
+use crate::time::Hertz;
+use crate::Peri;
+
+/// ITM_TCR bits.
+const ITM_TCR_ITMENA: u32 = 1 << 0;
+const ITM_TCR_SYNCENA: u32 = 1 << 2;
+const ITM_TCR_TRACE_BUS_ID: u32 = 1 << 16;
+/// ITM lock access key.
+const ITM_LAR_KEY: u32 = 0xC5AC_CE55;
+
+/// TPIU_SPPR asynchronous NRZ (UART) protocol.
+const TPIU_SPPR_NRZ: u32 = 0b10;
+/// TPIU_FFCR with the continuous formatter disabled.
+const TPIU_FFCR_BYPASS: u32 = 1 << 8;
+/// Maximum value of TPIU_ACPR.
+const TPIU_ACPR_MAX: u32 = 0x1FFF;
+
+/// SWO configuration error.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The baudrate is too low for the current HCLK frequency.
+    BaudrateTooLow,
+    /// The baudrate is higher than the HCLK frequency.
+    BaudrateTooHigh,
+}
+
+/// SWO trace output on ITM stimulus port 0.
+///
+/// Implements [`fmt::Write`], so it can be used with `write!` and `core::fmt::write`.
+pub struct Swo<'d> {
+    itm: ITM,
+    baudrate: Hertz,
+    _pin: Peri<'d, AnyPin>,
+}
+
+impl<'d> Swo<'d> {
+    /// Enable SWO trace output at `baudrate`, rounded down to an integer divider of HCLK.
+    pub fn new(
+        pin: Peri<'d, impl SwoPin>,
+        mut itm: ITM,
+        tpiu: &mut TPIU,
+        dcb: &mut DCB,
+        baudrate: Hertz,
+    ) -> Result<Self, ConfigError> {
+        let hclk = unsafe { crate::rcc::get_freqs() }.hclk1.to_hertz().unwrap();
+        if baudrate.0 > hclk.0 {
+            return Err(ConfigError::BaudrateTooHigh);
+        }
+        let prescaler = hclk.0.div_ceil(baudrate.0) - 1;
+        if prescaler > TPIU_ACPR_MAX {
+            return Err(ConfigError::BaudrateTooLow);
+        }
+
+        crate::pac::DBGMCU.cr().modify(|w| {
+            w.set_trace_ioen(true);
+            // Asynchronous trace.
+            w.set_trace_mode(0b00);
+        });
+        pin.set_as_af(0, AfType::output(OutputType::PushPull, Speed::VeryHigh));
+
+        dcb.enable_trace();
+        unsafe {
+            tpiu.sppr.write(TPIU_SPPR_NRZ);
+            tpiu.acpr.write(prescaler);
+            tpiu.ffcr.write(TPIU_FFCR_BYPASS);
+
+            itm.lar.write(ITM_LAR_KEY);
+            itm.tcr.write(ITM_TCR_ITMENA | ITM_TCR_SYNCENA | ITM_TCR_TRACE_BUS_ID);
+            itm.tpr.write(0);
+            itm.ter[0].write(1);
+        }
+
+        let baudrate = hclk / (prescaler + 1);
+        trace!("SWO at {} Hz", baudrate.0);
+
+        Ok(Self {
+            itm,
+            baudrate,
+            _pin: pin.into(),
+        })
+    }
+
+    /// Actual baudrate.
+    pub fn baudrate(&self) -> Hertz {
+        self.baudrate
+    }
+
+    /// Write `s` to ITM stimulus port 0, blocking while the port is busy.
+    pub fn itm_print(&mut self, s: &str) {
+        cortex_m::itm::write_str(&mut self.itm.stim[0], s);
+    }
+
+    /// Get the ITM, to write to other stimulus ports.
+    pub fn itm(&mut self) -> &mut ITM {
+        &mut self.itm
+    }
+}
+
+impl<'d> fmt::Write for Swo<'d> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.itm_print(s);
+        Ok(())
+    }
+}
+
+impl<'d> Drop for Swo<'d> {
+    fn drop(&mut self) {
+        unsafe { self.itm.ter[0].write(0) };
+        crate::pac::DBGMCU.cr().modify(|w| w.set_trace_ioen(false));
+        self._pin.set_as_disconnected();
+    }
+}
+
+trait SealedSwoPin: crate::gpio::Pin {}
+
+/// TRACESWO pin.
+#[allow(private_bounds)]
+pub trait SwoPin: SealedSwoPin {}
+
+impl SealedSwoPin for crate::peripherals::PB3 {}
+impl SwoPin for crate::peripherals::PB3 {}
@@ -231,18 +231,10 @@ impl RtcDriver {
 
         rcc::enable_and_reset_with_cs::<T>(cs);
 
-        let timer_freq = T::frequency();
-
         r.cr1().modify(|w| w.set_cen(false));
         r.cnt().write(|w| w.set_cnt(0));
 
-        let psc = timer_freq.0 / TICK_HZ as u32 - 1;
-        let psc: u16 = match psc.try_into() {
-            Err(_) => panic!("psc division overflow: {}", psc),
-            Ok(n) => n,
-        };
-
-        r.psc().write_value(psc);
+        r.psc().write_value(Self::prescaler());
         r.arr().write(|w| w.set_arr(u16::MAX));
 
         // Set URS, generate update and clear URS
@@ -265,6 +257,42 @@ impl RtcDriver {
         r.cr1().modify(|w| w.set_cen(true));
     }
 
+    fn prescaler() -> u16 {
+        let timer_freq = T::frequency();
+
+        let psc = timer_freq.0 / TICK_HZ as u32 - 1;
+        match psc.try_into() {
+            Err(_) => panic!("psc division overflow: {}", psc),
+            Ok(n) => n,
+        }
+    }
+
+    /// Update the prescaler after the timer clock frequency changed, keeping the current time.
+    ///
+    /// Loading the new prescaler resets the prescaler counter, dropping the part of the current
+    /// tick that already elapsed. To keep that loss small, the prescaler is loaded right after
+    /// a tick: the time lost per call is the few timer clock cycles between the tick and the
+    /// update, well below one tick. Waiting for the tick takes up to one tick.
+    fn reconfigure(&'static self, _cs: critical_section::CriticalSection) {
+        let r = regs_gp16();
+
+        r.psc().write_value(Self::prescaler());
+
+        let start = r.cnt().read().cnt();
+        let cnt = loop {
+            let cnt = r.cnt().read().cnt();
+            if cnt != start {
+                break cnt;
+            }
+        };
+
+        // Load the new prescaler right away. This clears the counter, so restore it after.
+        r.cr1().modify(|w| w.set_urs(vals::Urs::COUNTER_ONLY));
+        r.egr().write(|w| w.set_ug(true));
+        r.cr1().modify(|w| w.set_urs(vals::Urs::ANY_EVENT));
+        r.cnt().write(|w| w.set_cnt(cnt));
+    }
+
     fn on_interrupt(&self) {
         let r = regs_gp16();
 
@@ -521,3 +549,7 @@ pub(crate) fn get_driver() -> &'static RtcDriver {
 pub(crate) fn init(cs: CriticalSection) {
     DRIVER.init(cs)
 }
+
+pub(crate) fn reconfigure(cs: CriticalSection) {
+    DRIVER.reconfigure(cs)
+}
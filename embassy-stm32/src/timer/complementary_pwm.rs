@@ -92,6 +92,11 @@ impl<'d, T: AdvancedInstance4Channel> ComplementaryPwm<'d, T> {
         this
     }
 
+    /// Get access to the advanced timer registers.
+    pub(crate) fn regs_advanced(&self) -> crate::pac::timer::TimAdv {
+        self.inner.regs_advanced()
+    }
+
     /// Enable the given channel.
     pub fn enable(&mut self, channel: Channel) {
         self.inner.enable_channel(channel, true);
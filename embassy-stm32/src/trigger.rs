@@ -0,0 +1,201 @@
+//! Internal trigger interconnect
+//!
+//! Typed names for the hardware trigger sources of the ADC, DAC and LPTIM, replacing the
+//! `EXTSEL`/`TRIGSEL` numbers of the reference manual. A typical hardware-timed sampling chain:
+//!
+//! ```rust,ignore
+//! let tim = Timer::new(p.TIM2);
+//! tim.set_frequency(Hertz(10_000));
+//! trigger::set_trgo(&tim, TrgoSource::Update);
+//! tim.start();
+//!
+//! adc.set_regular_trigger(Some((AdcTrigger::Tim2Trgo, Edge::Rising)));
+//! let sample = adc.blocking_read(&mut pin); // converted on the next TIM2 update
+//! ```
+#[cfg(dac)]
+pub use crate::dac::TriggerSel as DacTrigger;
+use crate::pac::timer::vals::Mms;
+use crate::timer::low_level::Timer;
+use crate::timer::BasicInstance;
+
+/// Active edge of a trigger input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Edge {
+    /// Rising edge.
+    Rising = 1,
+    /// Falling edge.
+    Falling = 2,
+    /// Both edges.
+    Both = 3,
+}
+
+/// Event output on a timer's TRGO.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TrgoSource {
+    /// The `UG` bit, or a reset from the slave mode controller.
+    Reset,
+    /// The counter enable.
+    Enable,
+    /// The update event.
+    Update,
+    /// A capture or compare match on channel 1.
+    ComparePulse,
+    /// OC1REF, OC2REF, OC3REF or OC4REF, for channel 1 to 4.
+    CompareRef(u8),
+}
+
+/// Select the event that `timer` outputs on its TRGO.
+pub fn set_trgo<T: BasicInstance>(timer: &Timer<'_, T>, source: TrgoSource) {
+    let mms = match source {
+        TrgoSource::Reset => 0,
+        TrgoSource::Enable => 1,
+        TrgoSource::Update => 2,
+        TrgoSource::ComparePulse => 3,
+        TrgoSource::CompareRef(channel) => {
+            assert!((1..=4).contains(&channel));
+            3 + channel
+        }
+    };
+    timer.regs_basic().cr2().modify(|w| w.set_mms(Mms::from_bits(mms)));
+}
+
+/// Trigger of the ADC regular group (`EXTSEL`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+#[repr(u8)]
+pub enum AdcTrigger {
+    Tim1Ch1 = 0,
+    Tim1Ch2 = 1,
+    Tim1Ch3 = 2,
+    Tim2Ch2 = 3,
+    #[cfg(peri_tim3)]
+    Tim3Trgo = 4,
+    #[cfg(peri_tim4)]
+    Tim4Ch4 = 5,
+    Exti11 = 6,
+    #[cfg(peri_tim8)]
+    Tim8Trgo = 7,
+    #[cfg(peri_tim8)]
+    Tim8Trgo2 = 8,
+    Tim1Trgo = 9,
+    Tim1Trgo2 = 10,
+    Tim2Trgo = 11,
+    #[cfg(peri_tim4)]
+    Tim4Trgo = 12,
+    #[cfg(peri_tim6)]
+    Tim6Trgo = 13,
+    #[cfg(peri_tim15)]
+    Tim15Trgo = 14,
+    #[cfg(peri_tim3)]
+    Tim3Ch4 = 15,
+}
+
+/// Trigger of the ADC injected group (`JEXTSEL`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+#[repr(u8)]
+pub enum AdcInjectedTrigger {
+    Tim1Trgo = 0,
+    Tim1Ch4 = 1,
+    Tim2Trgo = 2,
+    Tim2Ch1 = 3,
+    #[cfg(peri_tim3)]
+    Tim3Ch4 = 4,
+    #[cfg(peri_tim4)]
+    Tim4Trgo = 5,
+    Exti15 = 6,
+    #[cfg(peri_tim8)]
+    Tim8Ch4 = 7,
+    Tim1Trgo2 = 8,
+    #[cfg(peri_tim8)]
+    Tim8Trgo = 9,
+    #[cfg(peri_tim8)]
+    Tim8Trgo2 = 10,
+    #[cfg(peri_tim3)]
+    Tim3Ch3 = 11,
+    #[cfg(peri_tim3)]
+    Tim3Trgo = 12,
+    #[cfg(peri_tim3)]
+    Tim3Ch1 = 13,
+    #[cfg(peri_tim6)]
+    Tim6Trgo = 14,
+    #[cfg(peri_tim15)]
+    Tim15Trgo = 15,
+}
+
+impl AdcInjectedTrigger {
+    /// Injected group trigger on `edge`, for [`crate::adc::Adc::configure_injected`].
+    pub fn on(self, edge: Edge) -> crate::adc::InjectedTrigger {
+        crate::adc::InjectedTrigger {
+            source: self,
+            edge: crate::adc::Exten::from_bits(edge as u8),
+        }
+    }
+}
+
+/// Trigger of an LPTIM (`TRIGSEL`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+#[repr(u8)]
+pub enum LptimTrigger {
+    /// The LPTIM_ETR pin.
+    Etr = 0,
+    RtcAlarmA = 1,
+    RtcAlarmB = 2,
+    RtcTamp1 = 3,
+    RtcTamp2 = 4,
+    RtcTamp3 = 5,
+    #[cfg(peri_comp1)]
+    Comp1 = 6,
+    #[cfg(peri_comp2)]
+    Comp2 = 7,
+}
+
+/// Start `timer` on a trigger edge instead of by software, or clear the trigger with `None`.
+///
+/// The timer must be disabled while its trigger is changed.
+#[cfg(lptim)]
+pub fn set_lptim_trigger<T: crate::lptim::Instance>(
+    _timer: &crate::lptim::timer::Timer<'_, T>,
+    trigger: Option<(LptimTrigger, Edge)>,
+) {
+    use crate::pac::lptim::vals::Trigen;
+
+    // `TRIGEN` 0 starts the timer by software.
+    let (trigsel, trigen) = match trigger {
+        Some((trigger, edge)) => (trigger as u8, edge as u8),
+        None => (0, 0),
+    };
+    T::regs().cfgr().modify(|w| {
+        w.set_trigsel(trigsel);
+        w.set_trigen(Trigen::from_bits(trigen));
+    });
+}
+
+/// Source of TIM2 input capture channel 4 (`TI4_RMP`).
+#[cfg(all(peri_tim2, peri_comp1))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Tim2Ch4Input {
+    /// The TIM2_CH4 pin.
+    Pin = 0,
+    /// COMP1 output.
+    Comp1 = 1,
+    /// COMP2 output.
+    #[cfg(peri_comp2)]
+    Comp2 = 2,
+    /// COMP1 and COMP2 outputs, ORed.
+    #[cfg(peri_comp2)]
+    Comp1OrComp2 = 3,
+}
+
+/// Route a comparator output to TIM2 input capture channel 4, e.g. to measure its frequency.
+#[cfg(all(peri_tim2, peri_comp1))]
+pub fn set_tim2_ch4_input(_timer: &Timer<'_, crate::peripherals::TIM2>, input: Tim2Ch4Input) {
+    crate::pac::TIM2.or1().modify(|w| w.set_ti4_rmp(input as u8));
+}
@@ -101,6 +101,7 @@ impl Into<u8> for PGPrescalerDivider {
 
 /// Error type for SSDeviation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SSDeviationError {
     /// The provided value is too low (0)
     ValueTooLow,
@@ -1,5 +1,6 @@
 /// Represents errors that can occur when configuring or validating TSC pin groups.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum GroupError {
     /// Error when a group has no sampling capacitor
     NoSamplingCapacitor,
@@ -13,6 +14,7 @@ pub enum GroupError {
 
 /// Error returned when attempting to set an invalid channel pin as active in the TSC.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum AcquisitionBankError {
     /// Indicates that one or more of the provided pins is not a valid channel pin.
     InvalidChannelPin,
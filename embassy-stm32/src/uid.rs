@@ -29,3 +29,143 @@ pub fn uid_hex_bytes() -> &'static [u8; 24] {
     });
     unsafe { &*core::ptr::addr_of!(UID_HEX) }
 }
+
+#[cfg(any(stm32l4, stm32wb))]
+mod info {
+    /// Flash size register, in KiB.
+    pub const FLASH_SIZE: *const u16 = 0x1FFF_75E0 as _;
+    /// Package data register.
+    pub const PACKAGE: *const u32 = 0x1FFF_7500 as _;
+}
+
+/// Get the size of the flash memory, in KiB, as reported by the device.
+#[cfg(any(stm32l4, stm32wb))]
+pub fn flash_size_kb() -> u16 {
+    unsafe { info::FLASH_SIZE.read_volatile() }
+}
+
+/// Get the package code of this device.
+///
+/// See the "Package data register" section of the reference manual for the meaning of the values.
+#[cfg(any(stm32l4, stm32wb))]
+pub fn package() -> u8 {
+    unsafe { (info::PACKAGE.read_volatile() & 0x1F) as u8 }
+}
+
+/// Get a stable 64-bit extended unique identifier (EUI-64) for this device.
+///
+/// On STM32WB, this is the factory-programmed 64-bit UID, which embeds ST's IEEE company ID.
+/// On other chips, it is derived from the 96-bit unique ID and marked as locally administered.
+pub fn eui64() -> [u8; 8] {
+    #[cfg(stm32wb)]
+    {
+        // UID64: UDN (32 bits), then device ID (8 bits) and company ID (24 bits).
+        const UID64: *const u32 = 0x1FFF_7580 as _;
+        let udn = unsafe { UID64.read_volatile() };
+        let id = unsafe { UID64.add(1).read_volatile() };
+        let mut eui = [0; 8];
+        eui[..4].copy_from_slice(&id.to_be_bytes());
+        eui[4..].copy_from_slice(&udn.to_be_bytes());
+        eui
+    }
+
+    #[cfg(not(stm32wb))]
+    {
+        eui64_from_uid(uid())
+    }
+}
+
+/// Fold the 96-bit unique ID into a locally administered, unicast EUI-64.
+#[cfg_attr(stm32wb, allow(unused))]
+fn eui64_from_uid(uid: &[u8; 12]) -> [u8; 8] {
+    let mut eui = [0; 8];
+    for (i, b) in uid.iter().enumerate() {
+        eui[i % 8] ^= b;
+    }
+    // Locally administered, unicast.
+    eui[0] = (eui[0] | 0x02) & !0x01;
+    eui
+}
+
+/// Get a BLE static random device address for this device, derived from the 96-bit unique ID.
+///
+/// The address is stable across resets, as required for static addresses. It is returned least
+/// significant byte first, as expected by HCI commands.
+pub fn ble_static_random_address() -> [u8; 6] {
+    ble_static_random_address_from_uid(uid())
+}
+
+fn ble_static_random_address_from_uid(uid: &[u8; 12]) -> [u8; 6] {
+    let mut addr = [0; 6];
+    for (i, b) in uid.iter().enumerate() {
+        addr[i % 6] ^= b;
+    }
+    // The two most significant bits of a static address are set.
+    addr[5] |= 0xC0;
+    // The 46-bit random part must be neither all zeros nor all ones.
+    if addr[..5] == [0; 5] && addr[5] & 0x3F == 0 {
+        addr[0] = 0x01;
+    } else if addr == [0xFF; 6] {
+        addr[0] = 0xFE;
+    }
+    addr
+}
+
+/// Get a serial number string for this device, made of the 16 hexadecimal ASCII digits of [`eui64`].
+///
+/// This is shorter than [`uid_hex`], which makes it convenient for BLE advertising data or USB
+/// serial number descriptors.
+pub fn serial_number() -> &'static str {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    static mut SERIAL: [u8; 16] = [0; 16];
+    static mut LOADED: bool = false;
+    critical_section::with(|_| unsafe {
+        if !LOADED {
+            for (idx, v) in eui64().iter().enumerate() {
+                SERIAL[idx * 2] = HEX[(v >> 4) as usize];
+                SERIAL[idx * 2 + 1] = HEX[(v & 0x0f) as usize];
+            }
+            LOADED = true;
+        }
+    });
+    unsafe { core::str::from_utf8_unchecked(&*core::ptr::addr_of!(SERIAL)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_derive_eui64() {
+        let uid = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x10, 0x20, 0x30, 0x40];
+        assert_eq!([0x12, 0x22, 0x33, 0x44, 0x05, 0x06, 0x07, 0x08], eui64_from_uid(&uid));
+        // Locally administered, unicast.
+        assert_eq!(0x02, eui64_from_uid(&[0xFF; 12])[0] & 0x03);
+    }
+
+    #[test]
+    fn can_derive_ble_static_random_address() {
+        let uid = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x10, 0x20, 0x30, 0x40, 0x50, 0x20];
+        assert_eq!(
+            [0x11, 0x22, 0x33, 0x44, 0x55, 0xE6],
+            ble_static_random_address_from_uid(&uid)
+        );
+    }
+
+    #[test]
+    fn ble_static_random_address_is_never_all_zeros_or_ones() {
+        // Random part all zeros.
+        assert_eq!([0x01, 0, 0, 0, 0, 0xC0], ble_static_random_address_from_uid(&[0; 12]));
+        let mut uid = [0; 12];
+        uid[5] = 0xC0;
+        assert_eq!([0x01, 0, 0, 0, 0, 0xC0], ble_static_random_address_from_uid(&uid));
+
+        // Random part all ones.
+        let mut uid = [0; 12];
+        uid[..6].copy_from_slice(&[0xFF; 6]);
+        assert_eq!(
+            [0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+            ble_static_random_address_from_uid(&uid)
+        );
+    }
+}
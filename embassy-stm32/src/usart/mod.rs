@@ -4,7 +4,7 @@
 
 use core::future::poll_fn;
 use core::marker::PhantomData;
-use core::sync::atomic::{compiler_fence, AtomicU8, Ordering};
+use core::sync::atomic::{compiler_fence, AtomicBool, AtomicU8, Ordering};
 use core::task::Poll;
 
 use embassy_embedded_hal::SetConfig;
@@ -189,6 +189,7 @@ pub enum ConfigError {
 
 #[non_exhaustive]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Config
 pub struct Config {
     /// Baud rate
@@ -534,6 +535,7 @@ impl<'d, M: Mode> UartTx<'d, M> {
         let info = self.info;
         let state = self.state;
         state.tx_rx_refcount.store(1, Ordering::Relaxed);
+        state.suspended.store(false, Ordering::Relaxed);
 
         info.rcc.enable_and_reset();
 
@@ -577,6 +579,84 @@ impl<'d, M: Mode> UartTx<'d, M> {
     pub fn set_baudrate(&self, baudrate: u32) -> Result<(), ConfigError> {
         set_baudrate(self.info, self.kernel_clock, baudrate)
     }
+
+    /// Wait for the transmission to complete, then gate the peripheral clock.
+    ///
+    /// This also stops the receiving half, if the UART was split. See [`Uart::suspend`].
+    pub fn suspend(&mut self) {
+        if !self.state.suspended.load(Ordering::Relaxed) {
+            unwrap!(blocking_flush(self.info));
+            self.state.suspended.store(true, Ordering::Relaxed);
+            self.info.rcc.disable();
+        }
+    }
+
+    /// Ungate the peripheral clock after [`suspend`](Self::suspend).
+    pub fn resume(&mut self) {
+        if self.state.suspended.load(Ordering::Relaxed) {
+            self.state.suspended.store(false, Ordering::Relaxed);
+            self.info.rcc.enable();
+        }
+    }
+}
+
+#[cfg(feature = "defmt-buffer")]
+impl<'d> UartTx<'d, Async> {
+    /// Handle to write from contexts that can't borrow the driver, like a logger flush.
+    pub(crate) fn handle(&self) -> TxHandle {
+        TxHandle {
+            info: self.info,
+            state: self.state,
+        }
+    }
+}
+
+/// Transmitter of a [`UartTx`], see [`UartTx::handle`].
+#[cfg(feature = "defmt-buffer")]
+#[derive(Clone, Copy)]
+pub(crate) struct TxHandle {
+    info: &'static Info,
+    state: &'static State,
+}
+
+#[cfg(feature = "defmt-buffer")]
+impl TxHandle {
+    /// Clear the transmission complete flag, before starting a write.
+    pub(crate) fn clear_tc(&self) {
+        let r = self.info.regs;
+        #[cfg(any(usart_v1, usart_v2))]
+        r.sr().modify(|w| w.set_tc(false));
+        #[cfg(any(usart_v3, usart_v4))]
+        {
+            let mut sr = regs::Isr(0);
+            sr.set_tc(true);
+            clear_interrupt_flags(r, sr);
+        }
+    }
+
+    /// Wait until the current transmission is complete.
+    pub(crate) fn blocking_flush(&self) {
+        unwrap!(blocking_flush(self.info));
+    }
+
+    /// Write `bytes` without DMA, ungating the clock of a suspended UART meanwhile.
+    pub(crate) fn blocking_write(&self, bytes: impl Iterator<Item = u8>) {
+        let suspended = self.state.suspended.load(Ordering::Relaxed);
+        if suspended {
+            self.info.rcc.enable();
+        }
+
+        let r = self.info.regs;
+        for b in bytes {
+            while !sr(r).read().txe() {}
+            unsafe { tdr(r).write_volatile(b) };
+        }
+
+        if suspended {
+            self.blocking_flush();
+            self.info.rcc.disable();
+        }
+    }
 }
 
 /// Wait until transmission complete
@@ -952,6 +1032,7 @@ impl<'d, M: Mode> UartRx<'d, M> {
         let info = self.info;
         let state = self.state;
         state.tx_rx_refcount.store(1, Ordering::Relaxed);
+        state.suspended.store(false, Ordering::Relaxed);
 
         info.rcc.enable_and_reset();
 
@@ -1088,7 +1169,7 @@ fn drop_tx_rx(info: &Info, state: &State) {
         state.tx_rx_refcount.store(refcount - 1, Ordering::Relaxed);
         refcount == 1
     });
-    if is_last_drop {
+    if is_last_drop && !state.suspended.load(Ordering::Relaxed) {
         info.rcc.disable();
     }
 }
@@ -1448,6 +1529,7 @@ impl<'d, M: Mode> Uart<'d, M> {
         let info = self.rx.info;
         let state = self.rx.state;
         state.tx_rx_refcount.store(2, Ordering::Relaxed);
+        state.suspended.store(false, Ordering::Relaxed);
 
         info.rcc.enable_and_reset();
 
@@ -1510,6 +1592,20 @@ impl<'d, M: Mode> Uart<'d, M> {
         self.rx.set_baudrate(baudrate)?;
         Ok(())
     }
+
+    /// Wait for the transmission to complete, then gate the peripheral clock.
+    ///
+    /// The configuration is kept, and the UART no longer keeps the chip out of the low-power stop
+    /// modes. Nothing is received until it is [resumed](Self::resume). Does nothing if the UART
+    /// is already suspended.
+    pub fn suspend(&mut self) {
+        self.tx.suspend();
+    }
+
+    /// Ungate the peripheral clock after [`suspend`](Self::suspend).
+    pub fn resume(&mut self) {
+        self.tx.resume();
+    }
 }
 
 fn reconfigure(info: &Info, kernel_clock: Hertz, config: &Config) -> Result<(), ConfigError> {
@@ -2010,6 +2106,7 @@ struct State {
     rx_waker: AtomicWaker,
     tx_waker: AtomicWaker,
     tx_rx_refcount: AtomicU8,
+    suspended: AtomicBool,
 }
 
 impl State {
@@ -2018,6 +2115,7 @@ impl State {
             rx_waker: AtomicWaker::new(),
             tx_waker: AtomicWaker::new(),
             tx_rx_refcount: AtomicU8::new(0),
+            suspended: AtomicBool::new(false),
         }
     }
 }
@@ -379,6 +379,10 @@ impl ReadReady for RingBufferedUartRx<'_> {
     fn read_ready(&mut self) -> Result<bool, Self::Error> {
         let len = self.ring_buf.len().map_err(|e| match e {
             crate::dma::ringbuffer::Error::Overrun => Self::Error::Overrun,
+            crate::dma::ringbuffer::Error::Transfer => {
+                error!("Ringbuffer error: DMA transfer error");
+                Self::Error::Overrun
+            }
             crate::dma::ringbuffer::Error::DmaUnsynced => {
                 error!(
                     "Ringbuffer error: DmaUNsynced, driver implementation is 
@@ -8,6 +8,27 @@ pub use _version::*;
 use crate::interrupt::typelevel::Interrupt;
 use crate::rcc;
 
+/// USB clock error.
+#[cfg(any(stm32l4, stm32wb))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ClockError {
+    /// The clock selected as 48 MHz source is not running.
+    NotRunning,
+    /// The selected clock is running, but not at 48 MHz (within the 0.25% tolerance of the spec).
+    WrongFrequency(crate::time::Hertz),
+}
+
+/// Check that the clock selected for USB runs at 48 MHz, without panicking.
+#[cfg(any(stm32l4, stm32wb))]
+fn check_clock<T: Instance>() -> Result<(), ClockError> {
+    match T::try_frequency() {
+        None => Err(ClockError::NotRunning),
+        Some(freq) if freq.0.abs_diff(48_000_000) > 120_000 => Err(ClockError::WrongFrequency(freq)),
+        Some(_) => Ok(()),
+    }
+}
+
 /// clock, power initialization stuff that's common for USB and OTG.
 fn common_init<T: Instance>() {
     // Check the USB clock is enabled and running at exactly 48 MHz.
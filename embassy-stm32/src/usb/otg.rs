@@ -89,6 +89,23 @@ impl<'d, T: Instance> Driver<'d, T> {
         }
     }
 
+    /// Initializes USB OTG peripheral with internal Full-Speed PHY, checking the 48 MHz clock first.
+    ///
+    /// Unlike [`Self::new_fs`], which panics once the bus is enabled, this returns an error if the
+    /// clock selected with `config.rcc.mux.clk48sel` is not running at 48 MHz.
+    #[cfg(stm32l4)]
+    pub fn try_new_fs(
+        peri: Peri<'d, T>,
+        irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        dp: Peri<'d, impl DpPin<T>>,
+        dm: Peri<'d, impl DmPin<T>>,
+        ep_out_buffer: &'d mut [u8],
+        config: Config,
+    ) -> Result<Self, super::ClockError> {
+        super::check_clock::<T>()?;
+        Ok(Self::new_fs(peri, irq, dp, dm, ep_out_buffer, config))
+    }
+
     /// Initializes USB OTG peripheral with internal High-Speed PHY.
     ///
     /// # Arguments
@@ -5,6 +5,8 @@ use core::marker::PhantomData;
 use core::sync::atomic::{AtomicBool, Ordering};
 use core::task::Poll;
 
+#[cfg(feature = "exti")]
+use embassy_futures::select::{select, Either};
 use embassy_hal_internal::PeripheralType;
 use embassy_sync::waitqueue::AtomicWaker;
 use embassy_usb_driver as driver;
@@ -12,6 +14,8 @@ use embassy_usb_driver::{
     Direction, EndpointAddress, EndpointAllocError, EndpointError, EndpointInfo, EndpointType, Event, Unsupported,
 };
 
+#[cfg(feature = "exti")]
+use crate::exti::ExtiInput;
 use crate::pac::usb::regs;
 use crate::pac::usb::vals::{EpType, Stat};
 use crate::pac::USBRAM;
@@ -279,11 +283,26 @@ struct EndpointData {
     used_out: bool,
 }
 
+/// Type of USB port the device is plugged into, as found by battery charger detection.
+#[cfg(all(feature = "time", any(usb_v3, usb_v4)))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChargerType {
+    /// Standard downstream port, up to 500 mA after enumeration.
+    Sdp,
+    /// Charging downstream port, up to 1.5 A with data.
+    Cdp,
+    /// Dedicated charging port, up to 1.5 A without data.
+    Dcp,
+}
+
 /// USB driver.
 pub struct Driver<'d, T: Instance> {
     phantom: PhantomData<&'d mut T>,
     alloc: [EndpointData; EP_COUNT],
     ep_mem_free: u16, // first free address in EP mem, in bytes.
+    #[cfg(feature = "exti")]
+    vbus: Option<ExtiInput<'d>>,
 }
 
 impl<'d, T: Instance> Driver<'d, T> {
@@ -346,9 +365,93 @@ impl<'d, T: Instance> Driver<'d, T> {
                 used_out: false,
             }; EP_COUNT],
             ep_mem_free: EP_COUNT as u16 * 8, // for each EP, 4 regs, so 8 bytes
+            #[cfg(feature = "exti")]
+            vbus: None,
         }
     }
 
+    /// Create a new USB driver for a self-powered device, with VBUS sensing.
+    ///
+    /// `vbus` must read high while the device is plugged into a host. The D+ pull-up is only
+    /// enabled while VBUS is present, and the bus reports power detected/removed events as VBUS
+    /// comes and goes.
+    #[cfg(feature = "exti")]
+    pub fn new_with_vbus(
+        _usb: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        dp: Peri<'d, impl DpPin<T>>,
+        dm: Peri<'d, impl DmPin<T>>,
+        vbus: ExtiInput<'d>,
+    ) -> Self {
+        let mut this = Self::new(_usb, _irq, dp, dm);
+        this.vbus = Some(vbus);
+        this
+    }
+
+    /// Detect the type of port the device is plugged into (USB battery charging 1.2).
+    ///
+    /// This runs the data contact detection, primary and secondary detection steps of the
+    /// battery charger detection (BCD) block. It must be called before the driver is started,
+    /// while the D+ pull-up is still disabled. If no data contact is detected within 1 second,
+    /// detection proceeds anyway, as some chargers don't connect the data lines.
+    #[cfg(all(feature = "time", any(usb_v3, usb_v4)))]
+    pub async fn detect_charger(&mut self) -> ChargerType {
+        use embassy_time::{Duration, Instant, Timer};
+
+        let regs = T::regs();
+        regs.bcdr().write(|w| w.set_bcden(true));
+
+        // Data contact detection.
+        regs.bcdr().modify(|w| w.set_dcden(true));
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while !regs.bcdr().read().dcdet() && Instant::now() < deadline {
+            Timer::after_millis(10).await;
+        }
+        regs.bcdr().modify(|w| w.set_dcden(false));
+
+        // Primary detection: tells standard ports apart from charging ports.
+        regs.bcdr().modify(|w| w.set_pden(true));
+        Timer::after_millis(50).await;
+        let charging_port = regs.bcdr().read().pdet();
+        regs.bcdr().modify(|w| w.set_pden(false));
+
+        let charger = if !charging_port {
+            ChargerType::Sdp
+        } else {
+            // Secondary detection: tells dedicated chargers apart from charging downstream ports.
+            regs.bcdr().modify(|w| w.set_sden(true));
+            Timer::after_millis(50).await;
+            let dedicated = regs.bcdr().read().sdet();
+            regs.bcdr().modify(|w| w.set_sden(false));
+
+            if dedicated {
+                ChargerType::Dcp
+            } else {
+                ChargerType::Cdp
+            }
+        };
+
+        regs.bcdr().write(|w| w.set_bcden(false));
+        trace!("charger detected: {:?}", charger);
+        charger
+    }
+
+    /// Create a new USB driver, checking the 48 MHz clock first.
+    ///
+    /// Unlike [`Self::new`], which panics, this returns an error if the clock selected with
+    /// `config.rcc.mux.clk48sel` is not running at 48 MHz. With HSI48 as source, enable
+    /// `sync_from_usb` in its config so CRS keeps it within the USB tolerance.
+    #[cfg(any(stm32l4, stm32wb))]
+    pub fn try_new(
+        _usb: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        dp: Peri<'d, impl DpPin<T>>,
+        dm: Peri<'d, impl DmPin<T>>,
+    ) -> Result<Self, super::ClockError> {
+        super::check_clock::<T>()?;
+        Ok(Self::new(_usb, _irq, dp, dm))
+    }
+
     fn alloc_ep_mem(&mut self, len: u16) -> u16 {
         assert!(len as usize % USBRAM_ALIGN == 0);
         let addr = self.ep_mem_free;
@@ -515,12 +618,6 @@ impl<'d, T: Instance> driver::Driver<'d> for Driver<'d, T> {
             w.set_ctrm(true);
         });
 
-        #[cfg(any(usb_v3, usb_v4))]
-        regs.bcdr().write(|w| w.set_dppu(true));
-
-        #[cfg(stm32l1)]
-        crate::pac::SYSCFG.pmc().modify(|w| w.set_usb_pu(true));
-
         trace!("enabled");
 
         let mut ep_types = [EpType::BULK; EP_COUNT - 1];
@@ -533,6 +630,10 @@ impl<'d, T: Instance> driver::Driver<'d> for Driver<'d, T> {
                 phantom: PhantomData,
                 ep_types,
                 inited: false,
+                #[cfg(feature = "exti")]
+                vbus: self.vbus.take(),
+                #[cfg(feature = "exti")]
+                powered: false,
             },
             ControlPipe {
                 _phantom: PhantomData,
@@ -549,66 +650,44 @@ pub struct Bus<'d, T: Instance> {
     phantom: PhantomData<&'d mut T>,
     ep_types: [EpType; EP_COUNT - 1],
     inited: bool,
+    #[cfg(feature = "exti")]
+    vbus: Option<ExtiInput<'d>>,
+    #[cfg(feature = "exti")]
+    powered: bool,
 }
 
 impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
     async fn poll(&mut self) -> Event {
-        poll_fn(move |cx| {
-            BUS_WAKER.register(cx.waker());
-
-            // TODO: implement VBUS detection.
-            if !self.inited {
-                self.inited = true;
-                return Poll::Ready(Event::PowerDetected);
-            }
-
-            let regs = T::regs();
-
-            if IRQ_RESUME.load(Ordering::Acquire) {
-                IRQ_RESUME.store(false, Ordering::Relaxed);
-                return Poll::Ready(Event::Resume);
-            }
-
-            if IRQ_RESET.load(Ordering::Acquire) {
-                IRQ_RESET.store(false, Ordering::Relaxed);
-
-                trace!("RESET");
-                regs.daddr().write(|w| {
-                    w.set_ef(true);
-                    w.set_add(0);
-                });
-
-                regs.epr(0).write(|w| {
-                    w.set_ep_type(EpType::CONTROL);
-                    w.set_stat_rx(Stat::NAK);
-                    w.set_stat_tx(Stat::NAK);
-                });
-
-                for i in 1..EP_COUNT {
-                    regs.epr(i).write(|w| {
-                        w.set_ea(i as _);
-                        w.set_ep_type(self.ep_types[i - 1]);
-                    })
+        #[cfg(feature = "exti")]
+        if let Some(vbus) = &mut self.vbus {
+            loop {
+                let present = vbus.is_high();
+                if present != self.powered {
+                    self.powered = present;
+                    return match present {
+                        true => Event::PowerDetected,
+                        false => Event::PowerRemoved,
+                    };
                 }
 
-                for w in &EP_IN_WAKERS {
-                    w.wake()
-                }
-                for w in &EP_OUT_WAKERS {
-                    w.wake()
+                if !present {
+                    vbus.wait_for_high().await;
+                    continue;
                 }
 
-                return Poll::Ready(Event::Reset);
+                match select(bus_event::<T>(&self.ep_types), vbus.wait_for_low()).await {
+                    Either::First(event) => return event,
+                    Either::Second(()) => continue,
+                }
             }
+        }
 
-            if IRQ_SUSPEND.load(Ordering::Acquire) {
-                IRQ_SUSPEND.store(false, Ordering::Relaxed);
-                return Poll::Ready(Event::Suspend);
-            }
+        if !self.inited {
+            self.inited = true;
+            return Event::PowerDetected;
+        }
 
-            Poll::Pending
-        })
-        .await
+        bus_event::<T>(&self.ep_types).await
     }
 
     fn endpoint_set_stalled(&mut self, ep_addr: EndpointAddress, stalled: bool) {
@@ -710,14 +789,98 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
         trace!("EPR after: {:04x}", epr.read().0);
     }
 
-    async fn enable(&mut self) {}
-    async fn disable(&mut self) {}
+    async fn enable(&mut self) {
+        #[cfg(any(usb_v3, usb_v4))]
+        T::regs().bcdr().write(|w| w.set_dppu(true));
+
+        #[cfg(stm32l1)]
+        crate::pac::SYSCFG.pmc().modify(|w| w.set_usb_pu(true));
+    }
+
+    async fn disable(&mut self) {
+        #[cfg(any(usb_v3, usb_v4))]
+        T::regs().bcdr().write(|w| w.set_dppu(false));
+
+        #[cfg(stm32l1)]
+        crate::pac::SYSCFG.pmc().modify(|w| w.set_usb_pu(false));
+    }
 
     async fn remote_wakeup(&mut self) -> Result<(), Unsupported> {
-        Err(Unsupported)
+        let regs = T::regs();
+
+        // Leave low-power mode, then signal resume to the host.
+        regs.cntr().modify(|w| {
+            w.set_fsusp(false);
+            w.set_lpmode(false);
+        });
+        regs.cntr().modify(|w| w.set_resume(true));
+
+        // Resume signaling must last between 1 and 15 ms.
+        #[cfg(feature = "time")]
+        embassy_time::Timer::after_millis(2).await;
+        #[cfg(not(feature = "time"))]
+        cortex_m::asm::delay(unsafe { crate::rcc::get_freqs() }.sys.to_hertz().unwrap().0 / 500);
+
+        regs.cntr().modify(|w| w.set_resume(false));
+
+        Ok(())
     }
 }
 
+/// Wait for the next bus event signaled by the interrupt handler.
+async fn bus_event<T: Instance>(ep_types: &[EpType; EP_COUNT - 1]) -> Event {
+    poll_fn(move |cx| {
+        BUS_WAKER.register(cx.waker());
+
+        let regs = T::regs();
+
+        if IRQ_RESUME.load(Ordering::Acquire) {
+            IRQ_RESUME.store(false, Ordering::Relaxed);
+            return Poll::Ready(Event::Resume);
+        }
+
+        if IRQ_RESET.load(Ordering::Acquire) {
+            IRQ_RESET.store(false, Ordering::Relaxed);
+
+            trace!("RESET");
+            regs.daddr().write(|w| {
+                w.set_ef(true);
+                w.set_add(0);
+            });
+
+            regs.epr(0).write(|w| {
+                w.set_ep_type(EpType::CONTROL);
+                w.set_stat_rx(Stat::NAK);
+                w.set_stat_tx(Stat::NAK);
+            });
+
+            for i in 1..EP_COUNT {
+                regs.epr(i).write(|w| {
+                    w.set_ea(i as _);
+                    w.set_ep_type(ep_types[i - 1]);
+                })
+            }
+
+            for w in &EP_IN_WAKERS {
+                w.wake()
+            }
+            for w in &EP_OUT_WAKERS {
+                w.wake()
+            }
+
+            return Poll::Ready(Event::Reset);
+        }
+
+        if IRQ_SUSPEND.load(Ordering::Acquire) {
+            IRQ_SUSPEND.store(false, Ordering::Relaxed);
+            return Poll::Ready(Event::Suspend);
+        }
+
+        Poll::Pending
+    })
+    .await
+}
+
 trait Dir {
     fn dir() -> Direction;
 }
@@ -0,0 +1,79 @@
+//! Vector table relocation
+//!
+//! Applications started by a bootloader at an offset in flash must point VTOR to their own vector
+//! table with [`relocate`], unless the bootloader already did.
+//!
+//! [`RamVectorTable`] copies the vector table to RAM. Interrupts then keep working while the flash
+//! bank holding the table is being erased or programmed, and handlers can be swapped at runtime.
+//!
+//! ```rust,ignore
+//! static TABLE: StaticCell<RamVectorTable<{ 16 + 64 }>> = StaticCell::new();
+//!
+//! let table = TABLE.init(RamVectorTable::new()).install();
+//! table.set_handler(Interrupt::TIM2, tim2_handler);
+//! ```
+use cortex_m::interrupt::InterruptNumber;
+use cortex_m::peripheral::SCB;
+
+/// Number of Cortex-M exception vectors before the first interrupt vector.
+const EXCEPTIONS: usize = 16;
+
+/// Get the address of the active vector table.
+pub fn current() -> u32 {
+    unsafe { (*SCB::PTR).vtor.read() }
+}
+
+/// Point VTOR to the vector table at `address`.
+///
+/// # Safety
+///
+/// `address` must hold a valid vector table for this chip, aligned to its size rounded up to a
+/// power of two (and at least 128 bytes). The table must stay valid while it is active.
+pub unsafe fn relocate(address: u32) {
+    cortex_m::asm::dsb();
+    (*SCB::PTR).vtor.write(address);
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+}
+
+/// Vector table in RAM, with `N` entries: the initial stack pointer, 15 exception vectors, then the
+/// interrupt vectors.
+///
+/// `N` must cover all interrupts of the chip.
+#[repr(C, align(1024))]
+pub struct RamVectorTable<const N: usize> {
+    vectors: [u32; N],
+}
+
+impl<const N: usize> RamVectorTable<N> {
+    /// Create an empty vector table. It must be installed before use.
+    pub const fn new() -> Self {
+        assert!(N > EXCEPTIONS && N <= 256);
+        Self { vectors: [0; N] }
+    }
+
+    /// Copy the active vector table to RAM, and make the copy active.
+    pub fn install(&'static mut self) -> &'static mut Self {
+        let source = current() as *const u32;
+        critical_section::with(|_| unsafe {
+            for (i, vector) in self.vectors.iter_mut().enumerate() {
+                *vector = source.add(i).read_volatile();
+            }
+            relocate(self.vectors.as_ptr() as u32);
+        });
+        self
+    }
+
+    /// Set the handler of interrupt `irq`.
+    pub fn set_handler(&mut self, irq: impl InterruptNumber, handler: unsafe extern "C" fn()) {
+        let index = EXCEPTIONS + irq.number() as usize;
+        unsafe { core::ptr::addr_of_mut!(self.vectors[index]).write_volatile(handler as usize as u32) };
+        cortex_m::asm::dsb();
+    }
+}
+
+impl<const N: usize> Default for RamVectorTable<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
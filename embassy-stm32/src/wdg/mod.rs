@@ -7,6 +7,14 @@ use stm32_metapac::iwdg::vals::{Key, Pr};
 use crate::rcc::LSI_FREQ;
 use crate::Peri;
 
+#[cfg(all(feature = "time", rtc, not(rtc_v1)))]
+mod task;
+#[cfg(wwdg)]
+pub mod wwdg;
+
+#[cfg(all(feature = "time", rtc, not(rtc_v1)))]
+pub use task::*;
+
 /// Independent watchdog (IWDG) driver.
 pub struct IndependentWatchdog<'d, T: Instance> {
     wdg: PhantomData<&'d mut T>,
@@ -0,0 +1,155 @@
+//! Task watchdog
+//!
+//! Supervises several tasks with a single IWDG. Each task registers with its own deadline and
+//! must check in before it expires. The hardware watchdog is only fed while every registered
+//! task is on time. When a task misses its deadline, its ID is stored in an RTC backup register
+//! and the watchdog is left to reset the MCU, so the culprit can be found with
+//! [`TaskWatchdog::take_culprit`] after the reset.
+//!
+//! ```rust,ignore
+//! static WATCHDOG: TaskWatchdog<4> = TaskWatchdog::new();
+//!
+//! #[embassy_executor::task]
+//! async fn worker() {
+//!     let handle = unwrap!(WATCHDOG.register(1, Duration::from_millis(500)));
+//!     loop {
+//!         do_work().await;
+//!         handle.check_in();
+//!     }
+//! }
+//!
+//! #[embassy_executor::task]
+//! async fn supervisor(wdg: IndependentWatchdog<'static, IWDG>, rtc: Rtc) {
+//!     WATCHDOG.run(wdg, Duration::from_millis(100), &rtc, 0).await
+//! }
+//! ```
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant, Timer};
+
+use super::{IndependentWatchdog, Instance};
+use crate::rtc::Rtc;
+
+/// Marker stored in the upper half of the backup register along with the culprit ID.
+const CULPRIT_MARKER: u32 = 0xDEAD_0000;
+
+/// Task ID, stored in the backup register when the task misses its deadline.
+pub type TaskId = u16;
+
+#[derive(Clone, Copy)]
+struct Slot {
+    id: TaskId,
+    timeout: Duration,
+    deadline: Instant,
+}
+
+/// Task watchdog supervising up to `N` tasks.
+pub struct TaskWatchdog<const N: usize> {
+    tasks: Mutex<CriticalSectionRawMutex, RefCell<[Option<Slot>; N]>>,
+}
+
+impl<const N: usize> TaskWatchdog<N> {
+    /// Create a new task watchdog, with no registered task.
+    pub const fn new() -> Self {
+        Self {
+            tasks: Mutex::new(RefCell::new([None; N])),
+        }
+    }
+
+    /// Register a task, which must then check in at least every `timeout`.
+    ///
+    /// The first deadline is `timeout` from now. The task is unregistered when the handle is
+    /// dropped. Returns `None` if `N` tasks are already registered.
+    pub fn register(&self, id: TaskId, timeout: Duration) -> Option<TaskHandle<'_, N>> {
+        self.tasks.lock(|tasks| {
+            let mut tasks = tasks.borrow_mut();
+            let (index, slot) = tasks.iter_mut().enumerate().find(|(_, slot)| slot.is_none())?;
+            *slot = Some(Slot {
+                id,
+                timeout,
+                deadline: Instant::now() + timeout,
+            });
+            Some(TaskHandle { watchdog: self, index })
+        })
+    }
+
+    /// Start the hardware watchdog and supervise the registered tasks, checking them every
+    /// `period`.
+    ///
+    /// `period` must be shorter than the IWDG timeout. When a task misses its deadline, its ID is
+    /// written to the RTC backup register `backup_register` and the IWDG is not fed anymore.
+    pub async fn run<T: Instance>(
+        &self,
+        mut wdg: IndependentWatchdog<'_, T>,
+        period: Duration,
+        rtc: &Rtc,
+        backup_register: usize,
+    ) -> ! {
+        wdg.unleash();
+
+        loop {
+            let now = Instant::now();
+            let culprit = self.tasks.lock(|tasks| {
+                tasks
+                    .borrow()
+                    .iter()
+                    .flatten()
+                    .find(|slot| now > slot.deadline)
+                    .map(|slot| slot.id)
+            });
+
+            match culprit {
+                None => wdg.pet(),
+                Some(id) => {
+                    warn!("task {} missed its watchdog deadline", id);
+                    rtc.write_backup_register(backup_register, CULPRIT_MARKER | id as u32);
+                    // Wait for the IWDG to reset the MCU.
+                    core::future::pending::<()>().await;
+                }
+            }
+
+            Timer::after(period).await;
+        }
+    }
+
+    /// Read and clear the ID of the task that caused the last watchdog reset, if any.
+    pub fn take_culprit(rtc: &Rtc, backup_register: usize) -> Option<TaskId> {
+        let value = rtc.read_backup_register(backup_register)?;
+        if value & 0xFFFF_0000 != CULPRIT_MARKER {
+            return None;
+        }
+        rtc.write_backup_register(backup_register, 0);
+        Some(value as TaskId)
+    }
+}
+
+impl<const N: usize> Default for TaskWatchdog<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Handle of a task registered with a [`TaskWatchdog`].
+pub struct TaskHandle<'a, const N: usize> {
+    watchdog: &'a TaskWatchdog<N>,
+    index: usize,
+}
+
+impl<'a, const N: usize> TaskHandle<'a, N> {
+    /// Check in, moving the deadline of the task to its timeout from now.
+    pub fn check_in(&self) {
+        self.watchdog.tasks.lock(|tasks| {
+            if let Some(slot) = &mut tasks.borrow_mut()[self.index] {
+                slot.deadline = Instant::now() + slot.timeout;
+            }
+        })
+    }
+}
+
+impl<'a, const N: usize> Drop for TaskHandle<'a, N> {
+    fn drop(&mut self) {
+        self.watchdog.tasks.lock(|tasks| tasks.borrow_mut()[self.index] = None)
+    }
+}
@@ -0,0 +1,201 @@
+//! Window watchdog (WWDG)
+//!
+//! The WWDG resets the MCU if it is not refreshed in time, or if it is refreshed too early (before
+//! the window opens). Just before the reset, it raises the early wakeup interrupt (EWI), exposed
+//! as [`WindowWatchdog::wait_early_wakeup`], which gives firmware a last chance to save some
+//! state.
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
+
+use embassy_hal_internal::PeripheralType;
+use embassy_sync::waitqueue::AtomicWaker;
+use stm32_metapac::wwdg::vals::Wdgtb;
+
+use crate::interrupt::typelevel::Interrupt;
+use crate::{interrupt, peripherals, rcc, Peri};
+
+static EWI_WAKER: AtomicWaker = AtomicWaker::new();
+static EWI_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Counter value at which the early wakeup interrupt fires. The reset happens on the next tick.
+const COUNTER_MIN: u8 = 0x40;
+/// Number of counter ticks from the largest counter value to the reset.
+const MAX_TICKS: u64 = 0x40;
+
+#[cfg(wwdg_v1)]
+const MAX_PSC_POWER: u8 = 3;
+#[cfg(not(wwdg_v1))]
+const MAX_PSC_POWER: u8 = 7;
+
+/// WWDG interrupt handler.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        if T::regs().sr().read().ewif() {
+            T::regs().sr().write(|w| w.set_ewif(false));
+            EWI_PENDING.store(true, Ordering::Release);
+            EWI_WAKER.wake();
+        }
+    }
+}
+
+/// Window watchdog (WWDG) driver.
+pub struct WindowWatchdog<'d, T: Instance> {
+    _peri: Peri<'d, T>,
+    counter: u8,
+}
+
+/// Number of counter ticks needed to cover `us` microseconds.
+fn ticks(freq: u32, psc_power: u8, us: u32) -> u64 {
+    (us as u64 * freq as u64).div_ceil(1_000_000 * (4096 << psc_power))
+}
+
+/// Lowest prescaler power which makes the watchdog period longer or equal to `timeout_us`.
+fn prescaler_power(freq: u32, timeout_us: u32) -> Option<u8> {
+    (0..=MAX_PSC_POWER).find(|&p| ticks(freq, p, timeout_us) <= MAX_TICKS)
+}
+
+impl<'d, T: Instance> WindowWatchdog<'d, T> {
+    /// Creates a WWDG instance which resets the MCU `timeout_us` microseconds after the last
+    /// refresh, and also when refreshed less than `window_us` microseconds after the last refresh.
+    ///
+    /// Use a `window_us` of 0 to allow refreshing at any time. A `window_us` above `timeout_us` is
+    /// clamped to it.
+    ///
+    /// [Self] has to be started with [Self::unleash()].
+    pub fn new(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        timeout_us: u32,
+        window_us: u32,
+    ) -> Self {
+        rcc::enable_and_reset::<T>();
+
+        let freq = T::frequency().0;
+        let psc_power = unwrap!(
+            prescaler_power(freq, timeout_us),
+            "WWDG timeout is too long for the APB clock"
+        );
+
+        let timeout_ticks = ticks(freq, psc_power, timeout_us).max(1) as u8;
+        let counter = COUNTER_MIN - 1 + timeout_ticks;
+
+        // The counter must be below the window value for a refresh to be accepted. A window longer
+        // than the timeout only leaves the last tick to refresh in.
+        let window_ticks = ticks(freq, psc_power, window_us).min(timeout_ticks as u64) as u8;
+        let window = if window_ticks == 0 {
+            0x7F
+        } else {
+            counter.saturating_sub(window_ticks).max(COUNTER_MIN)
+        };
+
+        EWI_PENDING.store(false, Ordering::Relaxed);
+
+        let wdg = T::regs();
+        wdg.cfr().write(|w| {
+            w.set_w(window);
+            w.set_wdgtb(Wdgtb::from_bits(psc_power));
+            w.set_ewi(true);
+        });
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        trace!(
+            "Window watchdog configured with {} ticks timeout, {} ticks window (WDGTB={}, T={}, W={})",
+            timeout_ticks,
+            window_ticks,
+            psc_power,
+            counter,
+            window
+        );
+
+        Self { _peri: peri, counter }
+    }
+
+    /// Unleash (start) the watchdog.
+    ///
+    /// Once started, the watchdog can only be stopped by a reset.
+    pub fn unleash(&mut self) {
+        self.pet();
+    }
+
+    /// Pet (reload, refresh) the watchdog.
+    ///
+    /// Petting before the window opens resets the MCU.
+    pub fn pet(&mut self) {
+        T::regs().cr().write(|w| {
+            w.set_t(self.counter);
+            w.set_wdga(true);
+        });
+    }
+
+    /// Wait for the early wakeup interrupt, which fires one counter tick before the reset.
+    ///
+    /// This is the last chance to log state before the reset. The watchdog can also still be
+    /// petted at that point to prevent the reset.
+    pub async fn wait_early_wakeup(&mut self) {
+        poll_fn(|cx| {
+            EWI_WAKER.register(cx.waker());
+            if EWI_PENDING.swap(false, Ordering::Acquire) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+trait SealedInstance {
+    fn regs() -> crate::pac::wwdg::Wwdg;
+}
+
+/// WWDG instance trait.
+#[allow(private_bounds)]
+pub trait Instance: SealedInstance + PeripheralType + rcc::RccPeripheral + 'static {
+    /// Interrupt for this WWDG instance.
+    type Interrupt: interrupt::typelevel::Interrupt;
+}
+
+foreach_interrupt!(
+    ($inst:ident, wwdg, WWDG, GLOBAL, $irq:ident) => {
+        impl SealedInstance for peripherals::$inst {
+            fn regs() -> crate::pac::wwdg::Wwdg {
+                crate::pac::$inst
+            }
+        }
+
+        impl Instance for peripherals::$inst {
+            type Interrupt = crate::interrupt::typelevel::$irq;
+        }
+    };
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_compute_ticks() {
+        // One tick is 4096 / 80 MHz = 51.2 µs without prescaler.
+        assert_eq!(0, ticks(80_000_000, 0, 0));
+        assert_eq!(1, ticks(80_000_000, 0, 1));
+        assert_eq!(20, ticks(80_000_000, 0, 1_000));
+        assert_eq!(64, ticks(80_000_000, 0, 3_276));
+        assert_eq!(49, ticks(80_000_000, 2, 10_000));
+    }
+
+    #[test]
+    fn can_compute_prescaler_power() {
+        assert_eq!(Some(0), prescaler_power(80_000_000, 3_276));
+        assert_eq!(Some(1), prescaler_power(80_000_000, 3_300));
+        assert_eq!(Some(2), prescaler_power(80_000_000, 10_000));
+        assert_eq!(None, prescaler_power(80_000_000, 10_000_000));
+    }
+}
@@ -0,0 +1,67 @@
+// required-features: pka
+#![no_std]
+#![no_main]
+
+#[path = "../common.rs"]
+mod common;
+use common::*;
+use embassy_executor::Spawner;
+use embassy_stm32::pka::{Pka, Point};
+use embassy_stm32::{bind_interrupts, peripherals, pka};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+   PKA => pka::InterruptHandler<peripherals::PKA>;
+});
+
+/// P-256 base point G.
+const G: Point = Point {
+    x: [
+        0x6B, 0x17, 0xD1, 0xF2, 0xE1, 0x2C, 0x42, 0x47, 0xF8, 0xBC, 0xE6, 0xE5, 0x63, 0xA4, 0x40, 0xF2, 0x77, 0x03,
+        0x7D, 0x81, 0x2D, 0xEB, 0x33, 0xA0, 0xF4, 0xA1, 0x39, 0x45, 0xD8, 0x98, 0xC2, 0x96,
+    ],
+    y: [
+        0x4F, 0xE3, 0x42, 0xE2, 0xFE, 0x1A, 0x7F, 0x9B, 0x8E, 0xE7, 0xEB, 0x4A, 0x7C, 0x0F, 0x9E, 0x16, 0x2B, 0xCE,
+        0x33, 0x57, 0x6B, 0x31, 0x5E, 0xCE, 0xCB, 0xB6, 0x40, 0x68, 0x37, 0xBF, 0x51, 0xF5,
+    ],
+};
+
+/// 2G.
+const G2: Point = Point {
+    x: [
+        0x7C, 0xF2, 0x7B, 0x18, 0x8D, 0x03, 0x4F, 0x7E, 0x8A, 0x52, 0x38, 0x03, 0x04, 0xB5, 0x1A, 0xC3, 0xC0, 0x89,
+        0x69, 0xE2, 0x77, 0xF2, 0x1B, 0x35, 0xA6, 0x0B, 0x48, 0xFC, 0x47, 0x66, 0x99, 0x78,
+    ],
+    y: [
+        0x07, 0x77, 0x55, 0x10, 0xDB, 0x8E, 0xD0, 0x40, 0x29, 0x3D, 0x9A, 0xC6, 0x9F, 0x74, 0x30, 0xDB, 0xBA, 0x7D,
+        0xAD, 0xE6, 0x3C, 0xE9, 0x82, 0x29, 0x9E, 0x04, 0xB7, 0x9D, 0x22, 0x78, 0x73, 0xD1,
+    ],
+};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p: embassy_stm32::Peripherals = init();
+
+    let mut pka = Pka::new(p.PKA, Irqs);
+
+    let mut two = [0u8; 32];
+    two[31] = 2;
+
+    // Known-answer: 2 * G.
+    let public = unwrap!(pka.public_key(&two).await);
+    defmt::assert_eq!(public, G2);
+
+    // Points on the curve pass the check, others are rejected.
+    unwrap!(pka.check_point(&G).await);
+    unwrap!(pka.check_point(&G2).await);
+    let mut bad = G2;
+    bad.y[31] ^= 1;
+    defmt::assert_eq!(pka.check_point(&bad).await, Err(pka::Error::InvalidPoint));
+
+    // ECDH with G as the peer key yields the x coordinate of our public key.
+    defmt::assert_eq!(unwrap!(pka.ecdh(&two, &G).await), G2.x);
+    defmt::assert_eq!(pka.ecdh(&two, &bad).await, Err(pka::Error::InvalidPoint));
+
+    info!("Test OK");
+    cortex_m::asm::bkpt();
+}